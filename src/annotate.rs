@@ -0,0 +1,154 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Overlays describing a map, drawn into the image itself: the projection and
+//! seed, and optionally a scale bar.
+
+use canvas::{self, Canvas, GLYPH_HEIGHT};
+use cph::Projection;
+use super::pixel_scale;
+
+// Scale bars are at most this fraction of the width of the image.
+const MAX_SCALE_BAR_FRACTION: f64 = 0.25;
+
+#[derive(Clone, Copy)]
+pub struct Annotation {
+    // Radius of the planet in kilometres, needed for the scale bar.
+    pub planet_radius: Option<f64>,
+}
+
+fn projection_name(projection: Projection) -> &'static str {
+    match projection {
+        Projection::Equirectangular => "EQUIRECTANGULAR",
+        Projection::CubeXP => "CUBE +X",
+        Projection::CubeXN => "CUBE -X",
+        Projection::CubeYP => "CUBE +Y",
+        Projection::CubeYN => "CUBE -Y",
+        Projection::CubeZP => "CUBE +Z",
+        Projection::CubeZN => "CUBE -Z",
+        Projection::WebMercator => "WEB MERCATOR",
+        Projection::Planar => "PLANAR",
+        Projection::KhronosPX => "KHRONOS CUBE +X",
+        Projection::KhronosNX => "KHRONOS CUBE -X",
+        Projection::KhronosPY => "KHRONOS CUBE +Y",
+        Projection::KhronosNY => "KHRONOS CUBE -Y",
+        Projection::KhronosPZ => "KHRONOS CUBE +Z",
+        Projection::KhronosNZ => "KHRONOS CUBE -Z",
+        Projection::Custom => "CUSTOM MAPPING",
+        Projection::CubeCross => "CUBE CROSS",
+        Projection::CubeGrid => "CUBE GRID",
+        Projection::KhronosCross => "KHRONOS CUBE CROSS",
+        Projection::KhronosGrid => "KHRONOS CUBE GRID",
+        Projection::Mollweide => "MOLLWEIDE",
+    }
+}
+
+// The largest distance of the form 1, 2 or 5 times a power of ten which is
+// no more than `max`.
+fn round_distance(max: f64) -> f64 {
+    let power = 10.0f64.powf(max.log10().floor());
+    if max >= 5.0 * power {
+        5.0 * power
+    } else if max >= 2.0 * power {
+        2.0 * power
+    } else {
+        power
+    }
+}
+
+fn format_distance(metres: f64) -> String {
+    if metres >= 1000.0 {
+        format!("{} KM", metres / 1000.0)
+    } else {
+        format!("{} M", metres)
+    }
+}
+
+/// Returns a copy of the elevations with a box in the bottom left corner
+/// giving the projection and seed, and a scale bar if the planet's radius is
+/// known.  The box is drawn at -1.0 and its contents at +1.0, the two ends of
+/// the elevation range, so they contrast in every output format.
+pub fn annotate(data: &[f64],
+                width: usize,
+                height: usize,
+                seed: i32,
+                projection: Projection,
+                annotation: &Annotation)
+                -> Vec<f64> {
+    let scale = usize::max(width / 512, 1);
+    let padding = 4 * scale;
+    let line_height = (GLYPH_HEIGHT + 3) * scale;
+    let bar_thickness = 2 * scale;
+
+    let label = format!("{} SEED {}", projection_name(projection), seed);
+    let scale_bar = annotation.planet_radius
+        .and_then(|radius| pixel_scale(projection, width, radius * 1000.0))
+        .and_then(|(metres_per_pixel, place)| {
+            let max_metres = width as f64 * MAX_SCALE_BAR_FRACTION * metres_per_pixel;
+            let metres = round_distance(max_metres);
+            let length = (metres / metres_per_pixel).round() as usize;
+            if length > 0 {
+                Some((length, format!("{} {}", format_distance(metres), place)))
+            } else {
+                None
+            }
+        });
+
+    let mut content_width = canvas::text_width(&label, scale);
+    let mut content_height = GLYPH_HEIGHT * scale;
+    if let Some((length, ref bar_label)) = scale_bar {
+        content_width = usize::max(content_width, length);
+        content_width = usize::max(content_width, canvas::text_width(bar_label, scale));
+        content_height += line_height - GLYPH_HEIGHT * scale + padding + bar_thickness +
+                          padding + GLYPH_HEIGHT * scale;
+    }
+
+    let mut overlay = Canvas::new(content_width + 2 * padding,
+                                  content_height + 2 * padding,
+                                  [0, 0, 0]);
+    let white = [255, 255, 255];
+    overlay.draw_text(padding, padding, scale, white, &label);
+    if let Some((length, ref bar_label)) = scale_bar {
+        let bar_y = padding + line_height + padding;
+        overlay.fill_rect(padding, bar_y, length, bar_thickness, white);
+        // Ticks at the ends of the bar.
+        overlay.fill_rect(padding, bar_y - padding, scale, padding + bar_thickness, white);
+        overlay.fill_rect(padding + length - scale,
+                          bar_y - padding,
+                          scale,
+                          padding + bar_thickness,
+                          white);
+        overlay.draw_text(padding, bar_y + bar_thickness + padding, scale, white, bar_label);
+    }
+
+    // Copy the overlay into the bottom left corner, clipping it to the image.
+    let mut annotated = data.to_vec();
+    let x0 = padding;
+    let y0 = height.saturating_sub(overlay.height + padding);
+    for j in 0..overlay.height {
+        for i in 0..overlay.width {
+            let (x, y) = (x0 + i, y0 + j);
+            if x < width && y < height {
+                let lit = overlay.data[(j * overlay.width + i) * 3] != 0;
+                annotated[y * width + x] = if lit { 1.0 } else { -1.0 };
+            }
+        }
+    }
+
+    annotated
+}
@@ -0,0 +1,135 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Filling of closed basins on the land with lakes.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::SEA_LEVEL;
+
+// A sample waiting to be visited by the flood, ordered so that the lowest
+// water level is visited first.
+struct Cell {
+    level: f64,
+    index: usize,
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Cell) -> bool {
+        self.level == other.level
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Cell) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Cell) -> Ordering {
+        other.level.partial_cmp(&self.level).unwrap_or(Ordering::Equal)
+    }
+}
+
+// Collects the eight neighbours of sample (x, y) of an equirectangular grid.
+// The grid wraps around in longitude, and the neighbour beyond a pole is the
+// sample on the opposite side of it.
+fn neighbours(width: usize, height: usize, x: usize, y: usize, out: &mut Vec<usize>) {
+    out.clear();
+    let west = (x + width - 1) % width;
+    let east = (x + 1) % width;
+    let opposite = (x + width / 2) % width;
+
+    if y > 0 {
+        out.extend_from_slice(&[(y - 1) * width + west, (y - 1) * width + x,
+                                (y - 1) * width + east]);
+    } else {
+        out.push(opposite);
+    }
+    out.extend_from_slice(&[y * width + west, y * width + east]);
+    if y + 1 < height {
+        out.extend_from_slice(&[(y + 1) * width + west, (y + 1) * width + x,
+                                (y + 1) * width + east]);
+    } else {
+        out.push(y * width + opposite);
+    }
+}
+
+/// Finds the basins on the land of an equirectangular grid of elevations
+/// which don't drain to the sea, and turns them into lakes by setting them to
+/// sea level.  Each basin is filled up to the point where it would spill
+/// over into a neighbouring one, using a priority flood from the sea.
+///
+/// If the planet has no sea, the lowest sample is treated as the only
+/// outlet.
+pub fn fill_basins(elevations: &mut [f64], width: usize, height: usize) {
+    let mut queue = BinaryHeap::new();
+    let mut visited = vec![false; width * height];
+    let mut levels = elevations.to_vec();
+
+    for (index, &value) in elevations.iter().enumerate() {
+        if value <= SEA_LEVEL {
+            visited[index] = true;
+            queue.push(Cell {
+                level: value,
+                index: index,
+            });
+        }
+    }
+    if queue.is_empty() {
+        let lowest = (0..elevations.len())
+            .min_by(|&a, &b| {
+                elevations[a].partial_cmp(&elevations[b]).unwrap_or(Ordering::Equal)
+            });
+        if let Some(index) = lowest {
+            visited[index] = true;
+            queue.push(Cell {
+                level: elevations[index],
+                index: index,
+            });
+        }
+    }
+
+    // Every sample is reached from its lowest possible spill path, so its
+    // water level is the higher of its own elevation and the level it was
+    // reached at.
+    let mut adjacent = Vec::with_capacity(8);
+    while let Some(cell) = queue.pop() {
+        neighbours(width, height, cell.index % width, cell.index / width, &mut adjacent);
+        for &n in &adjacent {
+            if !visited[n] {
+                visited[n] = true;
+                levels[n] = f64::max(elevations[n], cell.level);
+                queue.push(Cell {
+                    level: levels[n],
+                    index: n,
+                });
+            }
+        }
+    }
+
+    for (value, &level) in elevations.iter_mut().zip(levels.iter()) {
+        if level > *value {
+            *value = SEA_LEVEL;
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Batches of renders of a range of seeds, for --seed-range.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use clap::ArgMatches;
+
+use super::{render, BatchJob};
+
+/// Renders each of `seeds` with the options in `matches`, naming each seed's
+/// files apart with a prefix of its seed.  Up to `threads` seeds are rendered
+/// at once, each in a single thread, or with fewer seeds than that the
+/// threads are shared out between them.  A line is written to stderr as each
+/// seed is finished.
+pub fn run_batch(matches: &ArgMatches<'static>, seeds: &[i32], threads: usize) {
+    let workers = usize::min(threads, seeds.len());
+    let job_threads = usize::max(threads / workers, 1);
+    let prefix = matches.value_of("prefix").unwrap_or("seed").to_string();
+    let next_seed = Arc::new(AtomicUsize::new(0));
+
+    let mut joins = Vec::new();
+    for _ in 0..workers {
+        let next_seed = next_seed.clone();
+        let seeds = seeds.to_vec();
+        let matches = matches.clone();
+        let prefix = prefix.clone();
+        joins.push(::std::thread::spawn(move || {
+            loop {
+                let index = next_seed.fetch_add(1, Ordering::SeqCst);
+                if index >= seeds.len() {
+                    break;
+                }
+                let seed = seeds[index];
+                let job = BatchJob {
+                    prefix: Box::leak(format!("{}_{}", prefix, seed).into_boxed_str()),
+                    threads: job_threads,
+                };
+
+                let start = Instant::now();
+                render(matches.clone(), seed, Some(job));
+                eprintln!("Seed {} rendered in {:.2}s, written as {}_*",
+                          seed,
+                          start.elapsed().as_secs_f64(),
+                          job.prefix);
+            }
+        }));
+    }
+
+    for join in joins {
+        join.join().unwrap();
+    }
+}
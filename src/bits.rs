@@ -0,0 +1,204 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Bit-level output and Huffman code construction shared by the hand
+//! written encoders (deflate for indexed PNGs, VP8L for WebP).
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Packs bits into bytes, least significant bit first, as used by both
+/// deflate and VP8L.
+pub struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> BitWriter {
+        BitWriter {
+            out: Vec::new(),
+            acc: 0,
+            nbits: 0,
+        }
+    }
+
+    // Writes the low `n` bits of `value`, least significant bit first.
+    pub fn write_bits(&mut self, value: u32, n: u32) {
+        self.acc |= (value as u64 & ((1u64 << n) - 1)) << self.nbits;
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    // Huffman codes are packed starting from their most significant bit.
+    pub fn write_code(&mut self, code: u32, len: u32) {
+        let mut reversed = 0;
+        for i in 0..len {
+            reversed |= ((code >> i) & 1) << (len - 1 - i);
+        }
+        self.write_bits(reversed, len);
+    }
+
+    /// Pads the final partial byte with zeros and returns the output.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// Computes Huffman code lengths for the given symbol frequencies, limited
+/// to `max_len` bits.  Symbols with a frequency of zero get a length of
+/// zero; if only one symbol is used it gets a length of 1.
+pub fn huffman_lengths(freqs: &[u32], max_len: u8) -> Vec<u8> {
+    let mut freqs: Vec<u64> = freqs.iter().map(|&f| f as u64).collect();
+    loop {
+        let lengths = unlimited_huffman_lengths(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths;
+        }
+
+        // Flatten the distribution and try again, this converges quickly
+        // and only costs a little compression in rare cases.
+        for f in freqs.iter_mut() {
+            if *f > 0 {
+                *f = (*f >> 1) | 1;
+            }
+        }
+    }
+}
+
+fn unlimited_huffman_lengths(freqs: &[u64]) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let used: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+    }
+    if used.len() <= 1 {
+        return lengths;
+    }
+
+    // Nodes 0..n are leaves, the rest are internal nodes created while
+    // building the tree.
+    let mut parent: Vec<usize> = vec![0; used.len()];
+    let mut heap = BinaryHeap::new();
+    for (node, &symbol) in used.iter().enumerate() {
+        heap.push(Reverse((freqs[symbol], node)));
+    }
+    while heap.len() > 1 {
+        let Reverse((fa, a)) = heap.pop().unwrap();
+        let Reverse((fb, b)) = heap.pop().unwrap();
+        let node = parent.len();
+        parent.push(node);
+        parent[a] = node;
+        parent[b] = node;
+        heap.push(Reverse((fa + fb, node)));
+    }
+
+    let root = parent.len() - 1;
+    for (node, &symbol) in used.iter().enumerate() {
+        let mut depth = 0;
+        let mut n = node;
+        while n != root {
+            n = parent[n];
+            depth += 1;
+        }
+        lengths[symbol] = usize::min(depth, 255) as u8;
+    }
+
+    lengths
+}
+
+/// Assigns canonical Huffman codes given code lengths, as specified by both
+/// deflate and VP8L.
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let max_len = lengths.iter().cloned().max().unwrap_or(0) as usize;
+    let mut count = vec![0u32; max_len + 1];
+    for &len in lengths {
+        if len > 0 {
+            count[len as usize] += 1;
+        }
+    }
+
+    let mut next_code = vec![0u32; max_len + 1];
+    let mut code = 0;
+    for len in 1..(max_len + 1) {
+        code = (code + count[len - 1]) << 1;
+        next_code[len] = code;
+    }
+
+    lengths.iter()
+        .map(|&len| if len > 0 {
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            code
+        } else {
+            0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The Kraft sum of the lengths, scaled by 2^max_len so that it is exact.
+    fn kraft_sum(lengths: &[u8], max_len: u8) -> u64 {
+        lengths.iter()
+            .filter(|&&len| len > 0)
+            .map(|&len| 1u64 << (max_len - len))
+            .sum()
+    }
+
+    #[test]
+    fn skewed_frequencies_are_limited() {
+        // Fibonacci frequencies give the deepest possible tree, 29 levels
+        // for 30 symbols without a limit.
+        let mut freqs = vec![1u32, 1];
+        while freqs.len() < 30 {
+            let next = freqs[freqs.len() - 1] + freqs[freqs.len() - 2];
+            freqs.push(next);
+        }
+        for &max_len in &[7, 15] {
+            let lengths = huffman_lengths(&freqs, max_len);
+            assert!(lengths.iter().all(|&len| len >= 1 && len <= max_len));
+            assert!(kraft_sum(&lengths, max_len) <= 1 << max_len);
+        }
+    }
+
+    #[test]
+    fn unused_symbols_get_no_code() {
+        let lengths = huffman_lengths(&[0, 5, 0, 1000, 1, 0], 15);
+        assert_eq!(lengths[0], 0);
+        assert_eq!(lengths[2], 0);
+        assert_eq!(lengths[5], 0);
+        assert!(kraft_sum(&lengths, 15) <= 1 << 15);
+    }
+
+    #[test]
+    fn single_symbol_gets_one_bit() {
+        let lengths = huffman_lengths(&[0, 0, 7, 0], 15);
+        assert_eq!(lengths, vec![0, 0, 1, 0]);
+        assert!(kraft_sum(&lengths, 15) <= 1 << 15);
+    }
+}
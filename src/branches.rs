@@ -0,0 +1,112 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! A diagnostic map of which terrain groups the selectors of the planet chose
+//! at each point.
+//!
+//! The continents-with-hills and continents-with-mountains selectors are
+//! re-evaluated from their control values, as is the badlands selector and
+//! the maximum which lets the badlands poke out, so the map shows the terrain
+//! that actually makes up the final elevations.  The transitions made by the
+//! edge falloff of the selectors are drawn as mixtures of the colours.
+
+use canvas::{Canvas, Rgb};
+use super::{create_layers, lat_lon_to_pos, rect_lat_lon, OutputPaths, PlanetParams, SEA_LEVEL,
+            TERRAIN_EDGE_FALLOFF};
+
+const PLAINS_COLOUR: Rgb = [80, 170, 60];
+const HILLS_COLOUR: Rgb = [220, 190, 60];
+const MOUNTAINS_COLOUR: Rgb = [235, 235, 235];
+const BADLANDS_COLOUR: Rgb = [200, 60, 40];
+
+// Brightness of the terrain under the sea relative to the land, so that the
+// coastlines can be seen.
+const SEA_BRIGHTNESS: f64 = 0.5;
+
+// The weight a libnoise select module gives to its second source for the
+// given control value, with an upper bound high enough never to be reached.
+// Within `falloff` of the lower bound the sources are blended by an S-curve.
+fn select_weight(control: f64, lower_bound: f64, falloff: f64) -> f64 {
+    if control < lower_bound - falloff {
+        0.0
+    } else if control < lower_bound + falloff {
+        let t = (control - (lower_bound - falloff)) / (2.0 * falloff);
+        t * t * (3.0 - 2.0 * t)
+    } else {
+        1.0
+    }
+}
+
+/// Outputs "terrain_branches.png", an equirectangular map coloured by the
+/// share of the plains, hills, mountains and badlands in the terrain at each
+/// point.  `elevations` is the map produced by `generate_rect`, used to
+/// darken the sea.
+pub fn output_terrain_branches(seed: i32,
+                               params: &PlanetParams,
+                               elevations: &[f64],
+                               width: usize,
+                               height: usize,
+                               paths: OutputPaths) {
+    let layers = create_layers(seed, params);
+    let falloff = TERRAIN_EDGE_FALLOFF * params.terrain_blend;
+
+    let mut canvas = Canvas::new(width, height, [0, 0, 0]);
+    for y in 0..height {
+        for x in 0..width {
+            let (lat, lon) = rect_lat_lon(width, height, x, y);
+            let (px, py, pz) = lat_lon_to_pos(lat, lon);
+
+            let terrain_type = layers.terrain_type_def.get_value(px, py, pz);
+            let hills = select_weight(terrain_type, 1.0 - params.hills_amount, falloff);
+            let mountains = select_weight(terrain_type, 1.0 - params.mountains_amount, falloff);
+
+            // The badlands only count where the maximum took the badlands
+            // selector over the continents-with-mountains subgroup.
+            let with_mountains = layers.continents_with_mountains.get_value(px, py, pz);
+            let with_badlands = layers.continents_with_badlands.get_value(px, py, pz);
+            let badlands = if with_badlands > with_mountains {
+                let positions = layers.badlands_positions.get_value(px, py, pz);
+                select_weight(positions, 1.0 - params.badlands_amount, falloff)
+            } else {
+                0.0
+            };
+
+            let weights = [(1.0 - badlands) * (1.0 - mountains) * (1.0 - hills),
+                           (1.0 - badlands) * (1.0 - mountains) * hills,
+                           (1.0 - badlands) * mountains,
+                           badlands];
+            let colours = [PLAINS_COLOUR, HILLS_COLOUR, MOUNTAINS_COLOUR, BADLANDS_COLOUR];
+            let brightness = if elevations[y * width + x] > SEA_LEVEL {
+                1.0
+            } else {
+                SEA_BRIGHTNESS
+            };
+
+            let mut colour = [0u8; 3];
+            for c in 0..3 {
+                let mixed: f64 = weights.iter()
+                    .zip(colours.iter())
+                    .map(|(&weight, colour)| weight * colour[c] as f64)
+                    .sum();
+                colour[c] = (mixed * brightness).round() as u8;
+            }
+            canvas.set_pixel(x, y, colour);
+        }
+    }
+
+    canvas.write_png(&paths.path("terrain_branches.png"));
+}
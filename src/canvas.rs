@@ -0,0 +1,186 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! A minimal RGB drawing surface used for composite images (insets, labels,
+//! legends, etc.)
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::ColorType;
+use image::png::PNGEncoder;
+
+// Width and height of a glyph in the built-in font, in font pixels.
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+// Horizontal advance of a glyph, including a one pixel gap.
+const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+pub type Rgb = [u8; 3];
+
+/// An 8-bit per channel RGB image, stored top row first.
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: Rgb) -> Canvas {
+        let mut data = Vec::with_capacity(width * height * 3);
+        for _ in 0..(width * height) {
+            data.extend_from_slice(&background);
+        }
+        Canvas {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, colour: Rgb) {
+        if x < self.width && y < self.height {
+            let idx = (y * self.width + x) * 3;
+            self.data[idx..idx + 3].copy_from_slice(&colour);
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, colour: Rgb) {
+        for j in y..(y + height) {
+            for i in x..(x + width) {
+                self.set_pixel(i, j, colour);
+            }
+        }
+    }
+
+    /// Copies `other` onto this canvas with its top-left corner at `(x, y)`,
+    /// clipping anything that falls outside.
+    pub fn blit(&mut self, other: &Canvas, x: usize, y: usize) {
+        for j in 0..other.height {
+            for i in 0..other.width {
+                let idx = (j * other.width + i) * 3;
+                self.set_pixel(x + i, y + j,
+                               [other.data[idx], other.data[idx + 1], other.data[idx + 2]]);
+            }
+        }
+    }
+
+    /// Draws a one pixel wide line between two points, which may lie partly
+    /// or wholly outside the canvas.
+    pub fn draw_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, colour: Rgb) {
+        let steps = f64::max(f64::abs(x1 - x0), f64::abs(y1 - y0)).ceil() as usize;
+        for i in 0..(steps + 1) {
+            let t = if steps > 0 { i as f64 / steps as f64 } else { 0.0 };
+            let x = (x0 + (x1 - x0) * t).round();
+            let y = (y0 + (y1 - y0) * t).round();
+            if x >= 0.0 && y >= 0.0 {
+                self.set_pixel(x as usize, y as usize, colour);
+            }
+        }
+    }
+
+    /// Draws `text` using the built-in 5x7 font, each font pixel being drawn
+    /// as a `scale` by `scale` block.  Lowercase letters are drawn as
+    /// uppercase, unknown characters are drawn as blanks.
+    pub fn draw_text(&mut self, x: usize, y: usize, scale: usize, colour: Rgb, text: &str) {
+        let mut pen_x = x;
+        for c in text.chars() {
+            let glyph = glyph(c);
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> col) != 0 {
+                        self.fill_rect(pen_x + col * scale, y + row * scale, scale, scale, colour);
+                    }
+                }
+            }
+            pen_x += GLYPH_ADVANCE * scale;
+        }
+    }
+
+    pub fn write_png(&self, filename: &str) {
+        let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+        let writer = BufWriter::new(file);
+        let encoder = PNGEncoder::new(writer);
+
+        encoder.encode(&self.data, self.width as u32, self.height as u32, ColorType::RGB(8))
+            .expect("Failed to encode image data");
+    }
+}
+
+/// Returns the width in pixels of `text` drawn with `Canvas::draw_text`.
+pub fn text_width(text: &str, scale: usize) -> usize {
+    let len = text.chars().count();
+    if len == 0 {
+        0
+    } else {
+        (len * GLYPH_ADVANCE - 1) * scale
+    }
+}
+
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x00; GLYPH_HEIGHT],
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Planet files, which set options as the command line does, so that a
+//! planet and how it is output can be described in a file and shared.
+//!
+//! A planet file is TOML, setting the seed, the output type, format, size and
+//! projection, and the terrain parameters.  Each key is the long name of the
+//! option it sets, and the options which take no value are set with `true`.
+//! Unknown keys are an error, so that a typo isn't silently ignored.  For
+//! example:
+//!
+//! ```text
+//! # A large, dry planet.
+//! seed = 42
+//! type = "rect"
+//! format = "colour24"
+//! width = 4096
+//! sea-level = -0.2
+//! relief-only = true
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use toml;
+
+// A seed, given as a number or as a name to be hashed, see `parse_seed`.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Seed {
+    Number(i64),
+    Name(String),
+}
+
+// The options a planet file can set.  Those left out keep their defaults, or
+// the values given on the command line.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PlanetFile {
+    seed: Option<Seed>,
+    #[serde(rename = "type")]
+    output_type: Option<String>,
+    format: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    projection: Option<String>,
+    sea_level: Option<f64>,
+    shelf_level: Option<f64>,
+    mountains: Option<f64>,
+    hills: Option<f64>,
+    badlands: Option<f64>,
+    continent_frequency: Option<f64>,
+    continent_persistence: Option<f64>,
+    hills_persistence: Option<f64>,
+    plains_persistence: Option<f64>,
+    peak_variation: Option<f64>,
+    mountain_scale_frequency: Option<f64>,
+    hill_scale_frequency: Option<f64>,
+    turbulence_detail: Option<f64>,
+    turbulence_power: Option<f64>,
+    terrain_blend: Option<f64>,
+    river_orders: Option<u32>,
+    quality: Option<String>,
+    detail: Option<String>,
+    dune_wind_direction: Option<f64>,
+    relief_only: Option<bool>,
+    debug_group: Option<String>,
+}
+
+/// Reads the options set by a planet file, as command line arguments.  The
+/// `=` form keeps negative values from being taken for options.  Options set
+/// to `false` are left out, as if they weren't given.
+pub fn read_planet_file(filename: &str) -> io::Result<Vec<String>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let mut contents = String::new();
+    File::open(Path::new(filename))?.read_to_string(&mut contents)?;
+    let file: PlanetFile = toml::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+
+    // The options are read back out of the file's fields by name, which
+    // leaves out those which weren't set.
+    let table = match toml::Value::try_from(&file) {
+        Ok(toml::Value::Table(table)) => table,
+        Ok(_) => unreachable!(),
+        Err(err) => return Err(invalid(err.to_string())),
+    };
+    let mut args = Vec::new();
+    for (name, value) in table {
+        match value {
+            toml::Value::Boolean(true) => args.push(format!("--{}", name)),
+            toml::Value::Boolean(false) => {}
+            toml::Value::String(value) => args.push(format!("--{}={}", name, value)),
+            value => args.push(format!("--{}={}", name, value)),
+        }
+    }
+    Ok(args)
+}
@@ -0,0 +1,117 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Contact sheets: thumbnails of many seeds tiled into one labelled image,
+//! for picking out planets worth rendering in full.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use canvas::{self, Canvas, Rgb, GLYPH_HEIGHT};
+use palette::hypsometric_colour;
+use super::{create_generator, generate_rect, OutputPaths, PlanetParams};
+
+// Width of each thumbnail, which is an equirectangular map half as tall.
+const THUMBNAIL_WIDTH: usize = 192;
+const MARGIN: usize = 8;
+const TEXT_SCALE: usize = 2;
+
+const BACKGROUND: Rgb = [24, 24, 32];
+const TEXT: Rgb = [230, 230, 230];
+
+fn render_thumbnail(seed: i32, params: &PlanetParams) -> Canvas {
+    let width = THUMBNAIL_WIDTH;
+    let height = width / 2;
+    let generator = create_generator(seed, params);
+    let elevations = generate_rect(&*generator, width, height);
+
+    let mut thumbnail = Canvas::new(width, height, BACKGROUND);
+    for y in 0..height {
+        for x in 0..width {
+            thumbnail.set_pixel(x, y, hypsometric_colour(elevations[y * width + x]));
+        }
+    }
+    thumbnail
+}
+
+// The space given to each thumbnail, with its label and the margins after
+// it.
+const CELL_WIDTH: usize = THUMBNAIL_WIDTH + MARGIN;
+const CELL_HEIGHT: usize = THUMBNAIL_WIDTH / 2 + MARGIN / 2 + GLYPH_HEIGHT * TEXT_SCALE + MARGIN;
+
+/// The width and height of the contact sheet of `seeds` thumbnails in rows
+/// of `columns`.
+pub fn sheet_size(seeds: usize, columns: usize) -> (usize, usize) {
+    let columns = usize::min(columns, seeds);
+    let rows = (seeds + columns - 1) / columns;
+    (columns * CELL_WIDTH + MARGIN, rows * CELL_HEIGHT + MARGIN)
+}
+
+/// Outputs "contact_sheet.png", an elevation tinted thumbnail of the planet
+/// for each of `seeds` laid out in rows of `columns`, with its seed written
+/// underneath.
+pub fn output_contact_sheet(seeds: &[i32],
+                            params: &PlanetParams,
+                            columns: usize,
+                            paths: OutputPaths) {
+    let next_seed = Arc::new(AtomicUsize::new(0));
+    let thread_count = ::std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+
+    let mut joins = Vec::new();
+    for _ in 0..thread_count {
+        let next_seed = next_seed.clone();
+        let seeds = seeds.to_vec();
+        let params = *params;
+        joins.push(::std::thread::spawn(move || {
+            let mut thumbnails = Vec::new();
+            loop {
+                let index = next_seed.fetch_add(1, Ordering::SeqCst);
+                if index >= seeds.len() {
+                    break;
+                }
+                thumbnails.push((index, render_thumbnail(seeds[index], &params)));
+            }
+            thumbnails
+        }));
+    }
+
+    let mut thumbnails: Vec<(usize, Canvas)> = Vec::new();
+    for join in joins {
+        thumbnails.extend(join.join().unwrap());
+    }
+
+    let columns = usize::min(columns, seeds.len());
+    let (sheet_width, sheet_height) = sheet_size(seeds.len(), columns);
+    let mut sheet = Canvas::new(sheet_width, sheet_height, BACKGROUND);
+
+    for (index, thumbnail) in thumbnails {
+        let x = MARGIN + (index % columns) * CELL_WIDTH;
+        let y = MARGIN + (index / columns) * CELL_HEIGHT;
+        sheet.blit(&thumbnail, x, y);
+
+        let label = seeds[index].to_string();
+        let label_x = x + (THUMBNAIL_WIDTH - canvas::text_width(&label, TEXT_SCALE)) / 2;
+        sheet.draw_text(label_x,
+                        y + thumbnail.height + MARGIN / 2,
+                        TEXT_SCALE,
+                        TEXT,
+                        &label);
+    }
+
+    sheet.write_png(&paths.path("contact_sheet.png"));
+}
@@ -0,0 +1,76 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Contour lines: the elevation map drawn as lines of equal elevation, as on
+//! a topographic map.
+
+use canvas::{Canvas, Rgb};
+use palette::Gradient;
+use super::{MAX_ELEV, SEA_LEVEL};
+
+// The paper the contours are drawn on when there is no gradient to tint it.
+const PAPER: Rgb = [245, 241, 230];
+// Contours at or above sea level, and the bathymetric contours below it.
+const LAND_CONTOUR: Rgb = [0, 0, 0];
+const SEA_CONTOUR: Rgb = [40, 90, 170];
+
+/// Draws the contours of an equirectangular grid of elevations produced by
+/// `generate_rect`, every `interval` metres above and below sea level.
+///
+/// A contour passes between two neighbouring samples when a multiple of the
+/// interval lies between their elevations, and is drawn on the higher of the
+/// two, so that the lines are a pixel wide.  The grid wraps around in
+/// longitude.  The background is tinted with `gradient` if given, and is
+/// otherwise plain.
+pub fn render_contours(data: &[f64],
+                       width: usize,
+                       height: usize,
+                       interval: f64,
+                       gradient: Option<Gradient>)
+                       -> Canvas {
+    let mut canvas = Canvas::new(width, height, PAPER);
+    if let Some(gradient) = gradient {
+        for y in 0..height {
+            for x in 0..width {
+                canvas.set_pixel(x, y, gradient.colour(data[y * width + x]));
+            }
+        }
+    }
+
+    // The number of intervals above (or below) sea level of each sample.
+    let band = |idx: usize| ((data[idx] - SEA_LEVEL) * MAX_ELEV / interval).floor();
+
+    let draw = |canvas: &mut Canvas, a: usize, b: usize| {
+        if data[a].is_nan() || data[b].is_nan() || band(a) == band(b) {
+            return;
+        }
+        let higher = if data[a] > data[b] { a } else { b };
+        let colour = if band(higher) >= 0.0 { LAND_CONTOUR } else { SEA_CONTOUR };
+        canvas.set_pixel(higher % width, higher / width, colour);
+    };
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+            draw(&mut canvas, idx, y * width + (x + 1) % width);
+            if y + 1 < height {
+                draw(&mut canvas, idx, idx + width);
+            }
+        }
+    }
+
+    canvas
+}
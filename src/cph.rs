@@ -0,0 +1,304 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! The complexplanet heightmap (`.cph`) format.
+//!
+//! A `.cph` file is a 20 byte header followed by the elevations, in
+//! planetary elevation units, row by row with the top row first.  All values
+//! are little-endian.
+//!
+//! | Offset | Size | Field                                       |
+//! |--------|------|---------------------------------------------|
+//! | 0      | 4    | Magic bytes, `CPHM`                         |
+//! | 4      | 2    | Format version, currently 1                 |
+//! | 6      | 1    | Data type: 0 for `f32`, 1 for `f64`         |
+//! | 7      | 1    | Projection, see `Projection`                |
+//! | 8      | 4    | Width, `u32`                                |
+//! | 12     | 4    | Height, `u32`                               |
+//! | 16     | 4    | Seed, `i32`                                 |
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+pub const MAGIC: [u8; 4] = *b"CPHM";
+pub const VERSION: u16 = 1;
+pub const HEADER_SIZE: usize = 20;
+
+/// The projection of the samples in a heightmap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    Equirectangular = 0,
+    CubeXP = 1,
+    CubeXN = 2,
+    CubeYP = 3,
+    CubeYN = 4,
+    CubeZP = 5,
+    CubeZN = 6,
+    WebMercator = 7,
+    Planar = 8,
+    // Cube faces in the Khronos (OpenGL and KTX) cubemap convention.
+    KhronosPX = 9,
+    KhronosNX = 10,
+    KhronosPY = 11,
+    KhronosNY = 12,
+    KhronosPZ = 13,
+    KhronosNZ = 14,
+    // Samples in directions given by a mapping file, see `mapping`.
+    Custom = 15,
+    // All six cube faces in a single image, see `CubeLayout`, in the native
+    // or Khronos convention.
+    CubeCross = 16,
+    CubeGrid = 17,
+    KhronosCross = 18,
+    KhronosGrid = 19,
+    Mollweide = 20,
+}
+
+impl Projection {
+    pub fn from_id(id: u8) -> Option<Projection> {
+        match id {
+            0 => Some(Projection::Equirectangular),
+            1 => Some(Projection::CubeXP),
+            2 => Some(Projection::CubeXN),
+            3 => Some(Projection::CubeYP),
+            4 => Some(Projection::CubeYN),
+            5 => Some(Projection::CubeZP),
+            6 => Some(Projection::CubeZN),
+            7 => Some(Projection::WebMercator),
+            8 => Some(Projection::Planar),
+            9 => Some(Projection::KhronosPX),
+            10 => Some(Projection::KhronosNX),
+            11 => Some(Projection::KhronosPY),
+            12 => Some(Projection::KhronosNY),
+            13 => Some(Projection::KhronosPZ),
+            14 => Some(Projection::KhronosNZ),
+            15 => Some(Projection::Custom),
+            16 => Some(Projection::CubeCross),
+            17 => Some(Projection::CubeGrid),
+            18 => Some(Projection::KhronosCross),
+            19 => Some(Projection::KhronosGrid),
+            20 => Some(Projection::Mollweide),
+            _ => None,
+        }
+    }
+}
+
+/// The type the elevations are stored as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    F32 = 0,
+    F64 = 1,
+}
+
+/// The header of a `.cph` file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CphHeader {
+    pub data_type: DataType,
+    pub projection: Projection,
+    pub width: u32,
+    pub height: u32,
+    pub seed: i32,
+}
+
+impl CphHeader {
+    fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[6] = self.data_type as u8;
+        bytes[7] = self.projection as u8;
+        bytes[8..12].copy_from_slice(&self.width.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.seed.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_SIZE]) -> io::Result<CphHeader> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes[0..4] != MAGIC {
+            return Err(invalid("Not a complexplanet heightmap"));
+        }
+        if u16::from_le_bytes([bytes[4], bytes[5]]) != VERSION {
+            return Err(invalid("Unsupported heightmap version"));
+        }
+        let data_type = match bytes[6] {
+            0 => DataType::F32,
+            1 => DataType::F64,
+            _ => return Err(invalid("Unknown heightmap data type")),
+        };
+        let projection = match Projection::from_id(bytes[7]) {
+            Some(projection) => projection,
+            None => return Err(invalid("Unknown heightmap projection")),
+        };
+
+        Ok(CphHeader {
+            data_type: data_type,
+            projection: projection,
+            width: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            height: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+            seed: i32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]),
+        })
+    }
+}
+
+/// Writes `data` (`header.width * header.height` elevations) to a `.cph`
+/// file.
+pub fn write_cph(filename: &str, header: &CphHeader, data: &[f64]) -> io::Result<()> {
+    assert_eq!(data.len(), header.width as usize * header.height as usize);
+
+    let file = File::create(Path::new(filename))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&header.to_bytes())?;
+    for &value in data {
+        match header.data_type {
+            DataType::F32 => writer.write_all(&(value as f32).to_le_bytes())?,
+            DataType::F64 => writer.write_all(&value.to_le_bytes())?,
+        }
+    }
+    writer.flush()
+}
+
+/// Reads a `.cph` file, returning its header and elevations (converted to
+/// `f64` whatever the stored type).
+pub fn read_cph(filename: &str) -> io::Result<(CphHeader, Vec<f64>)> {
+    let file = File::open(Path::new(filename))?;
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header_bytes)?;
+    let header = CphHeader::from_bytes(&header_bytes)?;
+
+    // Check the size of the data against the file before allocating it, so
+    // that a corrupt header can't ask for more memory than the file holds.
+    let value_size = match header.data_type {
+        DataType::F32 => 4,
+        DataType::F64 => 8,
+    };
+    let count = (header.width as usize).checked_mul(header.height as usize);
+    let data_len = count.and_then(|count| count.checked_mul(value_size));
+    let count = match (count, data_len) {
+        (Some(count), Some(data_len)) if data_len as u64 == file_len - HEADER_SIZE as u64 => count,
+        _ => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "Heightmap size doesn't match its header"))
+        }
+    };
+    let mut data = Vec::with_capacity(count);
+    for _ in 0..count {
+        let value = match header.data_type {
+            DataType::F32 => {
+                let mut bytes = [0u8; 4];
+                reader.read_exact(&mut bytes)?;
+                f32::from_le_bytes(bytes) as f64
+            }
+            DataType::F64 => {
+                let mut bytes = [0u8; 8];
+                reader.read_exact(&mut bytes)?;
+                f64::from_le_bytes(bytes)
+            }
+        };
+        data.push(value);
+    }
+
+    Ok((header, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        ::std::env::temp_dir()
+            .join(format!("complexplanet-{}.cph", name))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn header(data_type: DataType) -> CphHeader {
+        CphHeader {
+            data_type: data_type,
+            projection: Projection::KhronosGrid,
+            width: 5,
+            height: 3,
+            seed: -12345,
+        }
+    }
+
+    // Elevations which are exact in f32.
+    fn elevations() -> Vec<f64> {
+        (0..15).map(|i| -1.0 + i as f64 / 8.0).collect()
+    }
+
+    fn assert_round_trip(name: &str, data_type: DataType) {
+        let path = temp_path(name);
+        write_cph(&path, &header(data_type), &elevations()).unwrap();
+        let (read_header, data) = read_cph(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read_header, header(data_type));
+        assert_eq!(data, elevations());
+    }
+
+    #[test]
+    fn f32_round_trips() {
+        assert_round_trip("round-trip-f32", DataType::F32);
+    }
+
+    #[test]
+    fn f64_round_trips() {
+        assert_round_trip("round-trip-f64", DataType::F64);
+    }
+
+    // Writes a valid heightmap, changes the byte at `offset` to `value`, and
+    // returns the kind of the error reading it back.
+    fn corrupted_error(name: &str, offset: usize, value: u8) -> io::ErrorKind {
+        let path = temp_path(name);
+        write_cph(&path, &header(DataType::F64), &elevations()).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[offset] = value;
+        fs::write(&path, &bytes).unwrap();
+        let result = read_cph(&path);
+        fs::remove_file(&path).unwrap();
+        result.unwrap_err().kind()
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        assert_eq!(corrupted_error("bad-magic", 0, b'X'), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bad_version_is_rejected() {
+        assert_eq!(corrupted_error("bad-version", 4, 2), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bad_projection_is_rejected() {
+        assert_eq!(corrupted_error("bad-projection", 7, 200), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn size_beyond_the_file_is_rejected() {
+        // A height of 0xff000003 rows, far more than the file holds.
+        assert_eq!(corrupted_error("bad-size", 15, 0xff), io::ErrorKind::InvalidData);
+    }
+}
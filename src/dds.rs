@@ -0,0 +1,127 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Output of the planet as a DDS cubemap, ready for upload to the GPU.
+//!
+//! The faces are sampled following the Direct3D (and OpenGL) cubemap
+//! conventions, so that a direction looked up in the cubemap gives the
+//! elevation of the planet in that direction.  The planet's +Y axis is
+//! north, as for the other projections.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::thread::JoinHandle;
+
+use super::{cube_face_direction, dither_offset, quantize_elevation, OutputOptions, Plane,
+            SharedModule};
+
+const DDS_MAGIC: &'static [u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXELFORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xfc00;
+
+// The faces in the order they are stored in a DDS file.
+const FACES: [Plane; 6] = [Plane::XP, Plane::XN, Plane::YP, Plane::YN, Plane::ZP, Plane::ZN];
+
+fn render_face(face: Plane, generator: SharedModule, size: usize, output: OutputOptions)
+               -> JoinHandle<Vec<u8>> {
+    ::std::thread::spawn(move || {
+        let mut texels = vec![0u8; size * size];
+
+        for b in 0..size {
+            let t = (b as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+            for a in 0..size {
+                let s = (a as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+                let (px, py, pz) = cube_face_direction(face, s, t);
+                let magnitude = f64::sqrt(px * px + py * py + pz * pz);
+                let value = generator.get_value(px / magnitude, py / magnitude, pz / magnitude);
+
+                // Out of band samples are written as the lowest elevation.
+                let value = match output.elevation_band {
+                    Some((min, max)) if value < min || value > max => -1.0,
+                    _ => value,
+                };
+                let offset = if output.dither { dither_offset(a, b) } else { 0.0 };
+                let value = output.tone.apply(value);
+                texels[b * size + a] = quantize_elevation(value, 0xff, offset) as u8;
+            }
+        }
+
+        texels
+    })
+}
+
+/// Outputs "cube.dds", an 8-bit luminance cubemap with faces `size` texels
+/// square.
+pub fn output_dds_cube(generator: &SharedModule, size: usize, output: OutputOptions) {
+    let joins: Vec<JoinHandle<Vec<u8>>> = FACES.iter()
+        .map(|&face| render_face(face, generator.clone(), size, output))
+        .collect();
+    let faces: Vec<Vec<u8>> = joins.into_iter().map(|join| join.join().unwrap()).collect();
+
+    let mut header = Vec::with_capacity(DDS_HEADER_SIZE as usize);
+    let mut put = |value: u32| {
+        header.extend_from_slice(&value.to_le_bytes());
+    };
+    put(DDS_HEADER_SIZE);
+    put(DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT);
+    put(size as u32); // height
+    put(size as u32); // width
+    put(size as u32); // pitch, one byte per texel
+    put(0); // depth
+    put(0); // mipmap count
+    for _ in 0..11 {
+        put(0); // reserved
+    }
+    put(DDS_PIXELFORMAT_SIZE);
+    put(DDPF_LUMINANCE);
+    put(0); // FourCC
+    put(8); // bits per texel
+    put(0xff); // luminance mask
+    put(0);
+    put(0);
+    put(0);
+    put(DDSCAPS_COMPLEX | DDSCAPS_TEXTURE);
+    put(DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALL_FACES);
+    put(0);
+    put(0);
+    put(0); // reserved
+
+    let file = File::create(Path::new(&output.paths.path("cube.dds")))
+        .expect("Failed to create file for writing");
+    let mut writer = BufWriter::new(file);
+    writer.write_all(DDS_MAGIC)
+        .and_then(|_| writer.write_all(&header))
+        .expect("Failed to write image data");
+    for face in &faces {
+        writer.write_all(face).expect("Failed to write image data");
+    }
+}
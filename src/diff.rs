@@ -0,0 +1,70 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Difference images between the planets of two seeds, for seeing how much
+//! and where a change moves the terrain.
+
+use canvas::Canvas;
+use super::OutputPaths;
+
+/// Outputs "diff.png", the absolute difference between two equally sized
+/// grids of elevations as greyscale, scaled so that the largest difference
+/// is white, and prints the root mean square and largest differences to
+/// stderr.  Samples which aren't finite in either grid are left out, and
+/// drawn in red.
+pub fn output_diff(elevations: &[f64],
+                   other: &[f64],
+                   width: usize,
+                   height: usize,
+                   label: &str,
+                   paths: OutputPaths) {
+    assert_eq!(elevations.len(), other.len());
+
+    let differences: Vec<f64> = elevations.iter()
+        .zip(other.iter())
+        .map(|(&a, &b)| f64::abs(a - b))
+        .collect();
+
+    let mut count = 0;
+    let mut sum_squares = 0.0;
+    let mut max = 0.0;
+    for &difference in differences.iter().filter(|difference| difference.is_finite()) {
+        count += 1;
+        sum_squares += difference * difference;
+        max = f64::max(max, difference);
+    }
+    let rms = if count > 0 { f64::sqrt(sum_squares / count as f64) } else { 0.0 };
+    eprintln!("Difference from {}: RMS {:.6} max {:.6}", label, rms, max);
+
+    let mut canvas = Canvas::new(width, height, [0, 0, 0]);
+    for y in 0..height {
+        for x in 0..width {
+            let difference = differences[y * width + x];
+            let colour = if !difference.is_finite() {
+                [255, 0, 0]
+            } else if max > 0.0 {
+                let level = (difference / max * 255.0).round() as u8;
+                [level, level, level]
+            } else {
+                [0, 0, 0]
+            };
+            canvas.set_pixel(x, y, colour);
+        }
+    }
+    canvas.write_png(&paths.path("diff.png"));
+}
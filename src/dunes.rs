@@ -0,0 +1,86 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Alignment of the badlands sand dunes to a prevailing wind.
+
+use std::f64::consts::PI;
+
+use noise::module::Module;
+
+// Spacing in longitude of the points the local frames are taken from, in
+// radians.  Closer spacing reduces the distortion of the dunes between the
+// points, but blends differently aligned dunes more often.
+const ANCHOR_SPACING: f64 = PI / 6.0;
+
+// How much longer the dunes are across the wind than along it.
+const DUNE_ELONGATION: f64 = 4.0;
+
+// Offset between the slices of noise used for neighbouring anchors, so that
+// the blended dunes don't show as shifted copies of each other.
+const ANCHOR_DECORRELATION: f64 = 97.25;
+
+/// A noise module which samples its source in a frame aligned with a wind
+/// blowing from a fixed compass direction everywhere on the planet, with the
+/// source stretched across the wind.  Ridges in the source then form dune
+/// crests lying across the wind, like those of a real sand sea.
+///
+/// No single frame aligned with north fits the whole sphere, so the source
+/// is sampled in the local east and north coordinates around points spaced
+/// `ANCHOR_SPACING` apart in longitude, and the values from the two nearest
+/// points are cross-faded.  The frames converge at the poles, where the
+/// alignment breaks down.
+pub struct WindAligned<S> {
+    source: S,
+    // Direction the wind blows from, clockwise from north, in radians.
+    direction: f64,
+}
+
+impl<S: Module> WindAligned<S> {
+    pub fn new(source: S, direction_degrees: f64) -> WindAligned<S> {
+        WindAligned {
+            source: source,
+            direction: direction_degrees.to_radians(),
+        }
+    }
+}
+
+impl<S: Module> Module for WindAligned<S> {
+    fn get_value(&self, x: f64, y: f64, z: f64) -> f64 {
+        let r = f64::sqrt(x * x + z * z);
+        let lat = f64::atan2(y, r);
+        let lon = f64::atan2(z, x);
+        let anchor_count = (2.0 * PI / ANCHOR_SPACING).round() as usize;
+
+        let t = (lon + PI) / ANCHOR_SPACING;
+        let anchor = t.floor();
+        let (sin, cos) = self.direction.sin_cos();
+        let sample = |anchor: f64| {
+            // Distances east and north in the anchor's frame, the latter
+            // measured from the equator.
+            let east = (lon - (-PI + anchor * ANCHOR_SPACING)) * r;
+            let north = lat;
+            let along = north * cos + east * sin;
+            let across = east * cos - north * sin;
+            let slice = (anchor as usize % anchor_count) as f64 * ANCHOR_DECORRELATION;
+            self.source.get_value(along, across / DUNE_ELONGATION, slice)
+        };
+
+        let weight = f64::cos((t - anchor) * PI / 2.0).powi(2);
+        weight * sample(anchor) + (1.0 - weight) * sample(anchor + 1.0)
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Output of the planet as seen from a point in orbit, through a gnomonic
+//! (rectilinear) camera looking straight down at it.
+
+use cph::Projection;
+use super::{check_finite, clamp, lat_lon_to_pos, pos_to_lat_lon, write_output_to_file,
+            OutputOptions, SharedModule};
+
+/// Where the camera is and how much it sees.
+#[derive(Clone, Copy)]
+pub struct View {
+    // The point on the surface below the camera, which is at the centre of
+    // the image, in degrees.
+    pub lat: f64,
+    pub lon: f64,
+    // The horizontal field of view, in degrees, less than 180.
+    pub fov: f64,
+    // The distance of the camera from the centre of the planet, in planetary
+    // radii, greater than 1.
+    pub distance: f64,
+}
+
+fn dot(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+impl View {
+    // The point on the unit sphere seen at the centre of pixel (x, y) of a
+    // `width` by `height` image, or None if the ray through it misses the
+    // planet.  North is up and east to the right.
+    fn pixel_pos(&self,
+                 width: usize,
+                 height: usize,
+                 x: usize,
+                 y: usize)
+                 -> Option<(f64, f64, f64)> {
+        let (lat, lon) = (self.lat.to_radians(), self.lon.to_radians());
+        let centre = lat_lon_to_pos(self.lat, self.lon);
+        let east = (-f64::sin(lon), 0.0, f64::cos(lon));
+        let north = (-f64::sin(lat) * f64::cos(lon), f64::cos(lat), -f64::sin(lat) * f64::sin(lon));
+
+        // The image plane is one unit in front of the camera.
+        let half_width = f64::tan(self.fov.to_radians() / 2.0);
+        let u = ((x as f64 + 0.5) / width as f64 * 2.0 - 1.0) * half_width;
+        let v = (1.0 - (y as f64 + 0.5) / height as f64 * 2.0) * half_width * height as f64 /
+                width as f64;
+        let dir = (-centre.0 + u * east.0 + v * north.0,
+                   -centre.1 + u * east.1 + v * north.1,
+                   -centre.2 + u * east.2 + v * north.2);
+        let origin = (centre.0 * self.distance, centre.1 * self.distance, centre.2 * self.distance);
+
+        // The nearer solution of |origin + t * dir| = 1.
+        let a = dot(dir, dir);
+        let b = 2.0 * dot(origin, dir);
+        let c = self.distance * self.distance - 1.0;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t = (-b - f64::sqrt(discriminant)) / (2.0 * a);
+        Some((origin.0 + t * dir.0, origin.1 + t * dir.1, origin.2 + t * dir.2))
+    }
+}
+
+/// Outputs "gnomonic.png", a `width` by `height` image of the planet from
+/// `view`, sampling the rows in `threads` worker threads.  Pixels which miss
+/// the planet are set to the background elevation of `output`.
+pub fn output_gnomonic(seed: i32,
+                       generator: &SharedModule,
+                       view: View,
+                       width: usize,
+                       height: usize,
+                       threads: usize,
+                       output: OutputOptions) {
+    let threads = clamp(threads, 1, height);
+    let mut joins = Vec::new();
+    for i in 0..threads {
+        let y0 = i * height / threads;
+        let y1 = (i + 1) * height / threads;
+        let generator = generator.clone();
+        let background = output.background;
+        joins.push(::std::thread::spawn(move || {
+            let mut rows = Vec::with_capacity(width * (y1 - y0));
+            for y in y0..y1 {
+                for x in 0..width {
+                    rows.push(match view.pixel_pos(width, height, x, y) {
+                        Some(pos) => generator.get_value(pos.0, pos.1, pos.2),
+                        None => background,
+                    });
+                }
+            }
+            rows
+        }));
+    }
+
+    let mut dest_buffer = Vec::with_capacity(width * height);
+    for join in joins {
+        dest_buffer.extend(join.join().unwrap());
+    }
+
+    // As with `region::output_region`, the header has no room for the view,
+    // so the image is recorded as custom sampling.
+    let filename = output.paths.path("gnomonic.png");
+    if output.strict {
+        check_finite(&filename, &dest_buffer, width, |x, y| {
+            view.pixel_pos(width, height, x, y).map(pos_to_lat_lon)
+        });
+    }
+    write_output_to_file(&filename,
+                         &dest_buffer,
+                         width,
+                         height,
+                         seed,
+                         Projection::Custom,
+                         output);
+}
@@ -0,0 +1,122 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Hypsographs: charts of how much of the planet's surface lies at each
+//! elevation.
+
+use canvas::{self, Canvas, Rgb, GLYPH_HEIGHT};
+use palette::hypsometric_colour;
+use super::{f64_clamp, rect_row_weight, SEA_LEVEL};
+
+// Number of histogram bars between -1.0 and +1.0.
+const BINS: usize = 64;
+const BIN_WIDTH: usize = 8;
+const PLOT_WIDTH: usize = BINS * BIN_WIDTH;
+const PLOT_HEIGHT: usize = 192;
+const MARGIN: usize = 16;
+const TICK_LENGTH: usize = 4;
+const TEXT_SCALE: usize = 2;
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const FOREGROUND: Rgb = [0, 0, 0];
+const SEA_LEVEL_COLOUR: Rgb = [200, 30, 30];
+
+/// Writes a hypsograph of an equirectangular grid of elevations produced by
+/// `generate_rect` to `filename`.  Each bar is the share of the surface area
+/// within a range of elevations, from -1.0 on the left to +1.0 on the right,
+/// tinted with the colour of that elevation.  The curve over the bars is the
+/// fraction of the surface above each elevation, the classic hypsographic
+/// curve, and sea level is marked with a vertical line.
+pub fn output_hypsograph(filename: &str, elevations: &[f64], width: usize, height: usize) {
+    // Rows are weighted by the area they cover, which shrinks towards the
+    // poles.
+    let mut areas = [0.0; BINS];
+    let mut total_area = 0.0;
+    let mut land_area = 0.0;
+    for y in 0..height {
+        let weight = rect_row_weight(width, height, y);
+        for &value in &elevations[(y * width)..((y + 1) * width)] {
+            let bin = ((value + 1.0) / 2.0 * BINS as f64).floor();
+            areas[f64_clamp(bin, 0.0, (BINS - 1) as f64) as usize] += weight;
+            total_area += weight;
+            if value > SEA_LEVEL {
+                land_area += weight;
+            }
+        }
+    }
+    let max_area = areas.iter().cloned().fold(0.0, f64::max);
+
+    let text_height = GLYPH_HEIGHT * TEXT_SCALE;
+    let plot_x = MARGIN;
+    let plot_y = MARGIN + text_height + MARGIN;
+    let chart_width = PLOT_WIDTH + 2 * MARGIN;
+    let chart_height = plot_y + PLOT_HEIGHT + TICK_LENGTH + MARGIN / 2 + text_height + MARGIN;
+    let mut chart = Canvas::new(chart_width, chart_height, BACKGROUND);
+
+    if max_area > 0.0 {
+        for (bin, &area) in areas.iter().enumerate() {
+            let bar_height = (area / max_area * PLOT_HEIGHT as f64).round() as usize;
+            let centre = -1.0 + (bin as f64 + 0.5) / BINS as f64 * 2.0;
+            chart.fill_rect(plot_x + bin * BIN_WIDTH,
+                            plot_y + PLOT_HEIGHT - bar_height,
+                            BIN_WIDTH - 1,
+                            bar_height,
+                            hypsometric_colour(centre));
+        }
+    }
+
+    let elevation_x = |value: f64| plot_x as f64 + (value + 1.0) / 2.0 * PLOT_WIDTH as f64;
+    chart.draw_line(elevation_x(SEA_LEVEL),
+                    plot_y as f64,
+                    elevation_x(SEA_LEVEL),
+                    (plot_y + PLOT_HEIGHT) as f64,
+                    SEA_LEVEL_COLOUR);
+
+    if total_area > 0.0 {
+        let mut above = total_area;
+        let mut last = (plot_x as f64, plot_y as f64);
+        for (bin, &area) in areas.iter().enumerate() {
+            above -= area;
+            let point = (plot_x as f64 + ((bin + 1) * BIN_WIDTH) as f64,
+                         plot_y as f64 + (1.0 - above / total_area) * PLOT_HEIGHT as f64);
+            chart.draw_line(last.0, last.1, point.0, point.1, FOREGROUND);
+            last = point;
+        }
+    }
+
+    let baseline = plot_y + PLOT_HEIGHT;
+    chart.fill_rect(plot_x, baseline, PLOT_WIDTH + 1, 1, FOREGROUND);
+    for &(value, label) in &[(-1.0, "-1"), (SEA_LEVEL, "0"), (1.0, "+1")] {
+        let x = elevation_x(value).round() as usize;
+        chart.fill_rect(x, baseline, 1, TICK_LENGTH, FOREGROUND);
+        chart.draw_text(x - canvas::text_width(label, TEXT_SCALE) / 2,
+                        baseline + TICK_LENGTH + MARGIN / 2,
+                        TEXT_SCALE,
+                        FOREGROUND,
+                        label);
+    }
+
+    let land = if total_area > 0.0 { land_area / total_area } else { 0.0 };
+    chart.draw_text(plot_x,
+                    MARGIN,
+                    TEXT_SCALE,
+                    FOREGROUND,
+                    &format!("LAND {:.1}%", land * 100.0));
+
+    chart.write_png(filename);
+}
@@ -0,0 +1,340 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! A small writer for indexed-colour (palette) PNG files.
+//!
+//! The PNG encoder provided by `image` has no way of supplying a PLTE chunk,
+//! so this module writes the file itself.  Image data is compressed with a
+//! single fixed-Huffman deflate block using a simple hash-chain LZ77
+//! matcher, which does well on the long runs of identical indices found in
+//! palettized maps.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bits::BitWriter;
+use canvas::Rgb;
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Writes `indices` (one palette index per pixel, top row first) as an
+/// indexed-colour PNG.  The bit depth is the smallest which can hold every
+/// palette entry.
+pub fn write_indexed_png(filename: &str,
+                         indices: &[u8],
+                         width: usize,
+                         height: usize,
+                         palette: &[Rgb]) {
+    assert!(!palette.is_empty() && palette.len() <= 256);
+
+    let bit_depth: usize = match palette.len() {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+
+    // Pack each scanline, prefixed by filter type 0 (None).
+    let row_bytes = (width * bit_depth + 7) / 8;
+    let mut raw = Vec::with_capacity((row_bytes + 1) * height);
+    for y in 0..height {
+        raw.push(0);
+        let mut byte = 0u8;
+        let mut used = 0;
+        for x in 0..width {
+            byte |= indices[y * width + x] << (8 - bit_depth - used);
+            used += bit_depth;
+            if used == 8 {
+                raw.push(byte);
+                byte = 0;
+                used = 0;
+            }
+        }
+        if used > 0 {
+            raw.push(byte);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&u32_be(width as u32));
+    ihdr.extend_from_slice(&u32_be(height as u32));
+    ihdr.extend_from_slice(&[bit_depth as u8, 3, 0, 0, 0]);
+
+    let mut plte = Vec::with_capacity(palette.len() * 3);
+    for colour in palette {
+        plte.extend_from_slice(colour);
+    }
+
+    let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&PNG_SIGNATURE).expect("Failed to write image data");
+    write_chunk(&mut writer, b"IHDR", &ihdr);
+    write_chunk(&mut writer, b"PLTE", &plte);
+    write_chunk(&mut writer, b"IDAT", &zlib_compress(&raw));
+    write_chunk(&mut writer, b"IEND", &[]);
+}
+
+pub fn u32_be(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+/// Writes a PNG chunk of the given type, with its length and CRC.
+pub fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) {
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+
+    writer.write_all(&u32_be(data.len() as u32))
+        .and_then(|_| writer.write_all(kind))
+        .and_then(|_| writer.write_all(data))
+        .and_then(|_| writer.write_all(&u32_be(crc.finish())))
+        .expect("Failed to write image data");
+}
+
+struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    fn new() -> Crc32 {
+        let mut table = [0u32; 256];
+        for n in 0..256 {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            }
+            table[n] = c;
+        }
+        Crc32 {
+            table: table,
+            crc: 0xffffffff,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.crc = self.table[((self.crc ^ b as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.crc ^ 0xffffffff
+    }
+}
+
+/// The Adler-32 checksum ending a zlib stream, updated with the data as it
+/// is compressed.
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.a = (self.a + byte as u32) % 65521;
+            self.b = (self.b + self.a) % 65521;
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+const LENGTH_BASE: [u32; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43,
+                                51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4,
+                                 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u32; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385,
+                              513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385,
+                              24577];
+const DIST_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9,
+                               10, 10, 11, 11, 12, 12, 13, 13];
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 32;
+const HASH_SIZE: usize = 1 << 15;
+
+fn write_literal(bits: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => bits.write_code(0x30 + symbol, 8),
+        144..=255 => bits.write_code(0x190 + symbol - 144, 9),
+        256..=279 => bits.write_code(symbol - 256, 7),
+        _ => bits.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(bits: &mut BitWriter, length: usize, distance: usize) {
+    let length = length as u32;
+    let distance = distance as u32;
+
+    let li = LENGTH_BASE.iter().rposition(|&base| base <= length).unwrap();
+    write_literal(bits, 257 + li as u32);
+    bits.write_bits(length - LENGTH_BASE[li], LENGTH_EXTRA[li]);
+
+    let di = DIST_BASE.iter().rposition(|&base| base <= distance).unwrap();
+    bits.write_code(di as u32, 5);
+    bits.write_bits(distance - DIST_BASE[di], DIST_EXTRA[di]);
+}
+
+fn hash3(data: &[u8], pos: usize) -> usize {
+    let h = (data[pos] as usize) << 10 ^ (data[pos + 1] as usize) << 5 ^ data[pos + 2] as usize;
+    h & (HASH_SIZE - 1)
+}
+
+/// Compresses `data` into a zlib stream consisting of a single fixed-Huffman
+/// deflate block.
+fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::new();
+    bits.write_bits(0x78, 8);
+    bits.write_bits(0x01, 8);
+    deflate_fixed_block(&mut bits, data, true);
+
+    let mut adler = Adler32::new();
+    adler.update(data);
+    let mut out = bits.finish();
+    out.extend_from_slice(&u32_be(adler.finish()));
+    out
+}
+
+/// Compresses `data` into a fixed-Huffman deflate block, the last of the
+/// stream if `last` is set.  Matches are only found within `data`.
+pub fn deflate_fixed_block(bits: &mut BitWriter, data: &[u8], last: bool) {
+    // BFINAL, BTYPE = 01 (fixed Huffman codes)
+    bits.write_bits(last as u32, 1);
+    bits.write_bits(1, 2);
+
+    let mut head = vec![usize::max_value(); HASH_SIZE];
+    let mut prev = vec![usize::max_value(); data.len()];
+    let insert = |head: &mut Vec<usize>, prev: &mut Vec<usize>, pos: usize| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash3(data, pos);
+            prev[pos] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+
+        if pos + MIN_MATCH <= data.len() {
+            let max_len = usize::min(MAX_MATCH, data.len() - pos);
+            let mut candidate = head[hash3(data, pos)];
+            let mut chain = 0;
+            while candidate != usize::max_value() && pos - candidate <= WINDOW_SIZE &&
+                  chain < MAX_CHAIN {
+                let mut len = 0;
+                while len < max_len && data[candidate + len] == data[pos + len] {
+                    len += 1;
+                }
+                if len > best_len {
+                    best_len = len;
+                    best_dist = pos - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            write_match(bits, best_len, best_dist);
+            for p in pos..(pos + best_len) {
+                insert(&mut head, &mut prev, p);
+            }
+            pos += best_len;
+        } else {
+            write_literal(bits, data[pos] as u32);
+            insert(&mut head, &mut prev, pos);
+            pos += 1;
+        }
+    }
+
+    write_literal(bits, 256);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use image;
+
+    use super::*;
+
+    // Writes an indexed PNG with a palette of the given size, then decodes it
+    // and checks the palette and the index of every pixel.
+    fn assert_round_trip(colours: usize, width: usize, height: usize) {
+        let palette: Vec<Rgb> = (0..colours)
+            .map(|i| [i as u8, (255 - i) as u8, (i * 7) as u8])
+            .collect();
+        let indices: Vec<u8> = (0..width * height)
+            .map(|i| ((i * 5 + i / 3) % colours) as u8)
+            .collect();
+        let path = ::std::env::temp_dir()
+            .join(format!("complexplanet-indexed-{}-{}x{}.png", colours, width, height));
+        let filename = path.to_str().unwrap();
+        write_indexed_png(filename, &indices, width, height, &palette);
+
+        // The PLTE chunk follows the signature and the 13 byte IHDR chunk.
+        let data = fs::read(filename).unwrap();
+        let plte = &data[8 + 25..];
+        assert_eq!(&plte[4..8], b"PLTE");
+        assert_eq!(&plte[..4], &u32_be(colours as u32 * 3));
+        let written: Vec<Rgb> = plte[8..8 + colours * 3]
+            .chunks(3)
+            .map(|c| [c[0], c[1], c[2]])
+            .collect();
+        assert_eq!(written, palette);
+
+        let decoded = image::open(&path).unwrap().to_rgb();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(decoded.dimensions(), (width as u32, height as u32));
+        let decoded_indices: Vec<u8> = decoded.pixels()
+            .map(|p| palette.iter().position(|c| c[..] == p.data[..]).unwrap() as u8)
+            .collect();
+        assert_eq!(decoded_indices, indices);
+    }
+
+    #[test]
+    fn each_bit_depth_round_trips() {
+        // Widths which don't fill the last byte of each row at 1, 2 and 4
+        // bits per pixel.
+        assert_round_trip(2, 13, 7);
+        assert_round_trip(4, 13, 7);
+        assert_round_trip(16, 13, 7);
+        assert_round_trip(256, 13, 7);
+    }
+
+    #[test]
+    fn large_image_round_trips() {
+        // Long enough for back references across rows.
+        assert_round_trip(40, 300, 200);
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! The mean solar energy reaching the surface over a year, from the axial
+//! tilt of the planet and the slope of the land.
+
+use std::f64::consts::PI;
+
+use cph::{self, CphHeader, DataType, Projection};
+use super::{rect_gradients, rect_lat_lon, OutputPaths, SEA_LEVEL};
+
+// Solar energy arriving at the top of the atmosphere facing the sun, in W/m²,
+// taken from the Earth.
+const SOLAR_CONSTANT: f64 = 1361.0;
+
+// Number of points in the orbit and in each day at which the position of the
+// sun is sampled.
+const YEAR_STEPS: usize = 360;
+const DAY_STEPS: usize = 96;
+
+// Computes the position of the sun averaged over a year at the given
+// latitude, as a vector in the local (east, north, up) frame whose length
+// is the mean insolation on a flat surface as a fraction of the solar
+// constant.  The sun counts for nothing while below the horizon.  The orbit
+// is taken to be circular, so the seasons only come from the `obliquity`,
+// in degrees.
+fn mean_sun(lat: f64, obliquity: f64) -> (f64, f64, f64) {
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let sin_obliquity = obliquity.to_radians().sin();
+
+    let (mut east, mut north, mut up) = (0.0, 0.0, 0.0);
+    for day in 0..YEAR_STEPS {
+        let orbit = (day as f64 + 0.5) / YEAR_STEPS as f64 * 2.0 * PI;
+        let declination = f64::asin(sin_obliquity * orbit.sin());
+        let (sin_dec, cos_dec) = declination.sin_cos();
+
+        for step in 0..DAY_STEPS {
+            let hour_angle = (step as f64 + 0.5) / DAY_STEPS as f64 * 2.0 * PI;
+            let (sin_hour, cos_hour) = hour_angle.sin_cos();
+            let sun_up = sin_lat * sin_dec + cos_lat * cos_dec * cos_hour;
+            if sun_up > 0.0 {
+                east -= cos_dec * sin_hour;
+                north += cos_lat * sin_dec - sin_lat * cos_dec * cos_hour;
+                up += sun_up;
+            }
+        }
+    }
+
+    let samples = (YEAR_STEPS * DAY_STEPS) as f64;
+    (east / samples, north / samples, up / samples)
+}
+
+/// Outputs "insolation.cph", the mean insolation in W/m² over a year of each
+/// sample of an equirectangular grid of elevations produced by
+/// `generate_rect`, for a planet with the given `obliquity` in degrees.  The
+/// insolation of each latitude is modulated by the slope and aspect of the
+/// land, so slopes facing the sun get more and those facing away less; the
+/// sea is flat.  Shadows cast by the surrounding terrain are not accounted
+/// for.
+pub fn output_insolation(elevations: &[f64],
+                         width: usize,
+                         height: usize,
+                         seed: i32,
+                         obliquity: f64,
+                         paths: OutputPaths) {
+    let gradients = rect_gradients(elevations, width, height);
+
+    let mut insolation = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let (lat, _) = rect_lat_lon(width, height, 0, y);
+        let sun = mean_sun(lat, obliquity);
+        for x in 0..width {
+            let idx = y * width + x;
+            let (dx, dy) = if elevations[idx] > SEA_LEVEL { gradients[idx] } else { (0.0, 0.0) };
+            // The mean sun can be projected onto the surface normal directly
+            // as long as the surface doesn't face away from the sun for part
+            // of the day, which only matters on steep slopes.
+            let len = f64::sqrt(dx * dx + dy * dy + 1.0);
+            let facing = (-dx * sun.0 - dy * sun.1 + sun.2) / len;
+            insolation.push(SOLAR_CONSTANT * f64::max(facing, 0.0));
+        }
+    }
+
+    let header = CphHeader {
+        data_type: DataType::F32,
+        projection: Projection::Equirectangular,
+        width: width as u32,
+        height: height as u32,
+        seed: seed,
+    };
+    cph::write_cph(&paths.path("insolation.cph"), &header, &insolation)
+        .expect("Failed to write insolation data");
+}
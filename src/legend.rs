@@ -0,0 +1,117 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Legends showing the colour each elevation is drawn with.
+
+use canvas::{self, Canvas, Rgb, GLYPH_HEIGHT};
+use palette::{self, PaletteMethod};
+use super::{quantize_elevation, quantize_elevation48, OutputFormat, Tone, SEA_LEVEL};
+
+const RAMP_WIDTH: usize = 32;
+const RAMP_HEIGHT: usize = 256;
+const MARGIN: usize = 8;
+const TICK_LENGTH: usize = 4;
+const TEXT_SCALE: usize = 2;
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const FOREGROUND: Rgb = [0, 0, 0];
+
+// Elevations labelled on the legend, in planetary elevation units.
+const TICKS: [f64; 9] = [1.0, 0.75, 0.5, 0.25, 0.0, -0.25, -0.5, -0.75, -1.0];
+
+/// Writes a legend for images of the given format and tone to `filename`: a
+/// ramp of the colours from +1.0 at the top to -1.0 at the bottom, labelled
+/// with elevations in planetary elevation units.
+///
+/// Palettes chosen by median cut depend on the colours in the image, so their
+/// legend shows the elevation tint the palette was chosen from.
+pub fn output_legend(filename: &str, format: OutputFormat, tone: Tone) {
+    // Elevation at the centre of each row of the ramp, top row first.
+    let values: Vec<f64> = (0..RAMP_HEIGHT)
+        .map(|row| 1.0 - 2.0 * (row as f64 + 0.5) / RAMP_HEIGHT as f64)
+        .collect();
+
+    let colours: Vec<Rgb> = match format {
+        OutputFormat::Indexed(colours, PaletteMethod::Terrain) => {
+            let (palette, indices) = palette::quantize(&values, colours, PaletteMethod::Terrain);
+            indices.iter().map(|&i| palette[i as usize]).collect()
+        }
+        OutputFormat::Indexed(_, PaletteMethod::MedianCut) => {
+            values.iter().map(|&v| palette::hypsometric_colour(v)).collect()
+        }
+        OutputFormat::Tinted(gradient) |
+        OutputFormat::WebP(_, Some(gradient)) => {
+            values.iter().map(|&v| gradient.colour(v)).collect()
+        }
+        OutputFormat::OceanMask => {
+            values.iter()
+                .map(|&v| if v > SEA_LEVEL { [0xff, 0xff, 0xff] } else { [0x00, 0x00, 0x00] })
+                .collect()
+        }
+        OutputFormat::Colour24 | OutputFormat::Ppm8 => {
+            values.iter()
+                .map(|&v| {
+                    let value = quantize_elevation(tone.apply(v), 0xffffff, 0.0);
+                    [(value >> 16) as u8, (value >> 8) as u8, value as u8]
+                })
+                .collect()
+        }
+        OutputFormat::Colour48 => {
+            // The most significant byte of each 16-bit channel.
+            values.iter()
+                .map(|&v| {
+                    let value = quantize_elevation48(tone.apply(v), 0.0);
+                    [(value >> 40) as u8, (value >> 24) as u8, (value >> 8) as u8]
+                })
+                .collect()
+        }
+        _ => {
+            values.iter()
+                .map(|&v| {
+                    let value = quantize_elevation(tone.apply(v), 0xff, 0.0) as u8;
+                    [value, value, value]
+                })
+                .collect()
+        }
+    };
+
+    let labels: Vec<String> = TICKS.iter().map(|&tick| format!("{:+.2}", tick)).collect();
+    let label_width = labels.iter().map(|l| canvas::text_width(l, TEXT_SCALE)).max().unwrap();
+    let text_height = GLYPH_HEIGHT * TEXT_SCALE;
+
+    let width = MARGIN + RAMP_WIDTH + TICK_LENGTH + MARGIN / 2 + label_width + MARGIN;
+    let height = RAMP_HEIGHT + MARGIN * 2 + text_height;
+    let ramp_top = MARGIN + text_height / 2;
+    let mut legend = Canvas::new(width, height, BACKGROUND);
+
+    for (row, colour) in colours.iter().enumerate() {
+        legend.fill_rect(MARGIN, ramp_top + row, RAMP_WIDTH, 1, *colour);
+    }
+
+    for (tick, label) in TICKS.iter().zip(labels.iter()) {
+        let row = ((1.0 - tick) / 2.0 * (RAMP_HEIGHT - 1) as f64).round() as usize;
+        let y = ramp_top + row;
+        legend.fill_rect(MARGIN + RAMP_WIDTH, y, TICK_LENGTH, 1, FOREGROUND);
+        legend.draw_text(MARGIN + RAMP_WIDTH + TICK_LENGTH + MARGIN / 2,
+                         y - text_height / 2,
+                         TEXT_SCALE,
+                         FOREGROUND,
+                         label);
+    }
+
+    legend.write_png(filename);
+}
@@ -0,0 +1,180 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Lock files recording the complete effective configuration of a run, so
+//! that its output can be reproduced exactly.
+//!
+//! A lock file is a JSON object holding the version of complexplanet which
+//! wrote it, the seed, output type and format, the resolved terrain
+//! parameters, and `args`, every option of the run including those left at
+//! their defaults.  Only `args` is read back; the rest is for the reader's
+//! benefit.  Replaying the arguments rather than the parameters keeps the
+//! output reproducible even if the defaults change in later versions.
+//!
+//! The metadata sidecars written by `--metadata` share the format of the
+//! parameters, so that two runs' parameters can be diffed.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use clap::ArgMatches;
+use serde::Serialize;
+use serde_json;
+
+use cph::Projection;
+use super::{Metadata, PlanetParams, MAX_ELEV, PLANET_CIRCUMFERENCE, SEA_LEVEL};
+
+// Options which aren't part of the configuration of the output.
+const EXCLUDED_ARGS: [&str; 2] = ["config", "emit-lock"];
+
+// Whether a defaulted option describing the view of --projection gnomonic or
+// orthographic is used by the run.  Those options are rejected when given
+// with the other projections, so are only recorded when they apply.
+fn view_arg_applies(matches: &ArgMatches, name: &str) -> bool {
+    let projection = matches.value_of("projection").unwrap();
+    match name {
+        "center" => projection == "gnomonic" || projection == "orthographic",
+        "fov" | "view-distance" => projection == "gnomonic",
+        _ => true,
+    }
+}
+
+// Every option given or defaulted, in a stable order, as `--name=value`
+// arguments which reproduce them.  The `=` form keeps negative values from
+// being taken for options.
+fn resolved_args(matches: &ArgMatches) -> Vec<String> {
+    let mut names: Vec<&str> = matches.args
+        .keys()
+        .cloned()
+        .filter(|name| !EXCLUDED_ARGS.contains(name))
+        .filter(|name| matches.occurrences_of(name) > 0 || view_arg_applies(matches, name))
+        .collect();
+    names.sort();
+
+    let mut args = Vec::new();
+    for name in names {
+        // Flags are matched without any values.
+        let values: Vec<&str> = match matches.values_of(name) {
+            Some(values) => values.collect(),
+            None => Vec::new(),
+        };
+        if values.is_empty() {
+            args.push(format!("--{}", name));
+        }
+        for value in values {
+            args.push(format!("--{}={}", name, value));
+        }
+    }
+    args
+}
+
+// The fields of a lock file.
+#[derive(Serialize)]
+struct Lock<'a> {
+    version: &'a str,
+    seed: i32,
+    #[serde(rename = "type")]
+    output_type: &'a str,
+    format: &'a str,
+    params: &'a PlanetParams,
+    args: Vec<String>,
+}
+
+// The field of a lock file which is read back.
+#[derive(Deserialize)]
+struct LockArgs {
+    args: Vec<String>,
+}
+
+// The fields of a metadata sidecar.
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    version: &'a str,
+    seed: i32,
+    #[serde(rename = "type")]
+    output_type: &'a str,
+    projection: String,
+    width: usize,
+    height: usize,
+    format: &'a str,
+    params: &'a PlanetParams,
+    constants: Constants,
+}
+
+// The planet's fixed constants, as recorded by the metadata sidecars.
+#[derive(Serialize)]
+struct Constants {
+    max_elev: f64,
+    planet_circumference: f64,
+    sea_level: f64,
+}
+
+fn write_json<T: Serialize>(filename: &str, value: &T) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(Path::new(filename))?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Writes a lock file of the configuration parsed into `matches`, and the
+/// terrain parameters resolved from it.
+pub fn write_lock(filename: &str, matches: &ArgMatches, seed: i32, params: &PlanetParams) {
+    let lock = Lock {
+        version: crate_version!(),
+        seed: seed,
+        output_type: matches.value_of("type").unwrap(),
+        format: matches.value_of("format").unwrap(),
+        params: params,
+        args: resolved_args(matches),
+    };
+    write_json(filename, &lock).expect("Failed to write lock file");
+}
+
+/// Writes the metadata sidecar of an output image: the seed, output type,
+/// projection, size and format it was generated with, the terrain parameters
+/// and the planet's fixed constants.  Unlike a lock file it isn't read back.
+pub fn write_metadata(filename: &str,
+                      metadata: &Metadata,
+                      seed: i32,
+                      projection: Projection,
+                      width: usize,
+                      height: usize) {
+    let sidecar = Sidecar {
+        version: crate_version!(),
+        seed: seed,
+        output_type: metadata.output_type,
+        projection: format!("{:?}", projection),
+        width: width,
+        height: height,
+        format: metadata.format,
+        params: &metadata.params,
+        constants: Constants {
+            max_elev: MAX_ELEV,
+            planet_circumference: PLANET_CIRCUMFERENCE,
+            sea_level: SEA_LEVEL,
+        },
+    };
+    write_json(filename, &sidecar).expect("Failed to write metadata file");
+}
+
+/// Reads the arguments recorded in a lock file.
+pub fn read_lock_args(filename: &str) -> io::Result<Vec<String>> {
+    let file = File::open(Path::new(filename))?;
+    let lock: LockArgs = serde_json::from_reader(BufReader::new(file))?;
+    Ok(lock.args)
+}
@@ -20,1585 +20,459 @@
 
 #[macro_use]
 extern crate clap;
+extern crate complexplanet;
 extern crate image;
+#[cfg(test)]
+extern crate image_webp;
 extern crate noise;
-
-use std::fs::File;
-use std::io::BufWriter;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+mod annotate;
+mod basins;
+mod batch;
+mod bits;
+mod branches;
+mod canvas;
+mod config;
+mod contact_sheet;
+mod contours;
+mod dds;
+mod diff;
+mod gnomonic;
+mod hypsograph;
+mod indexed_png;
+mod insolation;
+mod legend;
+mod lock;
+mod mapping;
+mod mesh;
+mod moisture;
+mod mollweide;
+mod normals;
+mod occlusion;
+mod orthographic;
+mod palette;
+mod passport;
+mod planar;
+mod png_stream;
+mod profile;
+mod region;
+mod search;
+mod stats;
+mod style;
+mod sunlight;
+mod temperature;
+mod vector_field;
+mod vrt;
+mod webp;
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
 use std::path::Path;
-use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread::JoinHandle;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use image::ColorType;
 use image::png::PNGEncoder;
-use noise::module::{Add, Billow, Blend, Cache, Clamp, Constant, Curve, Exponent, Max, Min, Module,
-                    Multiply, Perlin, RidgedMulti, ScaleBias, Select, Terrace, Turbulence, Voronoi};
-use noise::noisegen::NoiseQuality;
-
-////////////////////////////////////////////////////////////////////////////
-// Constants
-//
-// Modify these constants to change the terrain of the planet and to change
-// the boundaries and size of the elevation grid.
-//
-// Note: "Planetary elevation units" range from -1.0 (for the lowest
-// underwater trenches) to +1.0 (for the highest mountain peaks.)
-//
-
-// Frequency of the planet's continents.  Higher frequency produces smaller,
-// more numerous continents.  This value is measured in radians.
-const CONTINENT_FREQUENCY: f64 = 1.0;
-
-// Lacunarity of the planet's continents.  Changing this value produces
-// slightly different continents.  For the best results, this value should
-// be random, but close to 2.0.
-const CONTINENT_LACUNARITY: f64 = 2.208984375;
-
-// Lacunarity of the planet's mountains.  Changing this value produces
-// slightly different mountains.  For the best results, this value should
-// be random, but close to 2.0.
-const MOUNTAIN_LACUNARITY: f64 = 2.142578125;
-
-// Lacunarity of the planet's hills.  Changing this value produces slightly
-// different hills.  For the best results, this value should be random, but
-// close to 2.0.
-const HILLS_LACUNARITY: f64 = 2.162109375;
-
-// Lacunarity of the planet's plains.  Changing this value produces slightly
-// different plains.  For the best results, this value should be random, but
-// close to 2.0.
-const PLAINS_LACUNARITY: f64 = 2.314453125;
-
-// Lacunarity of the planet's badlands.  Changing this value produces
-// slightly different badlands.  For the best results, this value should be
-// random, but close to 2.0.
-const BADLANDS_LACUNARITY: f64 = 2.212890625;
-
-// Specifies the "twistiness" of the mountains.
-const MOUNTAINS_TWIST: f64 = 1.0;
-
-// Specifies the "twistiness" of the hills.
-const HILLS_TWIST: f64 = 1.0;
-
-// Specifies the "twistiness" of the badlands.
-const BADLANDS_TWIST: f64 = 1.0;
-
-// Specifies the planet's sea level.  This value must be between -1.0
-// (minimum planet elevation) and +1.0 (maximum planet elevation.)
-const SEA_LEVEL: f64 = 0.0;
-
-// Specifies the level on the planet in which continental shelves appear.
-// This value must be between -1.0 (minimum planet elevation) and +1.0
-// (maximum planet elevation), and must be less than SEA_LEVEL.
-const SHELF_LEVEL: f64 = -0.375;
-
-// Determines the amount of mountainous terrain that appears on the
-// planet.  Values range from 0.0 (no mountains) to 1.0 (all terrain is
-// covered in mountains).  Mountainous terrain will overlap hilly terrain.
-// Because the badlands terrain may overlap parts of the mountainous
-// terrain, setting MOUNTAINS_AMOUNT to 1.0 may not completely cover the
-// terrain in mountains.
-const MOUNTAINS_AMOUNT: f64 = 0.5;
-
-// Determines the amount of hilly terrain that appears on the planet.
-// Values range from 0.0 (no hills) to 1.0 (all terrain is covered in
-// hills).  This value must be less than MOUNTAINS_AMOUNT.  Because the
-// mountainous terrain will overlap parts of the hilly terrain, and
-// the badlands terrain may overlap parts of the hilly terrain, setting
-// HILLS_AMOUNT to 1.0 may not completely cover the terrain in hills.
-const HILLS_AMOUNT: f64 = (1.0 + MOUNTAINS_AMOUNT) / 2.0;
-
-// Determines the amount of badlands terrain that covers the planet.
-// Values range from 0.0 (no badlands) to 1.0 (all terrain is covered in
-// badlands.)  Badlands terrain will overlap any other type of terrain.
-const BADLANDS_AMOUNT: f64 = 0.03125;
-
-// Offset to apply to the terrain type definition.  Low values (< 1.0) cause
-// the rough areas to appear only at high elevations.  High values (> 2.0)
-// cause the rough areas to appear at any elevation.  The percentage of
-// rough areas on the planet are independent of this value.
-const TERRAIN_OFFSET: f64 = 1.0;
-
-// Specifies the amount of "glaciation" on the mountains.  This value
-// should be close to 1.0 and greater than 1.0.
-const MOUNTAIN_GLACIATION: f64 = 1.375;
-
-// Scaling to apply to the base continent elevations, in planetary elevation
-// units.
-const CONTINENT_HEIGHT_SCALE: f64 = (1.0 - SEA_LEVEL) / 4.0;
-
-// Maximum depth of the rivers, in planetary elevation units.
-const RIVER_DEPTH: f64 = 0.0234375;
-
-fn create_generator(seed: i32) -> Box<Module> {
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: continent definition
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: base continent definition (7 noise modules)
-    //
-    // This subgroup roughly defines the positions and base elevations of the
-    // planet's continents.
-    //
-    // The "base elevation" is the elevation of the terrain before any terrain
-    // features (mountains, hills, etc.) are placed on that terrain.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
-
-    // 1: [Continent module]: This Perlin-noise module generates the continents.
-    //    This noise module has a high number of octaves so that detail is
-    //    visible at high zoom levels.
-    let mut base_continent_def_pe0 = Perlin::new();
-    base_continent_def_pe0.set_seed(seed + 0);
-    base_continent_def_pe0.set_frequency(CONTINENT_FREQUENCY);
-    base_continent_def_pe0.set_persistence(0.5);
-    base_continent_def_pe0.set_lacunarity(CONTINENT_LACUNARITY);
-    base_continent_def_pe0.set_octave_count(14);
-    base_continent_def_pe0.set_quality(NoiseQuality::Standard);
-
-    // 2: [Continent-with-ranges module]: Next, a curve module modifies the
-    //    output value from the continent module so that very high values appear
-    //    near sea level.  This defines the positions of the mountain ranges.
-    let mut base_continent_def_cu = Curve::new(base_continent_def_pe0.clone());
-    base_continent_def_cu.add_control_point(-2.0000 + SEA_LEVEL, -1.625 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(-1.0000 + SEA_LEVEL, -1.375 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(0.0000 + SEA_LEVEL, -0.375 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(0.0625 + SEA_LEVEL, 0.125 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(0.1250 + SEA_LEVEL, 0.250 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(0.2500 + SEA_LEVEL, 1.000 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(0.5000 + SEA_LEVEL, 0.250 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(0.7500 + SEA_LEVEL, 0.250 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(1.0000 + SEA_LEVEL, 0.500 + SEA_LEVEL);
-    base_continent_def_cu.add_control_point(2.0000 + SEA_LEVEL, 0.500 + SEA_LEVEL);
-
-    // 3: [Carver module]: This higher-frequency Perlin-noise module will be
-    //    used by subsequent noise modules to carve out chunks from the mountain
-    //    ranges within the continent-with-ranges module so that the mountain
-    //    ranges will not be complely impassible.
-    let mut base_continent_def_pe1 = Perlin::new();
-    base_continent_def_pe1.set_seed(seed + 1);
-    base_continent_def_pe1.set_frequency(CONTINENT_FREQUENCY * 4.34375);
-    base_continent_def_pe1.set_persistence(0.5);
-    base_continent_def_pe1.set_lacunarity(CONTINENT_LACUNARITY);
-    base_continent_def_pe1.set_octave_count(11);
-    base_continent_def_pe1.set_quality(NoiseQuality::Standard);
-
-    // 4: [Scaled-carver module]: This scale/bias module scales the output
-    //    value from the carver module such that it is usually near 1.0.  This
-    //    is required for step 5.
-    let mut base_continent_def_sb = ScaleBias::new(base_continent_def_pe1.clone());
-    base_continent_def_sb.set_scale(0.375);
-    base_continent_def_sb.set_bias(0.625);
-
-    // 5: [Carved-continent module]: This minimum-value module carves out chunks
-    //    from the continent-with-ranges module.  It does this by ensuring that
-    //    only the minimum of the output values from the scaled-carver module
-    //    and the continent-with-ranges module contributes to the output value
-    //    of this subgroup.  Most of the time, the minimum-value module will
-    //    select the output value from the continents-with-ranges module since
-    //    the output value from the scaled-carver module is usually near 1.0.
-    //    Occasionally, the output value from the scaled-carver module will be
-    //    less than the output value from the continent-with-ranges module, so
-    //    in this case, the output value from the scaled-carver module is
-    //    selected.
-    let base_continent_def_mi = Min::new(base_continent_def_sb.clone(),
-                                         base_continent_def_cu.clone());
-
-    // 6: [Clamped-continent module]: Finally, a clamp module modifies the
-    //    carved-continent module to ensure that the output value of this
-    //    subgroup is between -1.0 and 1.0.
-    let mut base_continent_def_cl = Clamp::new(base_continent_def_mi.clone());
-    base_continent_def_cl.set_bounds(-1.0, 1.0);
-
-    // 7: [Base-continent-definition subgroup]: Caches the output value from the
-    //    clamped-continent module.
-    let base_continent_def: Rc<Module> = Rc::new(Cache::new(base_continent_def_cl.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continent definition (5 noise modules)
-    //
-    // This subgroup warps the output value from the the base-continent-
-    // definition subgroup, producing more realistic terrain.
-    //
-    // Warping the base continent definition produces lumpier terrain with
-    // cliffs and rifts.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
-
-    // 1: [Coarse-turbulence module]: This turbulence module warps the output
-    //    value from the base-continent-definition subgroup, adding some coarse
-    //    detail to it.
-    let mut continent_def_tu0 = Turbulence::new(base_continent_def.clone());
-    continent_def_tu0.set_seed(seed + 10);
-    continent_def_tu0.set_frequency(CONTINENT_FREQUENCY * 15.25);
-    continent_def_tu0.set_power(CONTINENT_FREQUENCY / 113.75);
-    continent_def_tu0.set_roughness(13);
-
-    // 2: [Intermediate-turbulence module]: This turbulence module warps the
-    //    output value from the coarse-turbulence module.  This turbulence has
-    //    a higher frequency, but lower power, than the coarse-turbulence
-    //    module, adding some intermediate detail to it.
-    let mut continent_def_tu1 = Turbulence::new(continent_def_tu0.clone());
-    continent_def_tu1.set_seed(seed + 11);
-    continent_def_tu1.set_frequency(CONTINENT_FREQUENCY * 47.25);
-    continent_def_tu1.set_power(CONTINENT_FREQUENCY / 433.75);
-    continent_def_tu1.set_roughness(12);
-
-    // 3: [Warped-base-continent-definition module]: This turbulence module
-    //    warps the output value from the intermediate-turbulence module.  This
-    //    turbulence has a higher frequency, but lower power, than the
-    //    intermediate-turbulence module, adding some fine detail to it.
-    let mut continent_def_tu2 = Turbulence::new(continent_def_tu1.clone());
-    continent_def_tu2.set_seed(seed + 12);
-    continent_def_tu2.set_frequency(CONTINENT_FREQUENCY * 95.25);
-    continent_def_tu2.set_power(CONTINENT_FREQUENCY / 1019.75);
-    continent_def_tu2.set_roughness(11);
-
-    // 4: [Select-turbulence module]: At this stage, the turbulence is applied
-    //    to the entire base-continent-definition subgroup, producing some very
-    //    rugged, unrealistic coastlines.  This selector module selects the
-    //    output values from the (unwarped) base-continent-definition subgroup
-    //    and the warped-base-continent-definition module, based on the output
-    //    value from the (unwarped) base-continent-definition subgroup.  The
-    //    selection boundary is near sea level and has a relatively smooth
-    //    transition.  In effect, only the higher areas of the base-continent-
-    //    definition subgroup become warped; the underwater and coastal areas
-    //    remain unaffected.
-    let mut continent_def_se = Select::new(base_continent_def.clone(),
-                                           continent_def_tu2.clone(),
-                                           base_continent_def.clone());
-    continent_def_se.set_bounds(SEA_LEVEL - 0.0375, SEA_LEVEL + 1000.0375);
-    continent_def_se.set_edge_falloff(0.0625);
-
-    // 7: [Continent-definition group]: Caches the output value from the
-    //    clamped-continent module.  This is the output value for the entire
-    //    continent-definition group.
-    let continent_def: Rc<Module> = Rc::new(Cache::new(continent_def_se.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: terrain type definition
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: terrain type definition (3 noise modules)
-    //
-    // This subgroup defines the positions of the terrain types on the planet.
-    //
-    // Terrain types include, in order of increasing roughness, plains, hills,
-    // and mountains.
-    //
-    // This subgroup's output value is based on the output value from the
-    // continent-definition group.  Rougher terrain mainly appears at higher
-    // elevations.
-    //
-    // -1.0 represents the smoothest terrain types (plains and underwater) and
-    // +1.0 represents the roughest terrain types (mountains).
-    //
-
-    // 1: [Warped-continent module]: This turbulence module slightly warps the
-    //    output value from the continent-definition group.  This prevents the
-    //    rougher terrain from appearing exclusively at higher elevations.
-    //    Rough areas may now appear in the the ocean, creating rocky islands
-    //    and fjords.
-    let mut terrain_type_def_tu = Turbulence::new(continent_def.clone());
-    terrain_type_def_tu.set_seed(seed + 20);
-    terrain_type_def_tu.set_frequency(CONTINENT_FREQUENCY * 18.125);
-    terrain_type_def_tu.set_power(CONTINENT_FREQUENCY / 20.59375 * TERRAIN_OFFSET);
-    terrain_type_def_tu.set_roughness(3);
-
-    // 2: [Roughness-probability-shift module]: This terracing module sharpens
-    //    the edges of the warped-continent module near sea level and lowers
-    //    the slope towards the higher-elevation areas.  This shrinks the areas
-    //    in which the rough terrain appears, increasing the "rarity" of rough
-    //    terrain.
-    let mut terrain_type_def_te = Terrace::new(terrain_type_def_tu.clone());
-    terrain_type_def_te.add_control_point(-1.00);
-    terrain_type_def_te.add_control_point(SHELF_LEVEL + SEA_LEVEL / 2.0);
-    terrain_type_def_te.add_control_point(1.00);
-
-    // 3: [Terrain-type-definition group]: Caches the output value from the
-    //    roughness-probability-shift module.  This is the output value for
-    //    the entire terrain-type-definition group.
-    let terrain_type_def: Rc<Module> = Rc::new(Cache::new(terrain_type_def_te.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: mountainous terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: mountain base definition (9 noise modules)
-    //
-    // This subgroup generates the base-mountain elevations.  Other subgroups
-    // will add the ridges and low areas to the base elevations.
-    //
-    // -1.0 represents low mountainous terrain and +1.0 represents high
-    // mountainous terrain.
-    //
-
-    // 1: [Mountain-ridge module]: This ridged-multifractal-noise module
-    //    generates the mountain ridges.
-    let mut mountain_base_def_rm0 = RidgedMulti::new();
-    mountain_base_def_rm0.set_seed(seed + 30);
-    mountain_base_def_rm0.set_frequency(1723.0);
-    mountain_base_def_rm0.set_lacunarity(MOUNTAIN_LACUNARITY);
-    mountain_base_def_rm0.set_octave_count(4);
-    mountain_base_def_rm0.set_quality(NoiseQuality::Standard);
-
-    // 2: [Scaled-mountain-ridge module]: Next, a scale/bias module scales the
-    //    output value from the mountain-ridge module so that its ridges are not
-    //    too high.  The reason for this is that another subgroup adds actual
-    //    mountainous terrain to these ridges.
-    let mut mountain_base_def_sb0 = ScaleBias::new(mountain_base_def_rm0.clone());
-    mountain_base_def_sb0.set_scale(0.5);
-    mountain_base_def_sb0.set_bias(0.375);
-
-    // 3: [River-valley module]: This ridged-multifractal-noise module generates
-    //    the river valleys.  It has a much lower frequency than the mountain-
-    //    ridge module so that more mountain ridges will appear outside of the
-    //    valleys.  Note that this noise module generates ridged-multifractal
-    //    noise using only one octave; this information will be important in the
-    //    next step.
-    let mut mountain_base_def_rm1 = RidgedMulti::new();
-    mountain_base_def_rm1.set_seed(seed + 31);
-    mountain_base_def_rm1.set_frequency(367.0);
-    mountain_base_def_rm1.set_lacunarity(MOUNTAIN_LACUNARITY);
-    mountain_base_def_rm1.set_octave_count(1);
-    mountain_base_def_rm1.set_quality(NoiseQuality::Best);
-
-    // 4: [Scaled-river-valley module]: Next, a scale/bias module applies a
-    //    scaling factor of -2.0 to the output value from the river-valley
-    //    module.  This stretches the possible elevation values because one-
-    //    octave ridged-multifractal noise has a lower range of output values
-    //    than multiple-octave ridged-multifractal noise.  The negative scaling
-    //    factor inverts the range of the output value, turning the ridges from
-    //    the river-valley module into valleys.
-    let mut mountain_base_def_sb1 = ScaleBias::new(mountain_base_def_rm1.clone());
-    mountain_base_def_sb1.set_scale(-2.0);
-    mountain_base_def_sb1.set_bias(-0.5);
-
-    // 5: [Low-flat module]: This low constant value is used by step 6.
-    let mut mountain_base_def_co = Constant::new();
-    mountain_base_def_co.set_const_value(-1.0);
-
-    // 6: [Mountains-and-valleys module]: This blender module merges the
-    //    scaled-mountain-ridge module and the scaled-river-valley module
-    //    together.  It causes the low-lying areas of the terrain to become
-    //    smooth, and causes the high-lying areas of the terrain to contain
-    //    ridges.  To do this, it uses the scaled-river-valley module as the
-    //    control module, causing the low-flat module to appear in the lower
-    //    areas and causing the scaled-mountain-ridge module to appear in the
-    //    higher areas.
-    let mountain_base_def_bl = Blend::new(mountain_base_def_co.clone(),
-                                          mountain_base_def_sb0.clone(),
-                                          mountain_base_def_sb1.clone());
-
-    // 7: [Coarse-turbulence module]: This turbulence module warps the output
-    //    value from the mountain-and-valleys module, adding some coarse detail
-    //    to it.
-    let mut mountain_base_def_tu0 = Turbulence::new(mountain_base_def_bl.clone());
-    mountain_base_def_tu0.set_seed(seed + 32);
-    mountain_base_def_tu0.set_frequency(1337.0);
-    mountain_base_def_tu0.set_power(1.0 / 6730.0 * MOUNTAINS_TWIST);
-    mountain_base_def_tu0.set_roughness(4);
-
-    // 8: [Warped-mountains-and-valleys module]: This turbulence module warps
-    //    the output value from the coarse-turbulence module.  This turbulence
-    //    has a higher frequency, but lower power, than the coarse-turbulence
-    //    module, adding some fine detail to it.
-    let mut mountain_base_def_tu1 = Turbulence::new(mountain_base_def_tu0.clone());
-    mountain_base_def_tu1.set_seed(seed + 33);
-    mountain_base_def_tu1.set_frequency(21221.0);
-    mountain_base_def_tu1.set_power(1.0 / 120157.0 * MOUNTAINS_TWIST);
-    mountain_base_def_tu1.set_roughness(6);
-
-    // 9: [Mountain-base-definition subgroup]: Caches the output value from the
-    //    warped-mountains-and-valleys module.
-    let mountain_base_def: Rc<Module> = Rc::new(Cache::new(mountain_base_def_tu1.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: high mountainous terrain (5 noise modules)
-    //
-    // This subgroup generates the mountainous terrain that appears at high
-    // elevations within the mountain ridges.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
-
-    // 1: [Mountain-basis-0 module]: This ridged-multifractal-noise module,
-    //    along with the mountain-basis-1 module, generates the individual
-    //    mountains.
-    let mut mountainous_high_rm0 = RidgedMulti::new();
-    mountainous_high_rm0.set_seed(seed + 40);
-    mountainous_high_rm0.set_frequency(2371.0);
-    mountainous_high_rm0.set_lacunarity(MOUNTAIN_LACUNARITY);
-    mountainous_high_rm0.set_octave_count(3);
-    mountainous_high_rm0.set_quality(NoiseQuality::Best);
-
-    // 2: [Mountain-basis-1 module]: This ridged-multifractal-noise module,
-    //    along with the mountain-basis-0 module, generates the individual
-    //    mountains.
-    let mut mountainous_high_rm1 = RidgedMulti::new();
-    mountainous_high_rm1.set_seed(seed + 41);
-    mountainous_high_rm1.set_frequency(2341.0);
-    mountainous_high_rm1.set_lacunarity(MOUNTAIN_LACUNARITY);
-    mountainous_high_rm1.set_octave_count(3);
-    mountainous_high_rm1.set_quality(NoiseQuality::Best);
-
-    // 3: [High-mountains module]: Next, a maximum-value module causes more
-    //    mountains to appear at the expense of valleys.  It does this by
-    //    ensuring that only the maximum of the output values from the two
-    //    ridged-multifractal-noise modules contribute to the output value of
-    //    this subgroup.
-    let mountainous_high_ma = Max::new(mountainous_high_rm0.clone(), mountainous_high_rm1.clone());
-
-    // 4: [Warped-high-mountains module]: This turbulence module warps the
-    //    output value from the high-mountains module, adding some detail to it.
-    let mut mountainous_high_tu = Turbulence::new(mountainous_high_ma.clone());
-    mountainous_high_tu.set_seed(seed + 42);
-    mountainous_high_tu.set_frequency(31511.0);
-    mountainous_high_tu.set_power(1.0 / 180371.0 * MOUNTAINS_TWIST);
-    mountainous_high_tu.set_roughness(4);
-
-    // 5: [High-mountainous-terrain subgroup]: Caches the output value from the
-    //    warped-high-mountains module.
-    let mountainous_high: Rc<Module> = Rc::new(Cache::new(mountainous_high_tu.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: low mountainous terrain (4 noise modules)
-    //
-    // This subgroup generates the mountainous terrain that appears at low
-    // elevations within the river valleys.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
-
-    // 1: [Lowland-basis-0 module]: This ridged-multifractal-noise module,
-    //    along with the lowland-basis-1 module, produces the low mountainous
-    //    terrain.
-    let mut mountainous_low_rm0 = RidgedMulti::new();
-    mountainous_low_rm0.set_seed(seed + 50);
-    mountainous_low_rm0.set_frequency(1381.0);
-    mountainous_low_rm0.set_lacunarity(MOUNTAIN_LACUNARITY);
-    mountainous_low_rm0.set_octave_count(8);
-    mountainous_low_rm0.set_quality(NoiseQuality::Best);
-
-    // 1: [Lowland-basis-1 module]: This ridged-multifractal-noise module,
-    //    along with the lowland-basis-0 module, produces the low mountainous
-    //    terrain.
-    let mut mountainous_low_rm1 = RidgedMulti::new();
-    mountainous_low_rm1.set_seed(seed + 51);
-    mountainous_low_rm1.set_frequency(1427.0);
-    mountainous_low_rm1.set_lacunarity(MOUNTAIN_LACUNARITY);
-    mountainous_low_rm1.set_octave_count(8);
-    mountainous_low_rm1.set_quality(NoiseQuality::Best);
-
-    // 3: [Low-mountainous-terrain module]: This multiplication module combines
-    //    the output values from the two ridged-multifractal-noise modules.
-    //    This causes the following to appear in the resulting terrain:
-    //    - Cracks appear when two negative output values are multiplied
-    //      together.
-    //    - Flat areas appear when a positive and a negative output value are
-    //      multiplied together.
-    //    - Ridges appear when two positive output values are multiplied
-    //      together.
-    let mountainous_low_mu = Multiply::new(mountainous_low_rm0.clone(),
-                                           mountainous_low_rm1.clone());
-
-    // 4: [Low-mountainous-terrain subgroup]: Caches the output value from the
-    //    low-moutainous-terrain module.
-    let mountainous_low: Rc<Module> = Rc::new(Cache::new(mountainous_low_mu.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: mountainous terrain (7 noise modules)
-    //
-    // This subgroup generates the final mountainous terrain by combining the
-    // high-mountainous-terrain subgroup with the low-mountainous-terrain
-    // subgroup.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
-
-    // 1: [Scaled-low-mountainous-terrain module]: First, this scale/bias module
-    //    scales the output value from the low-mountainous-terrain subgroup to a
-    //    very low value and biases it towards -1.0.  This results in the low
-    //    mountainous areas becoming more-or-less flat with little variation.
-    //    This will also result in the low mountainous areas appearing at the
-    //    lowest elevations in this subgroup.
-    let mut mountainous_terrain_sb0 = ScaleBias::new(mountainous_low.clone());
-    mountainous_terrain_sb0.set_scale(0.03125);
-    mountainous_terrain_sb0.set_bias(-0.96875);
-
-    // 2: [Scaled-high-mountainous-terrain module]: Next, this scale/bias module
-    //    scales the output value from the high-mountainous-terrain subgroup to
-    //    1/4 of its initial value and biases it so that its output value is
-    //    usually positive.
-    let mut mountainous_terrain_sb1 = ScaleBias::new(mountainous_high.clone());
-    mountainous_terrain_sb1.set_scale(0.25);
-    mountainous_terrain_sb1.set_bias(0.25);
-
-    // 3: [Added-high-mountainous-terrain module]: This addition module adds the
-    //    output value from the scaled-high-mountainous-terrain module to the
-    //    output value from the mountain-base-definition subgroup.  Mountains
-    //    now appear all over the terrain.
-    let mountainous_terrain_ad = Add::new(mountainous_terrain_sb1.clone(),
-                                          mountain_base_def.clone());
-
-    // 4: [Combined-mountainous-terrain module]: Note that at this point, the
-    //    entire terrain is covered in high mountainous terrain, even at the low
-    //    elevations.  To make sure the mountains only appear at the higher
-    //    elevations, this selector module causes low mountainous terrain to
-    //    appear at the low elevations (within the valleys) and the high
-    //    mountainous terrain to appear at the high elevations (within the
-    //    ridges.)  To do this, this noise module selects the output value from
-    //    the added-high-mountainous-terrain module if the output value from the
-    //    mountain-base-definition subgroup is higher than a set amount.
-    //    Otherwise, this noise module selects the output value from the scaled-
-    //    low-mountainous-terrain module.
-    let mut mountainous_terrain_se = Select::new(mountainous_terrain_sb0.clone(),
-                                                 mountainous_terrain_ad.clone(),
-                                                 mountain_base_def.clone());
-    mountainous_terrain_se.set_bounds(-0.5, 999.5);
-    mountainous_terrain_se.set_edge_falloff(0.5);
-
-    // 5: [Scaled-mountainous-terrain-module]: This scale/bias module slightly
-    //    reduces the range of the output value from the combined-mountainous-
-    //    terrain module, decreasing the heights of the mountain peaks.
-    let mut mountainous_terrain_sb2 = ScaleBias::new(mountainous_terrain_se.clone());
-    mountainous_terrain_sb2.set_scale(0.8);
-    mountainous_terrain_sb2.set_bias(0.0);
-
-    // 6: [Glaciated-mountainous-terrain-module]: This exponential-curve module
-    //    applies an exponential curve to the output value from the scaled-
-    //    mountainous-terrain module.  This causes the slope of the mountains to
-    //    smoothly increase towards higher elevations, as if a glacier grinded
-    //    out those mountains.  This exponential-curve module expects the output
-    //    value to range from -1.0 to +1.0.
-    let mut mountainous_terrain_ex = Exponent::new(mountainous_terrain_sb2.clone());
-    mountainous_terrain_ex.set_exponent(MOUNTAIN_GLACIATION);
-
-    // 7: [Mountainous-terrain group]: Caches the output value from the
-    //    glaciated-mountainous-terrain module.  This is the output value for
-    //    the entire mountainous-terrain group.
-    let mountainous_terrain: Rc<Module> = Rc::new(Cache::new(mountainous_terrain_ex.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: hilly terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: hilly terrain (11 noise modules)
-    //
-    // This subgroup generates the hilly terrain.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
-
-    // 1: [Hills module]: This billow-noise module generates the hills.
-    let mut hilly_terrain_bi = Billow::new();
-    hilly_terrain_bi.set_seed(seed + 60);
-    hilly_terrain_bi.set_frequency(1663.0);
-    hilly_terrain_bi.set_persistence(0.5);
-    hilly_terrain_bi.set_lacunarity(HILLS_LACUNARITY);
-    hilly_terrain_bi.set_octave_count(6);
-    hilly_terrain_bi.set_quality(NoiseQuality::Best);
-
-    // 2: [Scaled-hills module]: Next, a scale/bias module scales the output
-    //    value from the hills module so that its hilltops are not too high.
-    //    The reason for this is that these hills are eventually added to the
-    //    river valleys (see below.)
-    let mut hilly_terrain_sb0 = ScaleBias::new(hilly_terrain_bi.clone());
-    hilly_terrain_sb0.set_scale(0.5);
-    hilly_terrain_sb0.set_bias(0.5);
-
-    // 3: [River-valley module]: This ridged-multifractal-noise module generates
-    //    the river valleys.  It has a much lower frequency so that more hills
-    //    will appear in between the valleys.  Note that this noise module
-    //    generates ridged-multifractal noise using only one octave; this
-    //    information will be important in the next step.
-    let mut hilly_terrain_rm = RidgedMulti::new();
-    hilly_terrain_rm.set_seed(seed + 61);
-    hilly_terrain_rm.set_frequency(367.5);
-    hilly_terrain_rm.set_lacunarity(HILLS_LACUNARITY);
-    hilly_terrain_rm.set_quality(NoiseQuality::Best);
-    hilly_terrain_rm.set_octave_count(1);
-
-    // 4: [Scaled-river-valley module]: Next, a scale/bias module applies a
-    //    scaling factor of -2.0 to the output value from the river-valley
-    //    module.  This stretches the possible elevation values because one-
-    //    octave ridged-multifractal noise has a lower range of output values
-    //    than multiple-octave ridged-multifractal noise.  The negative scaling
-    //    factor inverts the range of the output value, turning the ridges from
-    //    the river-valley module into valleys.
-    let mut hilly_terrain_sb1 = ScaleBias::new(hilly_terrain_rm.clone());
-    hilly_terrain_sb1.set_scale(-2.0);
-    hilly_terrain_sb1.set_bias(-0.5);
-
-    // 5: [Low-flat module]: This low constant value is used by step 6.
-    let mut hilly_terrain_co = Constant::new();
-    hilly_terrain_co.set_const_value(-1.0);
-
-    // 6: [Mountains-and-valleys module]: This blender module merges the
-    //    scaled-hills module and the scaled-river-valley module together.  It
-    //    causes the low-lying areas of the terrain to become smooth, and causes
-    //    the high-lying areas of the terrain to contain hills.  To do this, it
-    //    uses the scaled-hills module as the control module, causing the low-
-    //    flat module to appear in the lower areas and causing the scaled-river-
-    //    valley module to appear in the higher areas.
-    let hilly_terrain_bl = Blend::new(hilly_terrain_co.clone(),
-                                      hilly_terrain_sb1.clone(),
-                                      hilly_terrain_sb0.clone());
-
-    // 7: [Scaled-hills-and-valleys module]: This scale/bias module slightly
-    //    reduces the range of the output value from the hills-and-valleys
-    //    module, decreasing the heights of the hilltops.
-    let mut hilly_terrain_sb2 = ScaleBias::new(hilly_terrain_bl.clone());
-    hilly_terrain_sb2.set_scale(0.75);
-    hilly_terrain_sb2.set_bias(-0.25);
-
-    // 8: [Increased-slope-hilly-terrain module]: To increase the hill slopes at
-    //    higher elevations, this exponential-curve module applies an
-    //    exponential curve to the output value the scaled-hills-and-valleys
-    //    module.  This exponential-curve module expects the input value to
-    //    range from -1.0 to 1.0.
-    let mut hilly_terrain_ex = Exponent::new(hilly_terrain_sb2.clone());
-    hilly_terrain_ex.set_exponent(1.375);
-
-    // 9: [Coarse-turbulence module]: This turbulence module warps the output
-    //    value from the increased-slope-hilly-terrain module, adding some
-    //    coarse detail to it.
-    let mut hilly_terrain_tu0 = Turbulence::new(hilly_terrain_ex.clone());
-    hilly_terrain_tu0.set_seed(seed + 62);
-    hilly_terrain_tu0.set_frequency(1531.0);
-    hilly_terrain_tu0.set_power(1.0 / 16921.0 * HILLS_TWIST);
-    hilly_terrain_tu0.set_roughness(4);
-
-    // 10: [Warped-hilly-terrain module]: This turbulence module warps the
-    //     output value from the coarse-turbulence module.  This turbulence has
-    //     a higher frequency, but lower power, than the coarse-turbulence
-    //     module, adding some fine detail to it.
-    let mut hilly_terrain_tu1 = Turbulence::new(hilly_terrain_tu0.clone());
-    hilly_terrain_tu1.set_seed(seed + 63);
-    hilly_terrain_tu1.set_frequency(21617.0);
-    hilly_terrain_tu1.set_power(1.0 / 117529.0 * HILLS_TWIST);
-    hilly_terrain_tu1.set_roughness(6);
-
-    // 11: [Hilly-terrain group]: Caches the output value from the warped-hilly-
-    //     terrain module.  This is the output value for the entire hilly-
-    //     terrain group.
-    let hilly_terrain: Rc<Module> = Rc::new(Cache::new(hilly_terrain_tu1.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: plains terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: plains terrain (7 noise modules)
-    //
-    // This subgroup generates the plains terrain.
-    //
-    // Because this subgroup will eventually be flattened considerably, the
-    // types and combinations of noise modules that generate the plains are not
-    // really that important; they only need to "look" interesting.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
+use noise::module::Module;
+
+use complexplanet::{clamp, create_generator, create_layers, create_moisture_generator, cph,
+                    f64_clamp, lat_lon_to_pos, sample_sphere, DebugGroup, Detail, PlanetParams,
+                    Quality, TerrainGroup, MAX_ELEV, MAX_RIVER_ORDERS, PLANET_CIRCUMFERENCE,
+                    SEA_LEVEL, TERRAIN_EDGE_FALLOFF};
+use complexplanet::shared::SharedModule;
+use cph::Projection;
+use palette::{Gradient, PaletteMethod};
+use region::Region;
+use style::Style;
+use stats::ElevationStats;
+use sunlight::SunPosition;
+use temperature::TemperatureModel;
 
-    // 1: [Plains-basis-0 module]: This billow-noise module, along with the
-    //    plains-basis-1 module, produces the plains.
-    let mut plains_terrain_bi0 = Billow::new();
-    plains_terrain_bi0.set_seed(seed + 70);
-    plains_terrain_bi0.set_frequency(1097.5);
-    plains_terrain_bi0.set_persistence(0.5);
-    plains_terrain_bi0.set_lacunarity(PLAINS_LACUNARITY);
-    plains_terrain_bi0.set_octave_count(8);
-    plains_terrain_bi0.set_quality(NoiseQuality::Best);
-
-    // 2: [Positive-plains-basis-0 module]: This scale/bias module makes the
-    //    output value from the plains-basis-0 module positive since this output
-    //    value will be multiplied together with the positive-plains-basis-1
-    //    module.
-    let mut plains_terrain_sb0 = ScaleBias::new(plains_terrain_bi0.clone());
-    plains_terrain_sb0.set_scale(0.5);
-    plains_terrain_sb0.set_bias(0.5);
-
-    // 3: [Plains-basis-1 module]: This billow-noise module, along with the
-    //    plains-basis-2 module, produces the plains.
-    let mut plains_terrain_bi1 = Billow::new();
-    plains_terrain_bi1.set_seed(seed + 71);
-    plains_terrain_bi1.set_frequency(1319.5);
-    plains_terrain_bi1.set_persistence(0.5);
-    plains_terrain_bi1.set_lacunarity(PLAINS_LACUNARITY);
-    plains_terrain_bi1.set_octave_count(8);
-    plains_terrain_bi1.set_quality(NoiseQuality::Best);
-
-    // 4: [Positive-plains-basis-1 module]: This scale/bias module makes the
-    //    output value from the plains-basis-1 module positive since this output
-    //    value will be multiplied together with the positive-plains-basis-0
-    //    module.
-    let mut plains_terrain_sb1 = ScaleBias::new(plains_terrain_bi1.clone());
-    plains_terrain_sb1.set_scale(0.5);
-    plains_terrain_sb1.set_bias(0.5);
-
-    // 5: [Combined-plains-basis module]: This multiplication module combines
-    //    the two plains basis modules together.
-    let plains_terrain_mu = Multiply::new(plains_terrain_sb0.clone(), plains_terrain_sb1.clone());
-
-    // 6: [Rescaled-plains-basis module]: This scale/bias module maps the output
-    //    value that ranges from 0.0 to 1.0 back to a value that ranges from
-    //    -1.0 to +1.0.
-    let mut plains_terrain_sb2 = ScaleBias::new(plains_terrain_mu.clone());
-    plains_terrain_sb2.set_scale(2.0);
-    plains_terrain_sb2.set_bias(-1.0);
-
-    // 7: [Plains-terrain group]: Caches the output value from the rescaled-
-    //    plains-basis module.  This is the output value for the entire plains-
-    //    terrain group.
-    let plains_terrain: Rc<Module> = Rc::new(Cache::new(plains_terrain_sb2.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: badlands terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: badlands sand (6 noise modules)
-    //
-    // This subgroup generates the sandy terrain for the badlands.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
 
-    // 1: [Sand-dunes module]: This ridged-multifractal-noise module generates
-    //    sand dunes.  This ridged-multifractal noise is generated with a single
-    //    octave, which makes very smooth dunes.
-    let mut badlands_sand_rm = RidgedMulti::new();
-    badlands_sand_rm.set_seed(seed + 80);
-    badlands_sand_rm.set_frequency(6163.5);
-    badlands_sand_rm.set_lacunarity(BADLANDS_LACUNARITY);
-    badlands_sand_rm.set_quality(NoiseQuality::Best);
-    badlands_sand_rm.set_octave_count(1);
-
-    // 2: [Scaled-sand-dunes module]: This scale/bias module shrinks the dune
-    //    heights by a small amount.  This is necessary so that the subsequent
-    //    noise modules in this subgroup can add some detail to the dunes.
-    let mut badlands_sand_sb0 = ScaleBias::new(badlands_sand_rm.clone());
-    badlands_sand_sb0.set_scale(0.875);
-    badlands_sand_sb0.set_bias(0.0);
-
-    // 3: [Dune-detail module]: This noise module uses Voronoi polygons to
-    //    generate the detail to add to the dunes.  By enabling the distance
-    //    algorithm, small polygonal pits are generated; the edges of the pits
-    //    are joined to the edges of nearby pits.
-    let mut badlands_sand_vo = Voronoi::new();
-    badlands_sand_vo.set_seed(seed + 81);
-    badlands_sand_vo.set_frequency(16183.25);
-    badlands_sand_vo.set_displacement(0.0);
-    badlands_sand_vo.enable_distance(true);
-
-    // 4: [Scaled-dune-detail module]: This scale/bias module shrinks the dune
-    //    details by a large amount.  This is necessary so that the subsequent
-    //    noise modules in this subgroup can add this detail to the sand-dunes
-    //    module.
-    let mut badlands_sand_sb1 = ScaleBias::new(badlands_sand_vo.clone());
-    badlands_sand_sb1.set_scale(0.25);
-    badlands_sand_sb1.set_bias(0.25);
-
-    // 5: [Dunes-with-detail module]: This addition module combines the scaled-
-    //    sand-dunes module with the scaled-dune-detail module.
-    let badlands_sand_ad = Add::new(badlands_sand_sb0.clone(), badlands_sand_sb1.clone());
-
-    // 6: [Badlands-sand subgroup]: Caches the output value from the dunes-with-
-    //    detail module.
-    let badlands_sand: Rc<Module> = Rc::new(Cache::new(badlands_sand_ad.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: badlands cliffs (7 noise modules)
-    //
-    // This subgroup generates the cliffs for the badlands.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
+#[derive(Copy, Clone)]
+enum Plane {
+    XP,
+    XN,
+    YP,
+    YN,
+    ZP,
+    ZN,
+}
 
-    // 1: [Cliff-basis module]: This Perlin-noise module generates some coherent
-    //    noise that will be used to generate the cliffs.
-    let mut badlands_cliffs_pe = Perlin::new();
-    badlands_cliffs_pe.set_seed(seed + 90);
-    badlands_cliffs_pe.set_frequency(CONTINENT_FREQUENCY * 839.0);
-    badlands_cliffs_pe.set_persistence(0.5);
-    badlands_cliffs_pe.set_lacunarity(BADLANDS_LACUNARITY);
-    badlands_cliffs_pe.set_octave_count(6);
-    badlands_cliffs_pe.set_quality(NoiseQuality::Standard);
-
-    // 2: [Cliff-shaping module]: Next, this curve module applies a curve to the
-    //    output value from the cliff-basis module.  This curve is initially
-    //    very shallow, but then its slope increases sharply.  At the highest
-    //    elevations, the curve becomes very flat again.  This produces the
-    //    stereotypical Utah-style desert cliffs.
-    let mut badlands_cliffs_cu = Curve::new(badlands_cliffs_pe.clone());
-    badlands_cliffs_cu.add_control_point(-2.0000, -2.0000);
-    badlands_cliffs_cu.add_control_point(-1.0000, -1.2500);
-    badlands_cliffs_cu.add_control_point(-0.0000, -0.7500);
-    badlands_cliffs_cu.add_control_point(0.5000, -0.2500);
-    badlands_cliffs_cu.add_control_point(0.6250, 0.8750);
-    badlands_cliffs_cu.add_control_point(0.7500, 1.0000);
-    badlands_cliffs_cu.add_control_point(2.0000, 1.2500);
-
-    // 3: [Clamped-cliffs module]: This clamping module makes the tops of the
-    //    cliffs very flat by clamping the output value from the cliff-shaping
-    //    module so that the tops of the cliffs are very flat.
-    let mut badlands_cliffs_cl = Clamp::new(badlands_cliffs_cu.clone());
-    badlands_cliffs_cl.set_bounds(-999.125, 0.875);
-
-    // 4: [Terraced-cliffs module]: Next, this terracing module applies some
-    //    terraces to the clamped-cliffs module in the lower elevations before
-    //    the sharp cliff transition.
-    let mut badlands_cliffs_te = Terrace::new(badlands_cliffs_cl.clone());
-    badlands_cliffs_te.add_control_point(-1.0000);
-    badlands_cliffs_te.add_control_point(-0.8750);
-    badlands_cliffs_te.add_control_point(-0.7500);
-    badlands_cliffs_te.add_control_point(-0.5000);
-    badlands_cliffs_te.add_control_point(0.0000);
-    badlands_cliffs_te.add_control_point(1.0000);
-
-    // 5: [Coarse-turbulence module]: This turbulence module warps the output
-    //    value from the terraced-cliffs module, adding some coarse detail to
-    //    it.
-    let mut badlands_cliffs_tu0 = Turbulence::new(badlands_cliffs_te.clone());
-    badlands_cliffs_tu0.set_seed(seed + 91);
-    badlands_cliffs_tu0.set_frequency(16111.0);
-    badlands_cliffs_tu0.set_power(1.0 / 141539.0 * BADLANDS_TWIST);
-    badlands_cliffs_tu0.set_roughness(3);
-
-    // 6: [Warped-cliffs module]: This turbulence module warps the output value
-    //    from the coarse-turbulence module.  This turbulence has a higher
-    //    frequency, but lower power, than the coarse-turbulence module, adding
-    //    some fine detail to it.
-    let mut badlands_cliffs_tu1 = Turbulence::new(badlands_cliffs_tu0.clone());
-    badlands_cliffs_tu1.set_seed(seed + 92);
-    badlands_cliffs_tu1.set_frequency(36107.0);
-    badlands_cliffs_tu1.set_power(1.0 / 211543.0 * BADLANDS_TWIST);
-    badlands_cliffs_tu1.set_roughness(3);
-
-    // 7: [Badlands-cliffs subgroup]: Caches the output value from the warped-
-    //    cliffs module.
-    let badlands_cliffs: Rc<Module> = Rc::new(Cache::new(badlands_cliffs_tu1.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: badlands terrain (3 noise modules)
-    //
-    // Generates the final badlands terrain.
-    //
-    // Using a scale/bias module, the badlands sand is flattened considerably,
-    // then the sand elevations are lowered to around -1.0.  The maximum value
-    // from the flattened sand module and the cliff module contributes to the
-    // final elevation.  This causes sand to appear at the low elevations since
-    // the sand is slightly higher than the cliff base.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
+// How the faces of cube output are arranged in a single image.
+#[derive(Copy, Clone)]
+enum CubeLayout {
+    // A horizontal cross, four faces wide and three high:
     //
+    //        +Y
+    //    -X  +Z  +X  -Z
+    //        -Y
+    Cross,
+    // Three faces wide and two high, +X, -X and +Y above -Y, +Z and -Z.
+    Grid,
+}
 
-    // 1: [Scaled-sand-dunes module]: This scale/bias module considerably
-    //    flattens the output value from the badlands-sands subgroup and lowers
-    //    this value to near -1.0.
-    let mut badlands_terrain_sb = ScaleBias::new(badlands_sand.clone());
-    badlands_terrain_sb.set_scale(0.25);
-    badlands_terrain_sb.set_bias(-0.75);
+impl CubeLayout {
+    // The number of columns and rows of faces, and the column and row of
+    // each face.
+    fn cells(&self) -> (usize, usize, [(Plane, usize, usize); 6]) {
+        match *self {
+            CubeLayout::Cross => {
+                (4,
+                 3,
+                 [(Plane::XP, 2, 1),
+                  (Plane::XN, 0, 1),
+                  (Plane::YP, 1, 0),
+                  (Plane::YN, 1, 2),
+                  (Plane::ZP, 1, 1),
+                  (Plane::ZN, 3, 1)])
+            }
+            CubeLayout::Grid => {
+                (3,
+                 2,
+                 [(Plane::XP, 0, 0),
+                  (Plane::XN, 1, 0),
+                  (Plane::YP, 2, 0),
+                  (Plane::YN, 0, 1),
+                  (Plane::ZP, 1, 1),
+                  (Plane::ZN, 2, 1)])
+            }
+        }
+    }
+}
 
-    // 2: [Dunes-and-cliffs module]: This maximum-value module causes the dunes
-    //    to appear in the low areas and the cliffs to appear in the high areas.
-    //    It does this by selecting the maximum of the output values from the
-    //    scaled-sand-dunes module and the badlands-cliffs subgroup.
-    let badlands_terrain_ma = Max::new(badlands_cliffs.clone(), badlands_terrain_sb.clone());
+// How the faces of cube output are named and oriented.
+#[derive(Copy, Clone)]
+enum CubemapOrder {
+    // "xp.png" to "zn.png", sampled edge to edge so that neighbouring faces
+    // share their edge pixels.
+    Native,
+    // "px.png", "nx.png", "py.png", "ny.png", "pz.png" and "nz.png", in the
+    // order and orientation of the Khronos (OpenGL and KTX) cubemap
+    // convention, as used for skyboxes.  Faces are sampled at texel centres,
+    // half a texel in from the edges, so neighbouring faces don't share any
+    // samples and their edge pixels don't match exactly.
+    Khronos,
+}
 
-    // 3: [Badlands-terrain group]: Caches the output value from the dunes-and-
-    //    cliffs module.  This is the output value for the entire badlands-
-    //    terrain group.
-    let badlands_terrain: Rc<Module> = Rc::new(Cache::new(badlands_terrain_ma.clone()));
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Greyscale8,
+    Greyscale16,
+    Colour24,
+    // Hypsometric tint reduced to the given number of colours, written as an
+    // indexed-colour PNG.
+    Indexed(usize, PaletteMethod),
+    // The same quantization as Greyscale8, or the tint of the gradient if
+    // given, written as a WebP image of the given quality.
+    WebP(u32, Option<Gradient>),
+    // Unquantized elevations in the complexplanet heightmap format, see the
+    // `cph` module.
+    Cph,
+    // Arrows pointing downhill on a grid of the given spacing in pixels,
+    // over a hillshade.
+    VectorField(usize),
+    // All six faces of the cube in a single 8-bit luminance DDS cubemap,
+    // only supported for cube output.
+    DdsCube,
+    // Raw little-endian signed 16-bit integers, in metres above sea level
+    // with +1.0 planetary elevation units being the given number of metres.
+    Int16Signed(f64),
+    // Raw little-endian 64-bit floats, the elevations exactly as generated,
+    // with an ENVI header describing them for GDAL and NumPy.
+    RawF64,
+    // RGBA, the tangent space normal in RGB and the 8-bit elevation in
+    // alpha.  The normals are computed with the terrain's height multiplied
+    // by the given exaggeration.
+    RgbaNormalHeight(f64),
+    // 8-bit greyscale, white where the elevation is above sea level and black
+    // where it is at or below it.
+    OceanMask,
+    // RGB, the elevations coloured by the given gradient.
+    Tinted(Gradient),
+    // 16-bit RGB, as Colour24 but with the elevation quantized to 48 bits.
+    Colour48,
+    // The same quantization as Greyscale16 and Colour24, written as binary
+    // Netpbm (P5 and P6) images rather than PNGs.
+    Pgm16,
+    Ppm8,
+}
 
+// The format of the output images along with options applying to any format.
+#[derive(Clone, Copy)]
+struct OutputOptions {
+    format: OutputFormat,
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: river positions
-    ////////////////////////////////////////////////////////////////////////////
+    // Where the output files are written.
+    paths: OutputPaths,
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: river positions (7 noise modules)
-    //
-    // This subgroup generates the river positions.
-    //
-    // -1.0 represents the lowest elevations and +1.0 represents the highest
-    // elevations.
-    //
+    // Only samples with an elevation within this range (inclusive) are
+    // output, the rest are treated as background.
+    elevation_band: Option<(f64, f64)>,
 
-    // 1: [Large-river-basis module]: This ridged-multifractal-noise module
-    //    creates the large, deep rivers.
-    let mut river_positions_rm0 = RidgedMulti::new();
-    river_positions_rm0.set_seed(seed + 100);
-    river_positions_rm0.set_frequency(18.75);
-    river_positions_rm0.set_lacunarity(CONTINENT_LACUNARITY);
-    river_positions_rm0.set_octave_count(1);
-    river_positions_rm0.set_quality(NoiseQuality::Best);
-
-    // 2: [Large-river-curve module]: This curve module applies a curve to the
-    //    output value from the large-river-basis module so that the ridges
-    //    become inverted.  This creates the rivers.  This curve also compresses
-    //    the edge of the rivers, producing a sharp transition from the land to
-    //    the river bottom.
-    let mut river_positions_cu0 = Curve::new(river_positions_rm0.clone());
-    river_positions_cu0.add_control_point(-2.000, 2.000);
-    river_positions_cu0.add_control_point(-1.000, 1.000);
-    river_positions_cu0.add_control_point(-0.125, 0.875);
-    river_positions_cu0.add_control_point(0.000, -1.000);
-    river_positions_cu0.add_control_point(1.000, -1.500);
-    river_positions_cu0.add_control_point(2.000, -2.000);
-
-    /// 3: [Small-river-basis module]: This ridged-multifractal-noise module
-    //     creates the small, shallow rivers.
-    let mut river_positions_rm1 = RidgedMulti::new();
-    river_positions_rm1.set_seed(seed + 101);
-    river_positions_rm1.set_frequency(43.25);
-    river_positions_rm1.set_lacunarity(CONTINENT_LACUNARITY);
-    river_positions_rm1.set_octave_count(1);
-    river_positions_rm1.set_quality(NoiseQuality::Best);
-
-    // 4: [Small-river-curve module]: This curve module applies a curve to the
-    //    output value from the small-river-basis module so that the ridges
-    //    become inverted.  This creates the rivers.  This curve also compresses
-    //    the edge of the rivers, producing a sharp transition from the land to
-    //    the river bottom.
-    let mut river_positions_cu1 = Curve::new(river_positions_rm1.clone());
-    river_positions_cu1.add_control_point(-2.000, 2.0000);
-    river_positions_cu1.add_control_point(-1.000, 1.5000);
-    river_positions_cu1.add_control_point(-0.125, 1.4375);
-    river_positions_cu1.add_control_point(0.000, 0.5000);
-    river_positions_cu1.add_control_point(1.000, 0.2500);
-    river_positions_cu1.add_control_point(2.000, 0.0000);
-
-    // 5: [Combined-rivers module]: This minimum-value module causes the small
-    //    rivers to cut into the large rivers.  It does this by selecting the
-    //    minimum output values from the large-river-curve module and the small-
-    //    river-curve module.
-    let river_positions_mi = Min::new(river_positions_cu0.clone(), river_positions_cu1.clone());
-
-    // 6: [Warped-rivers module]: This turbulence module warps the output value
-    //    from the combined-rivers module, which twists the rivers.  The high
-    //    roughness produces less-smooth rivers.
-    let mut river_positions_tu = Turbulence::new(river_positions_mi.clone());
-    river_positions_tu.set_seed(seed + 102);
-    river_positions_tu.set_frequency(9.25);
-    river_positions_tu.set_power(1.0 / 57.75);
-    river_positions_tu.set_roughness(6);
-
-    // 7: [River-positions group]: Caches the output value from the warped-
-    //    rivers module.  This is the output value for the entire river-
-    //    positions group.
-    let river_positions: Rc<Module> = Rc::new(Cache::new(river_positions_tu.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: scaled mountainous terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: scaled mountainous terrain (6 noise modules)
-    //
-    // This subgroup scales the output value from the mountainous-terrain group
-    // so that it can be added to the elevation defined by the continent-
-    // definition group.
-    //
-    // This subgroup scales the output value such that it is almost always
-    // positive.  This is done so that a negative elevation does not get applied
-    // to the continent-definition group, preventing parts of that group from
-    // having negative terrain features "stamped" into it.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // The elevation of the parts of an image outside the map, such as the
+    // corners of a Mollweide map.  NaN by default, which is treated as out of
+    // band, so is transparent with `elevation_band`.
+    background: f64,
 
-    // 1: [Base-scaled-mountainous-terrain module]: This scale/bias module
-    //    scales the output value from the mountainous-terrain group so that the
-    //    output value is measured in planetary elevation units.
-    let mut scaled_mountainous_terrain_sb0 = ScaleBias::new(mountainous_terrain.clone());
-    scaled_mountainous_terrain_sb0.set_scale(0.125);
-    scaled_mountainous_terrain_sb0.set_bias(0.125);
-
-    // 2: [Base-peak-modulation module]: At this stage, most mountain peaks have
-    //    roughly the same elevation.  This Perlin-noise module generates some
-    //    random values that will be used by subsequent noise modules to
-    //    randomly change the elevations of the mountain peaks.
-    let mut scaled_mountainous_terrain_pe = Perlin::new();
-    scaled_mountainous_terrain_pe.set_seed(seed + 110);
-    scaled_mountainous_terrain_pe.set_frequency(14.5);
-    scaled_mountainous_terrain_pe.set_persistence(0.5);
-    scaled_mountainous_terrain_pe.set_lacunarity(MOUNTAIN_LACUNARITY);
-    scaled_mountainous_terrain_pe.set_octave_count(6);
-    scaled_mountainous_terrain_pe.set_quality(NoiseQuality::Standard);
-
-    // 3: [Peak-modulation module]: This exponential-curve module applies an
-    //    exponential curve to the output value from the base-peak-modulation
-    //    module.  This produces a small number of high values and a much larger
-    //    number of low values.  This means there will be a few peaks with much
-    //    higher elevations than the majority of the peaks, making the terrain
-    //    features more varied.
-    let mut scaled_mountainous_terrain_ex = Exponent::new(scaled_mountainous_terrain_pe.clone());
-    scaled_mountainous_terrain_ex.set_exponent(1.25);
-
-    // 4: [Scaled-peak-modulation module]: This scale/bias module modifies the
-    //    range of the output value from the peak-modulation module so that it
-    //    can be used as the modulator for the peak-height-multiplier module.
-    //    It is important that this output value is not much lower than 1.0.
-    let mut scaled_mountainous_terrain_sb1 = ScaleBias::new(scaled_mountainous_terrain_ex.clone());
-    scaled_mountainous_terrain_sb1.set_scale(0.25);
-    scaled_mountainous_terrain_sb1.set_bias(1.0);
-
-    // 5: [Peak-height-multiplier module]: This multiplier module modulates the
-    //    heights of the mountain peaks from the base-scaled-mountainous-terrain
-    //    module using the output value from the scaled-peak-modulation module.
-    let scaled_mountainous_terrain_mu = Multiply::new(scaled_mountainous_terrain_sb0.clone(),
-                                                      scaled_mountainous_terrain_sb1.clone());
-
-    // 6: [Scaled-mountainous-terrain group]: Caches the output value from the
-    //    peak-height-multiplier module.  This is the output value for the
-    //    entire scaled-mountainous-terrain group.
-    let scaled_mountainous_terrain: Rc<Module> =
-        Rc::new(Cache::new(scaled_mountainous_terrain_mu.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: scaled hilly terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: scaled hilly terrain (6 noise modules)
-    //
-    // This subgroup scales the output value from the hilly-terrain group so
-    // that it can be added to the elevation defined by the continent-
-    // definition group.  The scaling amount applied to the hills is one half of
-    // the scaling amount applied to the scaled-mountainous-terrain group.
-    //
-    // This subgroup scales the output value such that it is almost always
-    // positive.  This is done so that negative elevations are not applied to
-    // the continent-definition group, preventing parts of the continent-
-    // definition group from having negative terrain features "stamped" into it.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // Whether to dither the elevations when quantizing them to integers, to
+    // break up the banding in flat areas.
+    dither: bool,
 
-    // 1: [Base-scaled-hilly-terrain module]: This scale/bias module scales the
-    //    output value from the hilly-terrain group so that this output value is
-    //    measured in planetary elevation units
-    let mut scaled_hilly_terrain_sb0 = ScaleBias::new(hilly_terrain.clone());
-    scaled_hilly_terrain_sb0.set_scale(0.0625);
-    scaled_hilly_terrain_sb0.set_bias(0.0625);
-
-    // 2: [Base-hilltop-modulation module]: At this stage, most hilltops have
-    //    roughly the same elevation.  This Perlin-noise module generates some
-    //    random values that will be used by subsequent noise modules to
-    //    randomly change the elevations of the hilltops.
-    let mut scaled_hilly_terrain_pe = Perlin::new();
-    scaled_hilly_terrain_pe.set_seed(seed + 120);
-    scaled_hilly_terrain_pe.set_frequency(13.5);
-    scaled_hilly_terrain_pe.set_persistence(0.5);
-    scaled_hilly_terrain_pe.set_lacunarity(HILLS_LACUNARITY);
-    scaled_hilly_terrain_pe.set_octave_count(6);
-    scaled_hilly_terrain_pe.set_quality(NoiseQuality::Standard);
-
-    // 3: [Hilltop-modulation module]: This exponential-curve module applies an
-    //    exponential curve to the output value from the base-hilltop-modulation
-    //    module.  This produces a small number of high values and a much larger
-    //    number of low values.  This means there will be a few hilltops with
-    //    much higher elevations than the majority of the hilltops, making the
-    //    terrain features more varied.
-    let mut scaled_hilly_terrain_ex = Exponent::new(scaled_hilly_terrain_pe.clone());
-    scaled_hilly_terrain_ex.set_exponent(1.25);
-
-    // 4: [Scaled-hilltop-modulation module]: This scale/bias module modifies
-    //    the range of the output value from the hilltop-modulation module so
-    //    that it can be used as the modulator for the hilltop-height-multiplier
-    //    module.  It is important that this output value is not much lower than
-    //    1.0.
-    let mut scaled_hilly_terrain_sb1 = ScaleBias::new(scaled_hilly_terrain_ex.clone());
-    scaled_hilly_terrain_sb1.set_scale(0.5);
-    scaled_hilly_terrain_sb1.set_bias(1.5);
-
-    // 5: [Hilltop-height-multiplier module]: This multiplier module modulates
-    //    the heights of the hilltops from the base-scaled-hilly-terrain module
-    //    using the output value from the scaled-hilltop-modulation module.
-    let scaled_hilly_terrain_mu = Multiply::new(scaled_hilly_terrain_sb0.clone(),
-                                                scaled_hilly_terrain_sb1.clone());
-
-    // 6: [Scaled-hilly-terrain group]: Caches the output value from the
-    //    hilltop-height-multiplier module.  This is the output value for the
-    //    entire scaled-hilly-terrain group.
-    let scaled_hilly_terrain: Rc<Module> = Rc::new(Cache::new(scaled_hilly_terrain_mu.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: scaled plains terrain
-    ////////////////////////////////////////////////////////////////////////////
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: scaled plains terrain (2 noise modules)
-    //
-    // This subgroup scales the output value from the plains-terrain group so
-    // that it can be added to the elevations defined by the continent-
-    // definition group.
-    //
-    // This subgroup scales the output value such that it is almost always
-    // positive.  This is done so that negative elevations are not applied to
-    // the continent-definition group, preventing parts of the continent-
-    // definition group from having negative terrain features "stamped" into it.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // Maps the elevations of the greyscale and colour formats to
+    // brightnesses, and whether to stretch the range of the elevations of
+    // each image to the full range, overriding `tone.range`.
+    tone: Tone,
+    auto_normalize: bool,
 
-    // 1: [Scaled-plains-terrain module]: This scale/bias module greatly
-    //    flattens the output value from the plains terrain.  This output value
-    //    is measured in planetary elevation units
-    let mut scaled_plains_terrain_sb = ScaleBias::new(plains_terrain.clone());
-    scaled_plains_terrain_sb.set_scale(0.00390625);
-    scaled_plains_terrain_sb.set_bias(0.0078125);
+    // Whether to also write the latitude and longitude of each pixel, see
+    // `write_coords`.
+    export_coords: bool,
 
-    // 2: [Scaled-plains-terrain group]: Caches the output value from the
-    //    scaled-plains-terrain module.  This is the output value for the entire
-    //    scaled-plains-terrain group.
-    let scaled_plains_terrain: Rc<Module> = Rc::new(Cache::new(scaled_plains_terrain_sb.clone()));
+    // Draw the projection, seed and a scale bar into the images.
+    annotate: Option<annotate::Annotation>,
 
+    // Also write a normal map of each image, see `write_normal_outputs`.
+    normals: bool,
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: scaled badlands terrain
-    ////////////////////////////////////////////////////////////////////////////
+    // Also write each image hillshaded for a sun at this azimuth and
+    // altitude, in degrees, see `write_normal_outputs`.
+    hillshade: Option<(f64, f64)>,
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: scaled badlands terrain (2 noise modules)
-    //
-    // This subgroup scales the output value from the badlands-terrain group so
-    // that it can be added to the elevations defined by the continent-
-    // definition group.
-    //
-    // This subgroup scales the output value such that it is almost always
-    // positive.  This is done so that negative elevations are not applied to
-    // the continent-definition group, preventing parts of the continent-
-    // definition group from having negative terrain features "stamped" into it.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // Also write the ambient occlusion of each image, casting this many rays
+    // out to this radius in metres, see `write_normal_outputs`.
+    ao: Option<(f64, usize)>,
 
-    // 1: [Scaled-badlands-terrain module]: This scale/bias module scales the
-    //    output value from the badlands-terrain group so that it is measured
-    //    in planetary elevation units
-    let mut scaled_badlands_terrain_sb = ScaleBias::new(badlands_terrain.clone());
-    scaled_badlands_terrain_sb.set_scale(0.0625);
-    scaled_badlands_terrain_sb.set_bias(0.0625);
+    // Multiplies the terrain's height in the normals and occlusion of the
+    // above.
+    normal_strength: f64,
 
-    // 2: [Scaled-badlands-terrain group]: Caches the output value from the
-    //    scaled-badlands-terrain module.  This is the output value for the
-    //    entire scaled-badlands-terrain group.
-    let scaled_badlands_terrain: Rc<Module> =
-        Rc::new(Cache::new(scaled_badlands_terrain_sb.clone()));
+    // Also write a JSON sidecar describing each output file, see
+    // `lock::write_metadata`.
+    metadata: Option<Metadata>,
 
+    // Exit with an error if any elevation generated isn't finite, see
+    // `check_finite`.
+    strict: bool,
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: final planet
-    ////////////////////////////////////////////////////////////////////////////
+    // Print the statistics of the elevations of each image to stderr, see
+    // `stats::ElevationStats`.  Those of the cube are printed for all of its
+    // faces together.
+    stats: bool,
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continental shelf (6 noise modules)
-    //
-    // This module subgroup creates the continental shelves.
-    //
-    // The output value from this module subgroup are measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // Also write the temperature of each image from this model, drawn with a
+    // thermal colour ramp if `temperature_ramp` is set, see
+    // `temperature::output_temperature`.
+    temperature: Option<TemperatureModel>,
+    temperature_ramp: bool,
 
-    // 1: [Shelf-creator module]: This terracing module applies a terracing
-    //    curve to the continent-definition group at the specified shelf level.
-    //    This terrace becomes the continental shelf.  Note that this terracing
-    //    module also places another terrace below the continental shelf near
-    //    -1.0.  The bottom of this terrace is defined as the bottom of the
-    //    ocean; subsequent noise modules will later add oceanic trenches to the
-    //    bottom of the ocean.
-    let mut continental_shelf_te = Terrace::new(continent_def.clone());
-    continental_shelf_te.add_control_point(-1.0);
-    continental_shelf_te.add_control_point(-0.75);
-    continental_shelf_te.add_control_point(SHELF_LEVEL);
-    continental_shelf_te.add_control_point(1.0);
-
-    // 2: [Oceanic-trench-basis module]: This ridged-multifractal-noise module
-    //    generates some coherent noise that will be used to generate the
-    //    oceanic trenches.  The ridges represent the bottom of the trenches.
-    let mut continental_shelf_rm = RidgedMulti::new();
-    continental_shelf_rm.set_seed(seed + 130);
-    continental_shelf_rm.set_frequency(CONTINENT_FREQUENCY * 4.375);
-    continental_shelf_rm.set_lacunarity(CONTINENT_LACUNARITY);
-    continental_shelf_rm.set_octave_count(16);
-    continental_shelf_rm.set_quality(NoiseQuality::Best);
-
-    // 3: [Oceanic-trench module]: This scale/bias module inverts the ridges
-    //    from the oceanic-trench-basis-module so that the ridges become
-    //    trenches.  This noise module also reduces the depth of the trenches so
-    //    that their depths are measured in planetary elevation units.
-    let mut continental_shelf_sb = ScaleBias::new(continental_shelf_rm.clone());
-    continental_shelf_sb.set_scale(-0.125);
-    continental_shelf_sb.set_bias(-0.125);
-
-    // 4: [Clamped-sea-bottom module]: This clamping module clamps the output
-    //    value from the shelf-creator module so that its possible range is
-    //    from the bottom of the ocean to sea level.  This is done because this
-    //    subgroup is only concerned about the oceans.
-    let mut continental_shelf_cl = Clamp::new(continental_shelf_te.clone());
-    continental_shelf_cl.set_bounds(-0.75, SEA_LEVEL);
-
-    // 5: [Shelf-and-trenches module]: This addition module adds the oceanic
-    //    trenches to the clamped-sea-bottom module.
-    let continental_shelf_ad = Add::new(continental_shelf_sb.clone(), continental_shelf_cl.clone());
-
-    // 6: [Continental-shelf subgroup]: Caches the output value from the shelf-
-    //    and-trenches module.
-    let continental_shelf: Rc<Module> = Rc::new(Cache::new(continental_shelf_ad.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module group: base continent elevations (3 noise modules)
-    //
-    // This subgroup generates the base elevations for the continents, before
-    // terrain features are added.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // Also draw each image in this style, see `style::render_style`.
+    style: Option<Style>,
 
-    // 1: [Base-scaled-continent-elevations module]: This scale/bias module
-    //    scales the output value from the continent-definition group so that it
-    //    is measured in planetary elevation units
-    let mut base_continent_elev_sb = ScaleBias::new(continent_def.clone());
-    base_continent_elev_sb.set_scale(CONTINENT_HEIGHT_SCALE);
-    base_continent_elev_sb.set_bias(0.0);
-
-    // 2: [Base-continent-with-oceans module]: This selector module applies the
-    //    elevations of the continental shelves to the base elevations of the
-    //    continent.  It does this by selecting the output value from the
-    //    continental-shelf subgroup if the corresponding output value from the
-    //    continent-definition group is below the shelf level.  Otherwise, it
-    //    selects the output value from the base-scaled-continent-elevations
-    //    module.
-    let mut base_continent_elev_se = Select::new(base_continent_elev_sb.clone(),
-                                                 continental_shelf.clone(),
-                                                 continent_def.clone());
-    base_continent_elev_se.set_bounds(SHELF_LEVEL - 1000.0, SHELF_LEVEL);
-    base_continent_elev_se.set_edge_falloff(0.03125);
-
-    // 3: [Base-continent-elevation subgroup]: Caches the output value from the
-    //    base-continent-with-oceans module.
-    let base_continent_elev: Rc<Module> = Rc::new(Cache::new(base_continent_elev_se.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continents with plains (2 noise modules)
-    //
-    // This subgroup applies the scaled-plains-terrain group to the base-
-    // continent-elevation subgroup.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // How far the poles of the climate of `temperature` and `style` are
+    // tilted from the geographic poles, in degrees, see `climate_lat`.
+    climate_tilt: f64,
 
-    // 1: [Continents-with-plains module]:  This addition module adds the
-    //    scaled-plains-terrain group to the base-continent-elevation subgroup.
-    let continents_with_plains_ad = Add::new(base_continent_elev.clone(),
-                                             scaled_plains_terrain.clone());
+    // The precision the elevations of cube and rect output are held in.
+    precision: Precision,
+}
 
-    // 2: [Continents-with-plains subgroup]: Caches the output value from the
-    //    continents-with-plains module.
-    let continents_with_plains: Rc<Module> = Rc::new(Cache::new(continents_with_plains_ad.clone()));
+// The precision the elevations of an image are held in between sampling and
+// writing them, see `Elevation`.
+//
+// The elevations are always sampled in f64.  Holding them in f32 halves the
+// memory of the largest images, and rounds them by at most 6e-8, against a
+// step of 8e-3 in the 8-bit formats and 3e-5 in the 16-bit formats, so only
+// the odd pixel right on the edge of a step comes out differently.  The steps
+// of the 24 and 48-bit colour formats are as fine as f32 though, so they lose
+// precision.
+#[derive(Clone, Copy)]
+enum Precision {
+    F32,
+    F64,
+}
 
+// A type the elevations of an image can be held in.
+trait Elevation: Copy + Send + 'static {
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continents with hills (3 noise modules)
-    //
-    // This subgroup applies the scaled-hilly-terrain group to the continents-
-    // with-plains subgroup.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    // The elevations as f64, for the outputs which work on them all at once.
+    fn widen<'a>(data: &'a [Self]) -> Cow<'a, [f64]>;
+}
 
-    // 1: [Continents-with-hills module]:  This addition module adds the scaled-
-    //    hilly-terrain group to the base-continent-elevation subgroup.
-    let continents_with_hills_ad = Add::new(base_continent_elev.clone(),
-                                            scaled_hilly_terrain.clone());
-
-    // 2: [Select-high-elevations module]: This selector module ensures that
-    //    the hills only appear at higher elevations.  It does this by selecting
-    //    the output value from the continent-with-hills module if the
-    //    corresponding output value from the terrain-type-defintion group is
-    //    above a certain value. Otherwise, it selects the output value from the
-    //    continents-with-plains subgroup.
-    let mut continents_with_hills_se = Select::new(continents_with_plains.clone(),
-                                                   continents_with_hills_ad.clone(),
-                                                   terrain_type_def.clone());
-    continents_with_hills_se.set_bounds(1.0 - HILLS_AMOUNT, 1001.0 - HILLS_AMOUNT);
-    continents_with_hills_se.set_edge_falloff(0.25);
-
-    // 3: [Continents-with-hills subgroup]: Caches the output value from the
-    //    select-high-elevations module.
-    let continents_with_hills: Rc<Module> = Rc::new(Cache::new(continents_with_hills_se.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continents with mountains (5 noise modules)
-    //
-    // This subgroup applies the scaled-mountainous-terrain group to the
-    // continents-with-hills subgroup.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+impl Elevation for f64 {
+    fn from_f64(value: f64) -> f64 {
+        value
+    }
 
-    // 1: [Continents-and-mountains module]:  This addition module adds the
-    //    scaled-mountainous-terrain group to the base-continent-elevation
-    //    subgroup.
-    let continents_with_mountains_ad0 = Add::new(base_continent_elev.clone(),
-                                                 scaled_mountainous_terrain.clone());
-
-    // 2: [Increase-mountain-heights module]:  This curve module applies a curve
-    //    to the output value from the continent-definition group.  This
-    //    modified output value is used by a subsequent noise module to add
-    //    additional height to the mountains based on the current continent
-    //    elevation.  The higher the continent elevation, the higher the
-    //    mountains.
-    let mut continents_with_mountains_cu = Curve::new(continent_def.clone());
-    continents_with_mountains_cu.add_control_point(-1.0, -0.0625);
-    continents_with_mountains_cu.add_control_point(0.0, 0.0000);
-    continents_with_mountains_cu.add_control_point(1.0 - MOUNTAINS_AMOUNT, 0.0625);
-    continents_with_mountains_cu.add_control_point(1.0, 0.2500);
-
-    // 3: [Add-increased-mountain-heights module]: This addition module adds
-    //    the increased-mountain-heights module to the continents-and-
-    //    mountains module.  The highest continent elevations now have the
-    //    highest mountains.
-    let continents_with_mountains_ad1 = Add::new(continents_with_mountains_ad0.clone(),
-                                                 continents_with_mountains_cu.clone());
-
-    // 4: [Select-high-elevations module]: This selector module ensures that
-    //    mountains only appear at higher elevations.  It does this by selecting
-    //    the output value from the continent-with-mountains module if the
-    //    corresponding output value from the terrain-type-defintion group is
-    //    above a certain value.  Otherwise, it selects the output value from
-    //    the continents-with-hills subgroup.  Note that the continents-with-
-    //    hills subgroup also contains the plains terrain.
-    let mut continents_with_mountains_se = Select::new(continents_with_hills.clone(),
-                                                       continents_with_mountains_ad1.clone(),
-                                                       terrain_type_def.clone());
-    continents_with_mountains_se.set_bounds(1.0 - MOUNTAINS_AMOUNT, 1001.0 - MOUNTAINS_AMOUNT);
-    continents_with_mountains_se.set_edge_falloff(0.25);
-
-    // 5: [Continents-with-mountains subgroup]: Caches the output value from
-    //    the select-high-elevations module.
-    let continents_with_mountains: Rc<Module> =
-        Rc::new(Cache::new(continents_with_mountains_se.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continents with badlands (5 noise modules)
-    //
-    // This subgroup applies the scaled-badlands-terrain group to the
-    // continents-with-mountains subgroup.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    fn to_f64(self) -> f64 {
+        self
+    }
 
-    // 1: [Badlands-positions module]: This Perlin-noise module generates some
-    //    random noise, which is used by subsequent noise modules to specify the
-    //    locations of the badlands.
-    let mut continents_with_badlands_pe = Perlin::new();
-    continents_with_badlands_pe.set_seed(seed + 140);
-    continents_with_badlands_pe.set_frequency(16.5);
-    continents_with_badlands_pe.set_persistence(0.5);
-    continents_with_badlands_pe.set_lacunarity(CONTINENT_LACUNARITY);
-    continents_with_badlands_pe.set_octave_count(2);
-    continents_with_badlands_pe.set_quality(NoiseQuality::Standard);
-
-    // 2: [Continents-and-badlands module]:  This addition module adds the
-    //    scaled-badlands-terrain group to the base-continent-elevation
-    //    subgroup.
-    let continents_with_badlands_ad = Add::new(base_continent_elev.clone(),
-                                               scaled_badlands_terrain.clone());
-
-    // 3: [Select-badlands-positions module]: This selector module places
-    //    badlands at random spots on the continents based on the Perlin noise
-    //    generated by the badlands-positions module.  To do this, it selects
-    //    the output value from the continents-and-badlands module if the
-    //    corresponding output value from the badlands-position module is
-    //    greater than a specified value.  Otherwise, this selector module
-    //    selects the output value from the continents-with-mountains subgroup.
-    //    There is also a wide transition between these two noise modules so
-    //    that the badlands can blend into the rest of the terrain on the
-    //    continents.
-    let mut continents_with_badlands_se = Select::new(continents_with_mountains.clone(),
-                                                      continents_with_badlands_ad.clone(),
-                                                      continents_with_badlands_pe.clone());
-    continents_with_badlands_se.set_bounds(1.0 - BADLANDS_AMOUNT, 1001.0 - BADLANDS_AMOUNT);
-    continents_with_badlands_se.set_edge_falloff(0.25);
-
-    // 4: [Apply-badlands module]: This maximum-value module causes the badlands
-    //    to "poke out" from the rest of the terrain.  It does this by ensuring
-    //    that only the maximum of the output values from the continents-with-
-    //    mountains subgroup and the select-badlands-positions modules
-    //    contribute to the output value of this subgroup.  One side effect of
-    //    this process is that the badlands will not appear in mountainous
-    //    terrain.
-    let continents_with_badlands_ma = Max::new(continents_with_mountains.clone(),
-                                               continents_with_badlands_se.clone());
-
-    // 5: [Continents-with-badlands subgroup]: Caches the output value from the
-    //    apply-badlands module.
-    let continents_with_badlands: Rc<Module> =
-        Rc::new(Cache::new(continents_with_badlands_ma.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: continents with rivers (4 noise modules)
-    //
-    // This subgroup applies the river-positions group to the continents-with-
-    // badlands subgroup.
-    //
-    // The output value from this module subgroup is measured in planetary
-    // elevation units (-1.0 for the lowest underwater trenches and +1.0 for the
-    // highest mountain peaks.)
-    //
+    fn widen<'a>(data: &'a [f64]) -> Cow<'a, [f64]> {
+        Cow::Borrowed(data)
+    }
+}
 
-    // 1: [Scaled-rivers module]: This scale/bias module scales the output value
-    //    from the river-positions group so that it is measured in planetary
-    //    elevation units and is negative; this is required for step 2.
-    let mut continents_with_rivers_sb = ScaleBias::new(river_positions.clone());
-    continents_with_rivers_sb.set_scale(RIVER_DEPTH / 2.0);
-    continents_with_rivers_sb.set_bias(-RIVER_DEPTH / 2.0);
-
-    // 2: [Add-rivers-to-continents module]: This addition module adds the
-    //    rivers to the continents-with-badlands subgroup.  Because the scaled-
-    //    rivers module only outputs a negative value, the scaled-rivers module
-    //    carves the rivers out of the terrain.
-    let continents_with_rivers_ad = Add::new(continents_with_badlands.clone(),
-                                             continents_with_rivers_sb.clone());
-
-    // 3: [Blended-rivers-to-continents module]: This selector module outputs
-    //    deep rivers near sea level and shallower rivers in higher terrain.  It
-    //    does this by selecting the output value from the continents-with-
-    //    badlands subgroup if the corresponding output value from the
-    //    continents-with-badlands subgroup is far from sea level.  Otherwise,
-    //    this selector module selects the output value from the add-rivers-to-
-    //    continents module.
-    let mut continents_with_rivers_se = Select::new(continents_with_badlands.clone(),
-                                                    continents_with_rivers_ad.clone(),
-                                                    continents_with_badlands.clone());
-    continents_with_rivers_se.set_bounds(SEA_LEVEL, CONTINENT_HEIGHT_SCALE + SEA_LEVEL);
-    continents_with_rivers_se.set_edge_falloff(CONTINENT_HEIGHT_SCALE - SEA_LEVEL);
-
-    // 4: [Continents-with-rivers subgroup]: Caches the output value from the
-    //    blended-rivers-to-continents module.
-    let continents_with_rivers: Rc<Module> = Rc::new(Cache::new(continents_with_rivers_se.clone()));
-
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Module subgroup: unscaled final planet (1 noise module)
-    //
-    // This subgroup simply caches the output value from the continent-with-
-    // rivers subgroup to contribute to the final output value.
-    //
+impl Elevation for f32 {
+    fn from_f64(value: f64) -> f32 {
+        value as f32
+    }
 
-    // 1: [Unscaled-final-planet subgroup]: Caches the output value from the
-    //    continent-with-rivers subgroup.
-    let unscaled_final_planet: Rc<Module> = Rc::new(Cache::new(continents_with_rivers.clone()));
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
 
-    Box::new(unscaled_final_planet)
+    fn widen<'a>(data: &'a [f32]) -> Cow<'a, [f64]> {
+        Cow::Owned(data.iter().map(|&value| value as f64).collect())
+    }
 }
 
-#[derive(Copy, Clone)]
-enum Plane {
-    XP,
-    XN,
-    YP,
-    YN,
-    ZP,
-    ZN,
+// What the metadata sidecars record besides the details of each image.  The
+// strings live for the whole run, as for `OutputPaths`.
+#[derive(Clone, Copy)]
+struct Metadata {
+    output_type: &'static str,
+    format: &'static str,
+    params: PlanetParams,
 }
 
+// The directory the output files are written to and the prefix given to
+// their names.  The strings live for the whole run, so they are static to keep
+// the options Copy for the worker threads.
 #[derive(Clone, Copy)]
-enum OutputFormat {
-    Greyscale8,
-    Greyscale16,
-    Colour24,
+struct OutputPaths {
+    dir: &'static str,
+    prefix: Option<&'static str>,
 }
 
-fn lat_lon_to_pos(lat: f64, lon: f64) -> (f64, f64, f64) {
-    let lat = lat.to_radians();
-    let lon = lon.to_radians();
-    let r = f64::cos(lat);
-    let x = r * f64::cos(lon);
-    let y = f64::sin(lat);
-    let z = r * f64::sin(lon);
-    (x, y, z)
+impl OutputPaths {
+    // The name of an output file with the prefix, as other outputs alongside
+    // it refer to it.
+    fn file_name(&self, name: &str) -> String {
+        match self.prefix {
+            Some(prefix) => format!("{}_{}", prefix, name),
+            None => name.to_string(),
+        }
+    }
+
+    // The path to write an output file to.
+    fn path(&self, name: &str) -> String {
+        Path::new(self.dir).join(self.file_name(name)).to_str().unwrap().to_string()
+    }
 }
 
-pub fn clamp<T: Ord>(value: T, lower_bound: T, upper_bound: T) -> T {
-    if value < lower_bound {
-        lower_bound
-    } else if value > upper_bound {
-        upper_bound
-    } else {
-        value
+impl OutputFormat {
+    // The name of the format as recorded by the metadata sidecars, the value
+    // of --format where it has one, or else that of the palette which
+    // replaced it.
+    fn name(&self) -> &'static str {
+        match *self {
+            OutputFormat::Greyscale8 => "greyscale8",
+            OutputFormat::Greyscale16 => "greyscale16",
+            OutputFormat::Colour24 => "colour24",
+            OutputFormat::Indexed(_, PaletteMethod::Terrain) => "indexed-terrain",
+            OutputFormat::Indexed(_, PaletteMethod::MedianCut) => "indexed-median-cut",
+            OutputFormat::WebP(_, None) => "webp",
+            OutputFormat::WebP(_, Some(Gradient::Earth)) => "webp-earth",
+            OutputFormat::WebP(_, Some(Gradient::Custom(_))) => "webp-custom",
+            OutputFormat::Cph => "cph",
+            OutputFormat::VectorField(_) => "vector-field",
+            OutputFormat::DdsCube => "dds-cube",
+            OutputFormat::Int16Signed(_) => "int16-signed",
+            OutputFormat::RawF64 => "rawf64",
+            OutputFormat::RgbaNormalHeight(_) => "rgba-normal-height",
+            OutputFormat::OceanMask => "ocean-mask",
+            OutputFormat::Tinted(Gradient::Earth) => "tinted-earth",
+            OutputFormat::Tinted(Gradient::Custom(_)) => "tinted-custom",
+            OutputFormat::Colour48 => "colour48",
+            OutputFormat::Pgm16 => "pgm16",
+            OutputFormat::Ppm8 => "ppm8",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match *self {
+            OutputFormat::WebP(..) => "webp",
+            OutputFormat::Cph => "cph",
+            OutputFormat::DdsCube => "dds",
+            OutputFormat::Int16Signed(_) => "raw",
+            OutputFormat::RawF64 => "bin",
+            OutputFormat::Pgm16 => "pgm",
+            OutputFormat::Ppm8 => "ppm",
+            _ => "png",
+        }
+    }
+
+    // A rough figure for the memory used while encoding, in bytes per pixel,
+    // on top of the elevations themselves.
+    fn encoding_bytes_per_pixel(&self) -> usize {
+        match *self {
+            OutputFormat::Greyscale8 => 1,
+            OutputFormat::Greyscale16 => 2,
+            OutputFormat::Colour24 => 3,
+            OutputFormat::Tinted(_) => 3,
+            // The indices and scanlines, plus the hash chain of the deflate
+            // encoder, a usize for each byte of the scanlines, and the
+            // compressed data.
+            OutputFormat::Indexed(..) => 11,
+            // Greyscale, RGB and ARGB copies of the image plus the LZ77
+            // tokens.
+            OutputFormat::WebP(..) => 16,
+            // Written straight from the elevations.
+            OutputFormat::Cph => 0,
+            // Gradients plus the RGB canvas.
+            OutputFormat::VectorField(_) => 19,
+            OutputFormat::DdsCube => 1,
+            // Written straight from the elevations.
+            OutputFormat::Int16Signed(_) => 0,
+            OutputFormat::RawF64 => 0,
+            // Normals plus the RGBA image.
+            OutputFormat::RgbaNormalHeight(_) => 28,
+            OutputFormat::OceanMask => 1,
+            OutputFormat::Colour48 => 6,
+            OutputFormat::Pgm16 => 2,
+            OutputFormat::Ppm8 => 3,
+        }
     }
 }
 
-pub fn f64_clamp(value: f64, lower_bound: f64, upper_bound: f64) -> f64 {
-    if value < lower_bound {
-        lower_bound
-    } else if value > upper_bound {
-        upper_bound
-    } else {
-        value
+// The latitude of (lat, lon) measured from climate poles tilted `tilt`
+// degrees from the geographic poles, the north pole towards longitude 0.
+fn climate_lat((lat, lon): (f64, f64), tilt: f64) -> f64 {
+    if tilt == 0.0 {
+        return lat;
     }
+    let (x, y, _) = lat_lon_to_pos(lat, lon);
+    let tilt = tilt.to_radians();
+    let sin_lat = x * f64::sin(tilt) + y * f64::cos(tilt);
+    f64::asin(f64_clamp(sin_lat, -1.0, 1.0)).to_degrees()
 }
 
+// The inverse of `lat_lon_to_pos`, `pos` need not be normalized.
+fn pos_to_lat_lon(pos: (f64, f64, f64)) -> (f64, f64) {
+    let (x, y, z) = pos;
+    let lat = f64::atan2(y, f64::sqrt(x * x + z * z)).to_degrees();
+    let lon = f64::atan2(z, x).to_degrees();
+    (lat, lon)
+}
+
+// Position on the cube of the integer coordinates (a, b) of a face, from 0 to
+// `max_coord` inclusive.  The first and last coordinates lie on the cube's
+// edges, and a coordinate on an edge gives exactly the same position from
+// either face sharing the edge, so the edge pixels of neighbouring faces are
+// sampled at identical points and match exactly.  This only holds for
+// `CubemapOrder::Native`, Khronos faces are sampled at texel centres instead.
 fn coord_to_pos(plane: Plane, a: usize, b: usize, max_coord: usize) -> (f64, f64, f64) {
     let (x, y, z) = match plane {
         Plane::XP => (max_coord, b, max_coord - a),
@@ -1614,155 +488,1835 @@ fn coord_to_pos(plane: Plane, a: usize, b: usize, max_coord: usize) -> (f64, f64
      -1.0 + z as f64 * 2.0 / max_coord as f64)
 }
 
+// Direction of the point (s, t) of a face in the Khronos cubemap
+// convention, where s and t are in [-1, 1] and t increases down the face.
+// Direct3D uses the same convention.
+fn cube_face_direction(plane: Plane, s: f64, t: f64) -> (f64, f64, f64) {
+    match plane {
+        Plane::XP => (1.0, -t, -s),
+        Plane::XN => (-1.0, -t, s),
+        Plane::YP => (s, 1.0, t),
+        Plane::YN => (s, -1.0, -t),
+        Plane::ZP => (s, -t, 1.0),
+        Plane::ZN => (-s, -t, -1.0),
+    }
+}
+
+// Directions on the cube of the right of a face image and up it.
+fn cube_face_axes(plane: Plane, order: CubemapOrder) -> ((f64, f64, f64), (f64, f64, f64)) {
+    let sub = |a: (f64, f64, f64), b: (f64, f64, f64)| (a.0 - b.0, a.1 - b.1, a.2 - b.2);
+    match order {
+        CubemapOrder::Native => {
+            let origin = coord_to_pos(plane, 0, 0, 1);
+            (sub(coord_to_pos(plane, 1, 0, 1), origin), sub(coord_to_pos(plane, 0, 1, 1), origin))
+        }
+        CubemapOrder::Khronos => {
+            let origin = cube_face_direction(plane, 0.0, 0.0);
+            (sub(cube_face_direction(plane, 1.0, 0.0), origin),
+             sub(origin, cube_face_direction(plane, 0.0, 1.0)))
+        }
+    }
+}
+
+// Position on the cube of pixel (a, row) of a face image, counting rows from
+// the top of the image.
+fn cube_face_pos(plane: Plane,
+                 order: CubemapOrder,
+                 a: usize,
+                 row: usize,
+                 size: usize)
+                 -> (f64, f64, f64) {
+    match order {
+        CubemapOrder::Native => coord_to_pos(plane, a, size - 1 - row, size - 1),
+        CubemapOrder::Khronos => {
+            let s = (a as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+            let t = (row as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+            cube_face_direction(plane, s, t)
+        }
+    }
+}
+
+// Samples a face of the cube, returning the elevations with the top row of
+// the image first.  Each pixel is the average of `supersample` by
+// `supersample` samples on a regular grid spanning it, as for
+// `generate_rect_region`.
+fn generate_cube_face<T: Elevation>(generator: &Module,
+                                    plane: Plane,
+                                    order: CubemapOrder,
+                                    size: usize,
+                                    supersample: usize)
+                                    -> Vec<T> {
+    let mut dest_buffer: Vec<T> = vec![T::from_f64(0.0); size * size];
+    let sample = |(px, py, pz): (f64, f64, f64)| {
+        let magnitude = f64::sqrt(px * px + py * py + pz * pz);
+        let px = px / magnitude;
+        let py = py / magnitude;
+        let pz = pz / magnitude;
+        generator.get_value(px, py, pz)
+    };
+
+    // The size of a pixel in units of the face axes.
+    let (right, up) = cube_face_axes(plane, order);
+    let pixel = match order {
+        CubemapOrder::Native => 1.0 / (size - 1) as f64,
+        CubemapOrder::Khronos => 2.0 / size as f64,
+    };
+    let offset = |i: usize| ((i as f64 + 0.5) / supersample as f64 - 0.5) * pixel;
+
+    for row in 0..size {
+        let row_start = &mut dest_buffer[(row * size)..];
+        for a in 0..size {
+            let pos = cube_face_pos(plane, order, a, row, size);
+            if supersample == 1 {
+                row_start[a] = T::from_f64(sample(pos));
+                continue;
+            }
+
+            let mut total = 0.0;
+            for i in 0..supersample {
+                for j in 0..supersample {
+                    let (u, v) = (offset(j), offset(i));
+                    total += sample((pos.0 + u * right.0 + v * up.0,
+                                     pos.1 + u * right.1 + v * up.1,
+                                     pos.2 + u * right.2 + v * up.2));
+                }
+            }
+            row_start[a] = T::from_f64(total / (supersample * supersample) as f64);
+        }
+    }
+    dest_buffer
+}
+
+// Outputs a face of the cube on a new thread, see `write_cube_face`.
 fn output_cube_face(plane: Plane,
+                    order: CubemapOrder,
                     seed: i32,
+                    generator: SharedModule,
                     size: usize,
-                    output_format: OutputFormat)
-                    -> JoinHandle<()> {
-    std::thread::spawn(move || {
-        let generator = create_generator(seed);
-        let mut dest_buffer: Vec<f64> = vec![0.0; size * size];
-
-        for b in 0..size {
-            let row_start = &mut dest_buffer[((size - 1 - b) * size)..];
-            for a in 0..size {
-                let (px, py, pz) = coord_to_pos(plane, a, b, size - 1);
-                let magnitude = f64::sqrt(px * px + py * py + pz * pz);
-                let px = px / magnitude;
-                let py = py / magnitude;
-                let pz = pz / magnitude;
-                row_start[a] = generator.get_value(px, py, pz);
-            }
-        }
-
-        let filename = match plane {
-            Plane::XP => "xp.png",
-            Plane::XN => "xn.png",
-            Plane::YP => "yp.png",
-            Plane::YN => "yn.png",
-            Plane::ZP => "zp.png",
-            Plane::ZN => "zn.png",
-        };
-        write_output_to_file(filename, &dest_buffer, size, size, output_format);
+                    supersample: usize,
+                    output: OutputOptions)
+                    -> JoinHandle<Option<ElevationStats>> {
+    std::thread::spawn(move || match output.precision {
+        Precision::F32 => {
+            write_cube_face::<f32>(plane, order, seed, &*generator, size, supersample, output)
+        }
+        Precision::F64 => {
+            write_cube_face::<f64>(plane, order, seed, &*generator, size, supersample, output)
+        }
     })
 }
 
-fn output_cube(seed: i32, size: usize, output_format: OutputFormat) {
-    let xp_join = output_cube_face(Plane::XP, seed, size, output_format);
-    let xn_join = output_cube_face(Plane::XN, seed, size, output_format);
-    let yp_join = output_cube_face(Plane::YP, seed, size, output_format);
-    let yn_join = output_cube_face(Plane::YN, seed, size, output_format);
-    let zp_join = output_cube_face(Plane::ZP, seed, size, output_format);
-    let zn_join = output_cube_face(Plane::ZN, seed, size, output_format);
+// Outputs a face of the cube, see `generate_cube_face`, holding the
+// elevations as `T`.  Returns the statistics of the face if `output.stats` is
+// set.
+fn write_cube_face<T: Elevation>(plane: Plane,
+                                 order: CubemapOrder,
+                                 seed: i32,
+                                 generator: &Module,
+                                 size: usize,
+                                 supersample: usize,
+                                 output: OutputOptions)
+                                 -> Option<ElevationStats> {
+    let dest_buffer: Vec<T> = generate_cube_face(generator, plane, order, size, supersample);
+
+    let (name, projection) = match (order, plane) {
+        (CubemapOrder::Native, Plane::XP) => ("xp.png", Projection::CubeXP),
+        (CubemapOrder::Native, Plane::XN) => ("xn.png", Projection::CubeXN),
+        (CubemapOrder::Native, Plane::YP) => ("yp.png", Projection::CubeYP),
+        (CubemapOrder::Native, Plane::YN) => ("yn.png", Projection::CubeYN),
+        (CubemapOrder::Native, Plane::ZP) => ("zp.png", Projection::CubeZP),
+        (CubemapOrder::Native, Plane::ZN) => ("zn.png", Projection::CubeZN),
+        (CubemapOrder::Khronos, Plane::XP) => ("px.png", Projection::KhronosPX),
+        (CubemapOrder::Khronos, Plane::XN) => ("nx.png", Projection::KhronosNX),
+        (CubemapOrder::Khronos, Plane::YP) => ("py.png", Projection::KhronosPY),
+        (CubemapOrder::Khronos, Plane::YN) => ("ny.png", Projection::KhronosNY),
+        (CubemapOrder::Khronos, Plane::ZP) => ("pz.png", Projection::KhronosPZ),
+        (CubemapOrder::Khronos, Plane::ZN) => ("nz.png", Projection::KhronosNZ),
+    };
+    let filename = &output.paths.path(name);
+    if output.strict {
+        check_finite(filename, &dest_buffer, size, |a, row| {
+            Some(pos_to_lat_lon(cube_face_pos(plane, order, a, row, size)))
+        });
+    }
+    write_output_to_file(filename,
+                         &dest_buffer,
+                         size,
+                         size,
+                         seed,
+                         projection,
+                         output);
+
+    if output.export_coords {
+        write_coords(filename, size, size, seed, projection, |a, row| {
+            pos_to_lat_lon(cube_face_pos(plane, order, a, row, size))
+        });
+    }
+
+    // The latitude follows from Y of the normalized position, the longitude
+    // from X and Z.
+    let lat_lon = |a, row| {
+        let (px, py, pz) = cube_face_pos(plane, order, a, row, size);
+        let magnitude = f64::sqrt(px * px + py * py + pz * pz);
+        (f64::asin(py / magnitude).to_degrees(), f64::atan2(pz, px).to_degrees())
+    };
+
+    if let Some(model) = output.temperature {
+        let stem = Path::new(filename).with_extension("");
+        temperature::output_temperature(&T::widen(&dest_buffer),
+                                        size,
+                                        size,
+                                        seed,
+                                        projection,
+                                        lat_lon,
+                                        output.climate_tilt,
+                                        &model,
+                                        output.temperature_ramp,
+                                        &format!("{}_temperature", stem.to_str().unwrap()));
+    }
+
+    if let Some(style) = output.style {
+        let stem = Path::new(filename).with_extension("");
+        style::render_style(&T::widen(&dest_buffer),
+                            size,
+                            size,
+                            style,
+                            lat_lon,
+                            output.climate_tilt)
+            .write_png(&format!("{}_{}.png", stem.to_str().unwrap(), style.name()));
+    }
+
+    if output.normals || output.hillshade.is_some() || output.ao.is_some() {
+        let (right, up) = cube_face_axes(plane, order);
+        let step = std::f64::consts::FRAC_PI_2 / size as f64;
+        write_normal_outputs(filename, generator, size, size, step, output, |a, row| {
+            (cube_face_pos(plane, order, a, row, size), right, up)
+        });
+    }
 
-    xp_join.join().unwrap();
-    xn_join.join().unwrap();
-    yp_join.join().unwrap();
-    yn_join.join().unwrap();
-    zp_join.join().unwrap();
-    zn_join.join().unwrap();
+    if output.stats {
+        Some(ElevationStats::from_elevations(&T::widen(&dest_buffer)))
+    } else {
+        None
+    }
 }
 
-fn output_rect(seed: i32, width: usize, output_format: OutputFormat) {
-    let height = width / 2;
-    let generator = create_generator(seed);
-    let mut dest_buffer: Vec<f64> = vec![0.0; width * height];
+// Outputs the faces of the cube, or only the given face, generating at most
+// `threads` faces at once from a single generator shared between them.  With
+// a layout the faces are written together as a single image instead.
+fn output_cube(seed: i32,
+               params: &PlanetParams,
+               size: usize,
+               order: CubemapOrder,
+               face: Option<Plane>,
+               layout: Option<CubeLayout>,
+               threads: usize,
+               supersample: usize,
+               output: OutputOptions) {
+    let generator = create_generator(seed, params);
+
+    // DDS cubemaps always follow the Khronos convention.
+    if let OutputFormat::DdsCube = output.format {
+        dds::output_dds_cube(&generator, size, output);
+        return;
+    }
 
-    for y in 0..height {
-        let row_start = &mut dest_buffer[((height - 1 - y) * width)..];
-        let cur_lat = -90.0 + (y as f64 / height as f64) * 180.0;
-        for x in 0..width {
-            let cur_lon = -180.0 + (x as f64 / width as f64) * 360.0;
-            let pos = lat_lon_to_pos(cur_lat, cur_lon);
-            row_start[x] = generator.get_value(pos.0, pos.1, pos.2);
+    if let Some(layout) = layout {
+        output_cube_layout(seed, &generator, size, order, layout, threads, supersample, output);
+        return;
+    }
+
+    let planes = match face {
+        Some(plane) => vec![plane],
+        None => vec![Plane::XP, Plane::XN, Plane::YP, Plane::YN, Plane::ZP, Plane::ZN],
+    };
+    let mut stats = ElevationStats::new();
+    for batch in planes.chunks(threads) {
+        let joins: Vec<JoinHandle<Option<ElevationStats>>> = batch.iter()
+            .map(|&plane| {
+                output_cube_face(plane, order, seed, generator.clone(), size, supersample, output)
+            })
+            .collect();
+
+        for join in joins {
+            if let Some(face_stats) = join.join().unwrap() {
+                stats.merge(&face_stats);
+            }
         }
     }
 
-    write_output_to_file("lat_lon.png", &dest_buffer, width, height, output_format);
+    if output.stats {
+        stats.print("cube");
+    }
 }
 
-fn write_output_to_file(filename: &str,
-                        data: &[f64],
-                        width: usize,
-                        height: usize,
-                        output_format: OutputFormat) {
-    let img_data = match output_format {
-        OutputFormat::Greyscale8 => {
-            let mut img_data = Vec::new();
-            img_data.resize(width * height, 0);
-            let mut idx = 0;
-            let mut img_idx = 0;
-            for _ in 0..height {
-                for _ in 0..width {
-                    let value = (data[idx] + 1.0) / 2.0;
-                    let value = (f64_clamp(value, 0.0, 1.0) * 255.0) as i32;
-                    let value = clamp(value, 0, 0xff) as u8;
-                    img_data[img_idx] = value;
-                    idx += 1;
-                    img_idx += 1;
-                }
-            }
-            img_data
+// Outputs the six faces of the cube arranged in a single image, cube_cross.png
+// or cube_grid.png.  Each face is oriented as it would be written on its own,
+// which in the cross lines up the edges the faces share.  The parts of the
+// cross outside the faces are set to the background elevation.
+fn output_cube_layout(seed: i32,
+                      generator: &SharedModule,
+                      size: usize,
+                      order: CubemapOrder,
+                      layout: CubeLayout,
+                      threads: usize,
+                      supersample: usize,
+                      output: OutputOptions) {
+    let (columns, rows, cells) = layout.cells();
+    let (name, projection) = match (order, layout) {
+        (CubemapOrder::Native, CubeLayout::Cross) => ("cube_cross.png", Projection::CubeCross),
+        (CubemapOrder::Native, CubeLayout::Grid) => ("cube_grid.png", Projection::CubeGrid),
+        (CubemapOrder::Khronos, CubeLayout::Cross) => {
+            ("cube_cross.png", Projection::KhronosCross)
         }
-        OutputFormat::Greyscale16 => {
-            let mut img_data = Vec::new();
-            img_data.resize(width * height * 2, 0);
-            let mut idx = 0;
-            let mut img_idx = 0;
-            for _ in 0..height {
-                for _ in 0..width {
-                    let value = (data[idx] + 1.0) / 2.0;
-                    let value = (f64_clamp(value, 0.0, 1.0) * 65535.0) as i32;
-                    let value = clamp(value, 0, 0xffff);
-                    img_data[img_idx] = ((value & 0xff00) >> 8) as u8;
-                    img_data[img_idx + 1] = (value & 0x00ff) as u8;
-                    idx += 1;
-                    img_idx += 2;
-                }
+        (CubemapOrder::Khronos, CubeLayout::Grid) => ("cube_grid.png", Projection::KhronosGrid),
+    };
+    let width = columns * size;
+    let mut dest_buffer = vec![output.background; width * rows * size];
+    let mut stats = ElevationStats::new();
+    for batch in cells.chunks(threads) {
+        let joins: Vec<JoinHandle<Vec<f64>>> = batch.iter()
+            .map(|&(plane, _, _)| {
+                let generator = generator.clone();
+                std::thread::spawn(move || {
+                    generate_cube_face(&*generator, plane, order, size, supersample)
+                })
+            })
+            .collect();
+
+        for (join, &(plane, column, row)) in joins.into_iter().zip(batch) {
+            let face = join.join().unwrap();
+            if output.strict {
+                check_finite(&output.paths.path(name), &face, size, |a, y| {
+                    Some(pos_to_lat_lon(cube_face_pos(plane, order, a, y, size)))
+                });
+            }
+            // Only the faces count, not the background around them.
+            stats.merge(&ElevationStats::from_elevations(&face));
+            for y in 0..size {
+                let start = (row * size + y) * width + column * size;
+                dest_buffer[start..(start + size)]
+                    .copy_from_slice(&face[(y * size)..((y + 1) * size)]);
             }
-            img_data
         }
-        OutputFormat::Colour24 => {
-            let mut img_data = Vec::new();
-            img_data.resize(width * height * 3, 0);
-            let mut idx = 0;
-            let mut img_idx = 0;
-            for _ in 0..height {
-                for _ in 0..width {
-                    let value = (data[idx] + 1.0) / 2.0;
-                    let value = (f64_clamp(value, 0.0, 1.0) * 16777215.0) as i32;
-                    let value = clamp(value, 0, 0xffffff);
-                    let r = ((value & 0x00ff0000) >> 16) as u8;
-                    let g = ((value & 0x0000ff00) >> 8) as u8;
-                    let b = (value & 0x000000ff) as u8;
-                    img_data[img_idx] = r;
-                    img_data[img_idx + 1] = g;
-                    img_data[img_idx + 2] = b;
-                    idx += 1;
-                    img_idx += 3;
-                }
+    }
+
+    write_output_to_file(&output.paths.path(name),
+                         &dest_buffer,
+                         width,
+                         rows * size,
+                         seed,
+                         projection,
+                         output);
+    if output.stats {
+        stats.print(&output.paths.path(name));
+    }
+}
+
+// Samples the generator over an equirectangular (lat/lon) grid, returning the
+// elevations with the northernmost row first.
+fn generate_rect(generator: &Module, width: usize, height: usize) -> Vec<f64> {
+    generate_rect_region(generator, width, height, 0, 0, width, height, false, 1)
+}
+
+// Samples `rows` whole rows of the equirectangular grid starting at row
+// `first_row` as `generate_rect_region` does, with the rows split between `threads`
+// worker threads sharing the generator.  Every sample is computed
+// independently of the others, so the result is the same whatever the number
+// of threads.
+fn generate_rect_threaded<T: Elevation>(generator: &SharedModule,
+                                        width: usize,
+                                        height: usize,
+                                        first_row: usize,
+                                        rows: usize,
+                                        adaptive_poles: bool,
+                                        supersample: usize,
+                                        threads: usize)
+                                        -> Vec<T> {
+    let threads = clamp(threads, 1, rows);
+    let mut joins = Vec::new();
+    for i in 0..threads {
+        let y0 = first_row + i * rows / threads;
+        let y1 = first_row + (i + 1) * rows / threads;
+        let generator = generator.clone();
+        joins.push(std::thread::spawn(move || {
+            generate_rect_region::<T>(&*generator,
+                                      width,
+                                      height,
+                                      0,
+                                      y0,
+                                      width,
+                                      y1 - y0,
+                                      adaptive_poles,
+                                      supersample)
+        }));
+    }
+
+    let mut dest_buffer = Vec::with_capacity(width * rows);
+    for join in joins {
+        dest_buffer.extend(join.join().unwrap());
+    }
+    dest_buffer
+}
+
+// Rows sampled adaptively never have fewer samples than this.
+const MIN_ADAPTIVE_ROW_SAMPLES: usize = 8;
+
+// Samples part of the equirectangular grid of the given size, the
+// `region_width` by `region_height` samples starting at column `x0` and row
+// `y0` (counting rows from the north).
+//
+// The samples of a row get closer together towards the poles, where they all
+// coincide.  If `adaptive_poles` is set, each row is instead sampled with
+// roughly the same spacing as the equator and linearly interpolated, which
+// saves work without losing detail.  A row exactly on a pole is always
+// sampled once at the pole itself, so that it is a single value.
+//
+// Each sample is the average of `supersample` by `supersample` points on a
+// regular grid spanning the pixel around it, to smooth out the aliasing of
+// high frequency noise.
+fn generate_rect_region<T: Elevation>(generator: &Module,
+                                      width: usize,
+                                      height: usize,
+                                      x0: usize,
+                                      y0: usize,
+                                      region_width: usize,
+                                      region_height: usize,
+                                      adaptive_poles: bool,
+                                      supersample: usize)
+                                      -> Vec<T> {
+    let mut dest_buffer: Vec<T> = vec![T::from_f64(0.0); region_width * region_height];
+    let lat_step = 180.0 / height as f64;
+    let sample = |lat: f64, lon: f64, lon_step: f64| if supersample == 1 {
+        let pos = lat_lon_to_pos(lat, lon);
+        generator.get_value(pos.0, pos.1, pos.2)
+    } else {
+        let offset = |i: usize| (i as f64 + 0.5) / supersample as f64 - 0.5;
+        let mut total = 0.0;
+        for i in 0..supersample {
+            for j in 0..supersample {
+                let pos = lat_lon_to_pos(lat + offset(i) * lat_step, lon + offset(j) * lon_step);
+                total += generator.get_value(pos.0, pos.1, pos.2);
             }
-            img_data
         }
+        total / (supersample * supersample) as f64
     };
 
-    let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
-    let writer = BufWriter::new(file);
+    for row in 0..region_height {
+        let row_start = &mut dest_buffer[(row * region_width)..];
+        let y = height - 1 - (y0 + row);
+        let cur_lat = -90.0 + (y as f64 / height as f64) * 180.0;
 
-    let ct = match output_format {
-        OutputFormat::Greyscale8 => ColorType::Gray(8),
-        OutputFormat::Greyscale16 => ColorType::Gray(16),
-        OutputFormat::Colour24 => ColorType::RGB(8),
-    };
+        // Sampling around the pole with lat_lon_to_pos gives points a
+        // rounding error away from it, which can differ slightly.
+        if cur_lat.abs() == 90.0 {
+            let value = T::from_f64(generator.get_value(0.0, cur_lat.signum(), 0.0));
+            for col in 0..region_width {
+                row_start[col] = value;
+            }
+            continue;
+        }
 
-    let encoder = PNGEncoder::new(writer);
+        let row_samples = if adaptive_poles {
+            let samples = (width as f64 * f64::cos(cur_lat.to_radians())).ceil() as usize;
+            clamp(samples, MIN_ADAPTIVE_ROW_SAMPLES, width)
+        } else {
+            width
+        };
 
-    encoder.encode(&img_data, width as u32, height as u32, ct)
-        .expect("Failed to encode image data");
-}
+        if row_samples == width {
+            for col in 0..region_width {
+                let x = x0 + col;
+                let cur_lon = -180.0 + (x as f64 / width as f64) * 360.0;
+                row_start[col] = T::from_f64(sample(cur_lat, cur_lon, 360.0 / width as f64));
+            }
+            continue;
+        }
 
-fn main() {
-    let matches = App::new("ComplexPlanet")
+        // Sample the reduced row over the part covering the region, plus one
+        // more to interpolate towards, wrapping around in longitude.
+        let first = x0 * row_samples / width;
+        let last = (x0 + region_width - 1) * row_samples / width + 1;
+        let samples: Vec<f64> = (first..(last + 1))
+            .map(|i| {
+                let cur_lon = -180.0 + ((i % row_samples) as f64 / row_samples as f64) * 360.0;
+                sample(cur_lat, cur_lon, 360.0 / row_samples as f64)
+            })
+            .collect();
+
+        for col in 0..region_width {
+            let x = x0 + col;
+            let i = x * row_samples / width - first;
+            let t = (x * row_samples % width) as f64 / width as f64;
+            row_start[col] = T::from_f64(samples[i] + (samples[i + 1] - samples[i]) * t);
+        }
+    }
+
+    dest_buffer
+}
+
+// Computes the gradient at each sample of an equirectangular grid produced by
+// `generate_rect`, as the rise in metres per metre towards the east and
+// towards the north.  The horizontal spacing of the samples shrinks towards
+// the poles, which is accounted for; the grid wraps around in longitude so
+// there is no seam at the antimeridian.
+fn rect_gradients(data: &[f64], width: usize, height: usize) -> Vec<(f64, f64)> {
+    let radius = PLANET_CIRCUMFERENCE / (2.0 * std::f64::consts::PI);
+    let lat_spacing = radius * std::f64::consts::PI / height as f64;
+    let mut gradients = vec![(0.0, 0.0); width * height];
+
+    for y in 0..height {
+        let lat = -90.0 + ((height - 1 - y) as f64 / height as f64) * 180.0;
+        // Avoid dividing by zero at the poles, where the samples of a row
+        // all coincide.
+        let lon_spacing = f64::max(f64::cos(lat.to_radians()), 1e-6) * radius * 2.0 *
+                          std::f64::consts::PI / width as f64;
+
+        let (y0, y1) = (if y > 0 { y - 1 } else { y }, usize::min(y + 1, height - 1));
+        for x in 0..width {
+            let x0 = (x + width - 1) % width;
+            let x1 = (x + 1) % width;
+
+            let dx = (data[y * width + x1] - data[y * width + x0]) * MAX_ELEV /
+                     (2.0 * lon_spacing);
+            let dy = if y1 > y0 {
+                (data[y0 * width + x] - data[y1 * width + x]) * MAX_ELEV /
+                ((y1 - y0) as f64 * lat_spacing)
+            } else {
+                0.0
+            };
+
+            gradients[y * width + x] = (dx, dy);
+        }
+    }
+
+    gradients
+}
+
+// Computes the slope at each sample of an equirectangular grid produced by
+// `generate_rect`, in degrees.
+fn rect_slopes(data: &[f64], width: usize, height: usize) -> Vec<f64> {
+    rect_gradients(data, width, height)
+        .iter()
+        .map(|&(dx, dy)| f64::atan(f64::sqrt(dx * dx + dy * dy)).to_degrees())
+        .collect()
+}
+
+// Specifies the elevation of the flat ocean floor that --crust-threshold
+// clamps the terrain below the threshold down to.
+const CRUST_FLOOR: f64 = -0.75;
+
+// Options specific to the equirectangular output.
+#[derive(Clone, Copy, Default)]
+struct RectOptions<'a> {
+    // Output a mask of the land with a slope below this angle, in degrees.
+    max_walkable_slope: Option<f64>,
+
+    // Output a mask of the land no higher than this above sea level, in
+    // planetary elevation units.
+    beach_band: Option<f64>,
+
+    // Output the elevation tinted map shaded for this sun position.
+    sun: Option<SunPosition>,
+
+    // Output the map as tiles of at most this many pixels square.
+    tile_size: Option<usize>,
+
+    // Sample fewer points along the rows near the poles, see
+    // `generate_rect_region`.
+    adaptive_poles: bool,
+
+    // Also write "lat_lon.vrt", georeferencing the map and its tiles.
+    emit_vrt: bool,
+
+    // Output a hypsograph of the map to this file.
+    hypsograph: Option<&'a str>,
+
+    // Raise or lower sea level until this fraction of the planet's surface
+    // is ocean.
+    target_ocean_fraction: Option<f64>,
+
+    // Fill the closed basins on the land with lakes, see
+    // `basins::fill_basins`.
+    fill_basins: bool,
+
+    // Clamp the elevations below this level down to CRUST_FLOOR.
+    crust_threshold: Option<f64>,
+
+    // Output a map of the terrain groups chosen by the selectors.
+    terrain_branches: bool,
+
+    // Output a map of the mean insolation over a year for this axial tilt,
+    // in degrees.
+    insolation: Option<f64>,
+
+    // Output a map of the moisture, dried out inland with this damping
+    // distance if given, see `moisture::output_moisture`.
+    moisture: bool,
+    moisture_damping: Option<f64>,
+
+    // Output contour lines every this many metres, see
+    // `contours::render_contours`.
+    contours: Option<f64>,
+
+    // Output the difference from the map of this seed, see
+    // `diff::output_diff`.
+    diff_seed: Option<i32>,
+
+    // Split the sampling of the map between this many threads.
+    threads: usize,
+
+    // Average this many by this many samples for each pixel, see
+    // `generate_rect_region`.
+    supersample: usize,
+
+    projection: RectProjection,
+
+    // Write the map a band of rows at a time as it is generated, see
+    // `stream_rect`.
+    stream: bool,
+
+    // Output a map of just this box instead of the whole globe, see
+    // `region::output_region`.  None of the other rect outputs are
+    // supported.
+    region: Option<Region>,
+}
+
+// The projection of rect output.
+#[derive(Clone, Copy)]
+enum RectProjection {
+    Equirectangular,
+    // See `mollweide::output_mollweide`.  None of the other rect outputs are
+    // supported.
+    Mollweide,
+    // See `gnomonic::output_gnomonic`, likewise.
+    Gnomonic(gnomonic::View),
+    // See `orthographic::output_orthographic`, likewise except for --style.
+    Orthographic(orthographic::View),
+}
+
+impl RectProjection {
+    fn name(&self) -> &'static str {
+        match *self {
+            RectProjection::Equirectangular => "equirectangular",
+            RectProjection::Mollweide => "mollweide",
+            RectProjection::Gnomonic(_) => "gnomonic",
+            RectProjection::Orthographic(_) => "orthographic",
+        }
+    }
+}
+
+impl Default for RectProjection {
+    fn default() -> RectProjection {
+        RectProjection::Equirectangular
+    }
+}
+
+// The advice given by `check_max_pixels` when there's no smaller way to
+// output an image.
+const RAISE_MAX_PIXELS: &'static str = "Raise --max-pixels if there is the memory for it";
+
+// Exits with an error if an image of `pixels` pixels, needing about `memory`
+// bytes, is over --max-pixels, printing `advice` on what to do instead.
+fn check_max_pixels(description: &str,
+                    pixels: usize,
+                    memory: usize,
+                    max_pixels: usize,
+                    advice: &str) {
+    if pixels > max_pixels {
+        println!("{} {} pixels, needing about {} MB, more than --max-pixels {}",
+                 description,
+                 pixels,
+                 memory / (1024 * 1024),
+                 max_pixels);
+        println!("{}", advice);
+        std::process::exit(1);
+    }
+}
+
+// Estimates the peak memory used to output a `width` by `height` image as a
+// single piece, in bytes.
+fn estimate_rect_memory(width: usize, height: usize, output_format: OutputFormat) -> usize {
+    let bytes_per_pixel = std::mem::size_of::<f64>() + output_format.encoding_bytes_per_pixel();
+    width.saturating_mul(height).saturating_mul(bytes_per_pixel)
+}
+
+const MIN_TILE_SIZE: usize = 16;
+
+// Chooses the largest power of two tile size whose tiles fit within
+// `max_memory` bytes.
+fn tile_size_for_memory(max_memory: usize, output_format: OutputFormat) -> usize {
+    let mut tile_size = MIN_TILE_SIZE;
+    while estimate_rect_memory(tile_size * 2, tile_size * 2, output_format) <= max_memory {
+        tile_size *= 2;
+    }
+    tile_size
+}
+
+// The first of the options of `output_rect` which adjust the elevations of
+// the map before it is written or derive other outputs from them, named as
+// their argument.  These need the whole map in f64, so `output_rect` only
+// writes the map itself with f32.
+fn rect_f64_option(output: OutputOptions, options: &RectOptions) -> Option<&'static str> {
+    let requested = [("diff-seed", options.diff_seed.is_some()),
+                     ("target-ocean-fraction", options.target_ocean_fraction.is_some()),
+                     ("crust-threshold", options.crust_threshold.is_some()),
+                     ("fill-basins", options.fill_basins),
+                     ("max-walkable-slope", options.max_walkable_slope.is_some()),
+                     ("beach-band", options.beach_band.is_some()),
+                     ("sun-lat", options.sun.is_some()),
+                     ("temperature", output.temperature.is_some()),
+                     ("style", output.style.is_some()),
+                     ("terrain-branches", options.terrain_branches),
+                     ("insolation", options.insolation.is_some()),
+                     ("moisture", options.moisture),
+                     ("contours", options.contours.is_some()),
+                     ("hypsograph", options.hypsograph.is_some())];
+    requested.iter().find(|&&(_, requested)| requested).map(|&(arg, _)| arg)
+}
+
+fn output_rect(seed: i32,
+               params: &PlanetParams,
+               width: usize,
+               height: usize,
+               output: OutputOptions,
+               options: &RectOptions) {
+    let generator = create_generator(seed, params);
+
+    match options.projection {
+        RectProjection::Equirectangular => {}
+        RectProjection::Mollweide => {
+            mollweide::output_mollweide(seed, &generator, width, height, options.threads, output);
+            return;
+        }
+        RectProjection::Gnomonic(view) => {
+            gnomonic::output_gnomonic(seed,
+                                      &generator,
+                                      view,
+                                      width,
+                                      height,
+                                      options.threads,
+                                      output);
+            return;
+        }
+        RectProjection::Orthographic(view) => {
+            orthographic::output_orthographic(seed,
+                                              &generator,
+                                              view,
+                                              width,
+                                              height,
+                                              options.threads,
+                                              output);
+            return;
+        }
+    }
+
+    if let Some(region) = options.region {
+        region::output_region(seed,
+                              &generator,
+                              region,
+                              width,
+                              height,
+                              options.threads,
+                              options.supersample,
+                              output);
+        return;
+    }
+
+    if let Some(tile_size) = options.tile_size {
+        let tiles = output_rect_tiles(seed,
+                                      &*generator,
+                                      width,
+                                      height,
+                                      tile_size,
+                                      options.adaptive_poles,
+                                      options.supersample,
+                                      output);
+        if options.emit_vrt {
+            vrt::write_vrt(&output.paths.path("lat_lon.vrt"), width, height, &tiles, output);
+        }
+        return;
+    }
+
+    let filename = output.paths.path("lat_lon.png");
+    if options.stream {
+        stream_rect(seed, &generator, width, height, &filename, output, options);
+        write_rect_sidecars(&filename, &*generator, width, height, seed, output, options);
+        return;
+    }
+
+    if let Precision::F32 = output.precision {
+        // None of the outputs derived from the elevations are supported with
+        // f32, see `rect_f64_option`, so only the map itself is written.
+        let dest_buffer: Vec<f32> = generate_rect_checked(&generator, width, height, output,
+                                                          options);
+        if output.stats {
+            ElevationStats::from_elevations(&f32::widen(&dest_buffer)).print(&filename);
+        }
+        write_output_to_file(&filename,
+                             &dest_buffer,
+                             width,
+                             height,
+                             seed,
+                             Projection::Equirectangular,
+                             output);
+        write_rect_sidecars(&filename, &*generator, width, height, seed, output, options);
+        return;
+    }
+
+    let mut dest_buffer: Vec<f64> = generate_rect_checked(&generator, width, height, output,
+                                                          options);
+
+    // The maps are compared as generated, before any of the adjustments
+    // below.
+    if let Some(other_seed) = options.diff_seed {
+        let other_generator = create_generator(other_seed, params);
+        let other: Vec<f64> = generate_rect_checked(&other_generator, width, height, output,
+                                                    options);
+        diff::output_diff(&dest_buffer,
+                          &other,
+                          width,
+                          height,
+                          &format!("seed {}", other_seed),
+                          output.paths);
+    }
+
+    // The elevations are shifted so that the new sea level is at SEA_LEVEL,
+    // so everything below sees the new coastline.
+    if let Some(fraction) = options.target_ocean_fraction {
+        let level = sea_level_for_ocean_fraction(&dest_buffer, width, height, fraction);
+        println!("Sea level for an ocean fraction of {} is {:+.6}", fraction, level);
+        for value in dest_buffer.iter_mut() {
+            *value += SEA_LEVEL - level;
+        }
+    }
+
+    // Clamped after the sea level shift so that the threshold is relative
+    // to the new sea level, and before the basins are filled so the deep
+    // floor isn't mistaken for a basin.
+    if let Some(threshold) = options.crust_threshold {
+        for value in dest_buffer.iter_mut() {
+            if *value < threshold {
+                *value = CRUST_FLOOR;
+            }
+        }
+    }
+
+    if options.fill_basins {
+        basins::fill_basins(&mut dest_buffer, width, height);
+    }
+
+    if output.stats {
+        ElevationStats::from_elevations(&dest_buffer).print(&filename);
+    }
+
+    write_output_to_file(&filename,
+                         &dest_buffer,
+                         width,
+                         height,
+                         seed,
+                         Projection::Equirectangular,
+                         output);
+    write_rect_sidecars(&filename, &*generator, width, height, seed, output, options);
+
+    // Walkable terrain is land with a gentle enough slope, written as a mask
+    // which is +1.0 where walkable and -1.0 elsewhere.
+    if let Some(max_slope) = options.max_walkable_slope {
+        let slopes = rect_slopes(&dest_buffer, width, height);
+        let mask: Vec<f64> = dest_buffer.iter()
+            .zip(slopes.iter())
+            .map(|(&elev, &slope)| if elev > SEA_LEVEL && slope < max_slope { 1.0 } else { -1.0 })
+            .collect();
+        write_output_to_file(&output.paths.path("walkable.png"),
+                             &mask,
+                             width,
+                             height,
+                             seed,
+                             Projection::Equirectangular,
+                             output);
+    }
+
+    // Beaches are likewise written as a mask of the land just above sea
+    // level.
+    if let Some(band) = options.beach_band {
+        let mask: Vec<f64> = dest_buffer.iter()
+            .map(|&elev| if elev > SEA_LEVEL && elev <= SEA_LEVEL + band { 1.0 } else { -1.0 })
+            .collect();
+        write_output_to_file(&output.paths.path("beach.png"),
+                             &mask,
+                             width,
+                             height,
+                             seed,
+                             Projection::Equirectangular,
+                             output);
+    }
+
+    if let Some(sun) = options.sun {
+        sunlight::render_sunlit(&dest_buffer, width, height, sun)
+            .write_png(&output.paths.path("sunlit.png"));
+    }
+
+    if let Some(model) = output.temperature {
+        temperature::output_temperature(&dest_buffer,
+                                        width,
+                                        height,
+                                        seed,
+                                        Projection::Equirectangular,
+                                        |x, y| rect_lat_lon(width, height, x, y),
+                                        output.climate_tilt,
+                                        &model,
+                                        output.temperature_ramp,
+                                        &output.paths.path("temperature"));
+    }
+
+    if let Some(style) = output.style {
+        style::render_style(&dest_buffer,
+                            width,
+                            height,
+                            style,
+                            |x, y| rect_lat_lon(width, height, x, y),
+                            output.climate_tilt)
+            .write_png(&output.paths.path(&format!("{}.png", style.name())));
+    }
+
+    if options.terrain_branches {
+        branches::output_terrain_branches(seed,
+                                          params,
+                                          &dest_buffer,
+                                          width,
+                                          height,
+                                          output.paths);
+    }
+
+    if let Some(obliquity) = options.insolation {
+        insolation::output_insolation(&dest_buffer,
+                                      width,
+                                      height,
+                                      seed,
+                                      obliquity,
+                                      output.paths);
+    }
+
+    if options.moisture {
+        moisture::output_moisture(seed,
+                                  params,
+                                  &dest_buffer,
+                                  width,
+                                  height,
+                                  options.moisture_damping,
+                                  output.paths);
+    }
+
+    if let Some(interval) = options.contours {
+        let gradient = match output.format {
+            OutputFormat::Tinted(gradient) |
+            OutputFormat::WebP(_, Some(gradient)) => Some(gradient),
+            _ => None,
+        };
+        contours::render_contours(&dest_buffer, width, height, interval, gradient)
+            .write_png(&output.paths.path("contours.png"));
+    }
+
+    if let Some(filename) = options.hypsograph {
+        hypsograph::output_hypsograph(filename, &dest_buffer, width, height);
+    }
+}
+
+// Samples the equirectangular map for `output_rect`, checking the elevations
+// if `output.strict` is set.
+fn generate_rect_checked<T: Elevation>(generator: &SharedModule,
+                                       width: usize,
+                                       height: usize,
+                                       output: OutputOptions,
+                                       options: &RectOptions)
+                                       -> Vec<T> {
+    let dest_buffer = generate_rect_threaded(generator,
+                                             width,
+                                             height,
+                                             0,
+                                             height,
+                                             options.adaptive_poles,
+                                             options.supersample,
+                                             options.threads);
+    if output.strict {
+        check_finite(&output.paths.path("lat_lon.png"), &dest_buffer, width, |x, y| {
+            Some(rect_lat_lon(width, height, x, y))
+        });
+    }
+    dest_buffer
+}
+
+// Rows of the equirectangular map generated at once by `stream_rect`.
+const STREAM_BAND_ROWS: usize = 64;
+
+// Writes the equirectangular map to `filename` a band of rows at a time as
+// they are generated, so that only one band of the elevations and pixels is
+// held in memory whatever the height of the map.  Only the PNG greyscale and
+// colour formats are supported.
+fn stream_rect(seed: i32,
+               generator: &SharedModule,
+               width: usize,
+               height: usize,
+               filename: &str,
+               output: OutputOptions,
+               options: &RectOptions) {
+    if let Some(metadata) = output.metadata {
+        let filename = Path::new(filename).with_extension("json");
+        lock::write_metadata(filename.to_str().unwrap(), &metadata, seed,
+                             Projection::Equirectangular, width, height);
+    }
+
+    let (colour_type, bit_depth) = match output.format {
+        OutputFormat::Greyscale8 => (0, 8),
+        OutputFormat::Greyscale16 => (0, 16),
+        OutputFormat::Colour24 => (2, 8),
+        OutputFormat::Colour48 => (2, 16),
+        _ => unreachable!(),
+    };
+    let mut png = png_stream::PngStream::create(filename, width, height, colour_type, bit_depth);
+    let mut y0 = 0;
+    while y0 < height {
+        let rows = usize::min(STREAM_BAND_ROWS, height - y0);
+        let band: Vec<f64> = generate_rect_threaded(generator,
+                                                    width,
+                                                    height,
+                                                    y0,
+                                                    rows,
+                                                    options.adaptive_poles,
+                                                    options.supersample,
+                                                    options.threads);
+        png.write_rows(&quantize_rows(output.format, &band, width, y0, output.tone, output.dither));
+        y0 += rows;
+    }
+    png.finish();
+}
+
+// Writes the outputs alongside the equirectangular map written to `filename`
+// which don't depend on its elevations.
+fn write_rect_sidecars(filename: &str,
+                       generator: &Module,
+                       width: usize,
+                       height: usize,
+                       seed: i32,
+                       output: OutputOptions,
+                       options: &RectOptions) {
+
+    if options.emit_vrt {
+        let source = vrt::VrtSource {
+            filename: output.paths.file_name("lat_lon.png"),
+            x: 0,
+            y: 0,
+            width: width,
+            height: height,
+        };
+        vrt::write_vrt(&output.paths.path("lat_lon.vrt"), width, height, &[source], output);
+    }
+
+    if output.export_coords {
+        write_coords(filename,
+                     width,
+                     height,
+                     seed,
+                     Projection::Equirectangular,
+                     |x, y| rect_lat_lon(width, height, x, y));
+    }
+
+    if output.normals || output.hillshade.is_some() || output.ao.is_some() {
+        let step = std::f64::consts::PI / height as f64;
+        write_normal_outputs(filename, generator, width, height, step, output, |x, y| {
+            let (lat, lon) = rect_lat_lon(width, height, x, y);
+            let pos = lat_lon_to_pos(lat, lon);
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+            let east = (-lon.sin(), 0.0, lon.cos());
+            let north = (-lat.sin() * lon.cos(), lat.cos(), -lat.sin() * lon.sin());
+            (pos, east, north)
+        });
+    }
+}
+
+// Outputs the equirectangular map as "lat_lon_X_Y.png" tiles of at most
+// `tile_size` pixels square, where X and Y are the column and row of the tile
+// counting from the north west.  Only one tile is held in memory at a time.
+// Returns the tiles written, for a VRT to reference.
+fn output_rect_tiles(seed: i32,
+                     generator: &Module,
+                     width: usize,
+                     height: usize,
+                     tile_size: usize,
+                     adaptive_poles: bool,
+                     supersample: usize,
+                     output: OutputOptions)
+                     -> Vec<vrt::VrtSource> {
+    let columns = (width + tile_size - 1) / tile_size;
+    let rows = (height + tile_size - 1) / tile_size;
+    let mut tiles = Vec::with_capacity(columns * rows);
+
+    for tile_y in 0..rows {
+        for tile_x in 0..columns {
+            let x0 = tile_x * tile_size;
+            let y0 = tile_y * tile_size;
+            let tile_width = usize::min(tile_size, width - x0);
+            let tile_height = usize::min(tile_size, height - y0);
+            let dest_buffer: Vec<f64> = generate_rect_region(generator,
+                                                             width,
+                                                             height,
+                                                             x0,
+                                                             y0,
+                                                             tile_width,
+                                                             tile_height,
+                                                             adaptive_poles,
+                                                             supersample);
+
+            let name = format!("lat_lon_{}_{}.png", tile_x, tile_y);
+            let filename = output.paths.path(&name);
+            write_output_to_file(&filename,
+                                 &dest_buffer,
+                                 tile_width,
+                                 tile_height,
+                                 seed,
+                                 Projection::Equirectangular,
+                                 output);
+
+            if output.export_coords {
+                write_coords(&filename,
+                             tile_width,
+                             tile_height,
+                             seed,
+                             Projection::Equirectangular,
+                             |x, y| rect_lat_lon(width, height, x0 + x, y0 + y));
+            }
+
+            tiles.push(vrt::VrtSource {
+                filename: output.paths.file_name(&name),
+                x: x0,
+                y: y0,
+                width: tile_width,
+                height: tile_height,
+            });
+        }
+    }
+
+    tiles
+}
+
+// Size of a slippy map tile, in pixels.
+const XYZ_TILE_SIZE: usize = 256;
+
+// Converts a position in the Web Mercator projection, with both coordinates
+// normalized to [0, 1] and the origin at the top left (north west), to a
+// latitude and longitude.
+fn web_mercator_to_lat_lon(u: f64, v: f64) -> (f64, f64) {
+    let lon = u * 360.0 - 180.0;
+    let lat = f64::atan(f64::sinh(std::f64::consts::PI * (1.0 - 2.0 * v))).to_degrees();
+    (lat, lon)
+}
+
+// Outputs every tile of the given zoom level as "z/x/y.png", using the
+// standard XYZ (slippy map) tiling of the Web Mercator projection.  Tiles
+// which already exist are skipped, so an interrupted run can be resumed.
+fn output_xyz_tiles(seed: i32,
+                    params: &PlanetParams,
+                    zoom: u32,
+                    threads: usize,
+                    output: OutputOptions) {
+    let tiles_per_side = 1usize << zoom;
+    let tile_count = tiles_per_side * tiles_per_side;
+    let next_tile = Arc::new(AtomicUsize::new(0));
+
+    let generator = create_generator(seed, params);
+    let mut joins = Vec::new();
+    for _ in 0..threads {
+        let next_tile = next_tile.clone();
+        let generator = generator.clone();
+        joins.push(std::thread::spawn(move || {
+            let map_size = (tiles_per_side * XYZ_TILE_SIZE) as f64;
+            let mut dest_buffer: Vec<f64> = vec![0.0; XYZ_TILE_SIZE * XYZ_TILE_SIZE];
+
+            loop {
+                let tile = next_tile.fetch_add(1, Ordering::SeqCst);
+                if tile >= tile_count {
+                    break;
+                }
+
+                let tile_x = tile % tiles_per_side;
+                let tile_y = tile / tiles_per_side;
+                let tile_lat_lon = |x: usize, y: usize| {
+                    let u = ((tile_x * XYZ_TILE_SIZE + x) as f64 + 0.5) / map_size;
+                    let v = ((tile_y * XYZ_TILE_SIZE + y) as f64 + 0.5) / map_size;
+                    web_mercator_to_lat_lon(u, v)
+                };
+                let dir = output.paths.path(&format!("{}/{}", zoom, tile_x));
+                let filename = format!("{}/{}.png", dir, tile_y);
+                if Path::new(&filename).with_extension(output.format.extension()).exists() {
+                    continue;
+                }
+
+                for y in 0..XYZ_TILE_SIZE {
+                    for x in 0..XYZ_TILE_SIZE {
+                        let (lat, lon) = tile_lat_lon(x, y);
+                        let pos = lat_lon_to_pos(lat, lon);
+                        dest_buffer[y * XYZ_TILE_SIZE + x] = generator.get_value(pos.0,
+                                                                                 pos.1,
+                                                                                 pos.2);
+                    }
+                }
+
+                fs::create_dir_all(&dir).expect("Failed to create tile directory");
+                write_output_to_file(&filename,
+                                     &dest_buffer,
+                                     XYZ_TILE_SIZE,
+                                     XYZ_TILE_SIZE,
+                                     seed,
+                                     Projection::WebMercator,
+                                     output);
+
+                if output.export_coords {
+                    write_coords(&filename,
+                                 XYZ_TILE_SIZE,
+                                 XYZ_TILE_SIZE,
+                                 seed,
+                                 Projection::WebMercator,
+                                 &tile_lat_lon);
+                }
+            }
+        }));
+    }
+
+    for join in joins {
+        join.join().unwrap();
+    }
+}
+
+// The latitude and longitude of sample (x, y) of an equirectangular grid, as
+// sampled by `generate_rect`.
+fn rect_lat_lon(width: usize, height: usize, x: usize, y: usize) -> (f64, f64) {
+    let lat = -90.0 + ((height - 1 - y) as f64 / height as f64) * 180.0;
+    let lon = -180.0 + (x as f64 / width as f64) * 360.0;
+    (lat, lon)
+}
+
+// The distance covered by a pixel of an image `width` pixels wide of a planet
+// of the given radius, along with where on the image that distance applies.
+// None if the projection has no single meaningful scale.
+fn pixel_scale(projection: Projection, width: usize, radius: f64) -> Option<(f64, &'static str)> {
+    match projection {
+        Projection::Equirectangular => {
+            Some((2.0 * std::f64::consts::PI * radius / width as f64, "AT EQUATOR"))
+        }
+        // Native faces are sampled edge to edge, Khronos faces at texel
+        // centres, see `cube_face_pos`.
+        Projection::CubeXP | Projection::CubeXN | Projection::CubeYP | Projection::CubeYN |
+        Projection::CubeZP | Projection::CubeZN => {
+            Some((2.0 * radius / (width - 1) as f64, "AT CENTRE"))
+        }
+        Projection::KhronosPX | Projection::KhronosNX | Projection::KhronosPY |
+        Projection::KhronosNY | Projection::KhronosPZ | Projection::KhronosNZ => {
+            Some((2.0 * radius / width as f64, "AT CENTRE"))
+        }
+        // Four faces across a cross and three across a grid.
+        Projection::CubeCross => Some((2.0 * radius / (width / 4 - 1) as f64, "AT FACE CENTRE")),
+        Projection::CubeGrid => Some((2.0 * radius / (width / 3 - 1) as f64, "AT FACE CENTRE")),
+        Projection::KhronosCross => Some((2.0 * radius / (width / 4) as f64, "AT FACE CENTRE")),
+        Projection::KhronosGrid => Some((2.0 * radius / (width / 3) as f64, "AT FACE CENTRE")),
+        // True along the equator, though not in the north-south direction.
+        Projection::Mollweide => {
+            Some((2.0 * std::f64::consts::PI * radius / width as f64, "AT EQUATOR"))
+        }
+        Projection::WebMercator | Projection::Planar | Projection::Custom => None,
+    }
+}
+
+// The area of the planet's surface covered by each sample of row `y` of an
+// equirectangular grid, relative to the samples on the equator.
+fn rect_row_weight(width: usize, height: usize, y: usize) -> f64 {
+    let (lat, _) = rect_lat_lon(width, height, 0, y);
+    f64::cos(lat.to_radians())
+}
+
+// Number of bisection steps taken by `sea_level_for_ocean_fraction`, enough
+// to find the level to well within the precision of any output format.
+const SEA_LEVEL_SEARCH_STEPS: usize = 40;
+
+// Finds the elevation which has the given fraction of the area of an
+// equirectangular grid at or below it, by bisection.
+fn sea_level_for_ocean_fraction(elevations: &[f64],
+                                width: usize,
+                                height: usize,
+                                fraction: f64)
+                                -> f64 {
+    let ocean_fraction = |level: f64| {
+        let mut total_area = 0.0;
+        let mut ocean_area = 0.0;
+        for y in 0..height {
+            let weight = rect_row_weight(width, height, y);
+            let row = &elevations[(y * width)..((y + 1) * width)];
+            total_area += weight * width as f64;
+            ocean_area += weight * row.iter().filter(|&&value| value <= level).count() as f64;
+        }
+        ocean_area / total_area
+    };
+
+    let mut lower = elevations.iter().cloned().fold(std::f64::INFINITY, f64::min);
+    let mut upper = elevations.iter().cloned().fold(std::f64::NEG_INFINITY, f64::max);
+    for _ in 0..SEA_LEVEL_SEARCH_STEPS {
+        let level = (lower + upper) / 2.0;
+        if ocean_fraction(level) < fraction {
+            lower = level;
+        } else {
+            upper = level;
+        }
+    }
+    upper
+}
+
+// Exits with an error if any of the generated elevations of an image is NaN or
+// infinite, reporting the first such pixel and where it was sampled.
+// `lat_lon` gives the latitude and longitude of each pixel, or None for
+// pixels outside the map, which aren't checked.
+fn check_finite<T, F>(filename: &str, data: &[T], width: usize, lat_lon: F)
+    where T: Elevation,
+          F: Fn(usize, usize) -> Option<(f64, f64)>
+{
+    let mut count = 0;
+    let mut first = None;
+    for (idx, value) in data.iter().map(|&value| value.to_f64()).enumerate() {
+        let (x, y) = (idx % width, idx / width);
+        if let Some(pos) = lat_lon(x, y) {
+            if !value.is_finite() {
+                count += 1;
+                first = first.or(Some((x, y, pos, value)));
+            }
+        }
+    }
+
+    if let Some((x, y, (lat, lon), value)) = first {
+        println!("{}: {} elevations aren't finite, the first {} at pixel ({}, {}), latitude {} \
+                  longitude {}",
+                 filename,
+                 count,
+                 value,
+                 x,
+                 y,
+                 lat,
+                 lon);
+        std::process::exit(1);
+    }
+}
+
+// Writes the latitude and longitude, in degrees, at which each pixel of an
+// output image was sampled, as heightmaps alongside it.  For "xp.png" these
+// are "xp_lat.cph" and "xp_lon.cph".
+fn write_coords<F>(filename: &str,
+                   width: usize,
+                   height: usize,
+                   seed: i32,
+                   projection: Projection,
+                   lat_lon: F)
+    where F: Fn(usize, usize) -> (f64, f64)
+{
+    let mut lats = Vec::with_capacity(width * height);
+    let mut lons = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (lat, lon) = lat_lon(x, y);
+            lats.push(lat);
+            lons.push(lon);
+        }
+    }
+
+    let header = cph::CphHeader {
+        data_type: cph::DataType::F64,
+        projection: projection,
+        width: width as u32,
+        height: height as u32,
+        seed: seed,
+    };
+    let stem = Path::new(filename).with_extension("");
+    let stem = stem.to_str().unwrap();
+    for &(suffix, values) in &[("lat", &lats), ("lon", &lons)] {
+        cph::write_cph(&format!("{}_{}.cph", stem, suffix), &header, values)
+            .expect("Failed to write heightmap data");
+    }
+}
+
+// Writes the outputs derived from the shape of the terrain around an output
+// image alongside it: for "xp.png" the tangent space normal map "xp_n.png",
+// the hillshade "xp_hillshade.png" and the ambient occlusion "xp_ao.png", as
+// requested.  The normals are of the terrain as generated, sampled `step`
+// radians either side of each pixel, see `normals::sphere_normals`, and the
+// occlusion is sampled as in `occlusion::sphere_occlusion`.
+fn write_normal_outputs<F>(filename: &str,
+                           generator: &Module,
+                           width: usize,
+                           height: usize,
+                           step: f64,
+                           output: OutputOptions,
+                           frame: F)
+    where F: Fn(usize, usize) -> ((f64, f64, f64), (f64, f64, f64), (f64, f64, f64))
+{
+    let stem = Path::new(filename).with_extension("");
+    let stem = stem.to_str().unwrap();
+
+    if let Some((radius, rays)) = output.ao {
+        let occlusion = occlusion::sphere_occlusion(generator, width, height, radius, rays,
+                                                    output.normal_strength, &frame);
+        let file = File::create(Path::new(&format!("{}_ao.png", stem)))
+            .expect("Failed to create file for writing");
+        PNGEncoder::new(BufWriter::new(file))
+            .encode(&occlusion, width as u32, height as u32, ColorType::Gray(8))
+            .expect("Failed to write image data");
+    }
+
+    if !output.normals && output.hillshade.is_none() {
+        return;
+    }
+    let normals =
+        normals::sphere_normals(generator, width, height, step, output.normal_strength, frame);
+
+    if output.normals {
+        normals::write_normal_map(&format!("{}_n.png", stem), &normals, width, height);
+    }
+
+    if let Some((azimuth, altitude)) = output.hillshade {
+        let shade = normals::hillshade(&normals, azimuth, altitude);
+        let file = File::create(Path::new(&format!("{}_hillshade.png", stem)))
+            .expect("Failed to create file for writing");
+        PNGEncoder::new(BufWriter::new(file))
+            .encode(&shade, width as u32, height as u32, ColorType::Gray(8))
+            .expect("Failed to write image data");
+    }
+}
+
+// Quantizes an elevation to an integer between 0 (for -1.0) and `max` (for
+// +1.0).  `offset` is added before truncating, an offset of 0.0 simply
+// truncates while offsets spread evenly over [0, 1) dither the result.
+fn quantize_elevation(value: f64, max: i32, offset: f64) -> i32 {
+    let value = (value + 1.0) / 2.0;
+    let value = (f64_clamp(value, 0.0, 1.0) * max as f64 + offset) as i32;
+    clamp(value, 0, max)
+}
+
+// As `quantize_elevation`, but between 0 and 2^48 - 1, which doesn't fit in
+// an i32.
+fn quantize_elevation48(value: f64, offset: f64) -> u64 {
+    let max = ((1u64 << 48) - 1) as f64;
+    let value = (value + 1.0) / 2.0;
+    f64::min(f64_clamp(value, 0.0, 1.0) * max + offset, max) as u64
+}
+
+// How the elevations of the greyscale and colour formats are mapped to
+// brightnesses before quantizing them.
+#[derive(Clone, Copy)]
+struct Tone {
+    // The elevations stretched to the full range of the format, the lowest
+    // to black and the highest to white.  None is the full -1.0 to +1.0.
+    range: Option<(f64, f64)>,
+    gamma: f64,
+    // Whether the highest elevations are displayed darkest and the lowest
+    // lightest, bringing out the structure of the ocean floor.
+    invert: bool,
+}
+
+impl Tone {
+    // Maps an elevation to the elevation displayed: stretched from `range`
+    // to -1.0 to +1.0, then scaled to [0, 1] as for `quantize_elevation`,
+    // raised to the power 1 / `gamma` and scaled back, then negated if
+    // `invert` is set.
+    fn apply(&self, value: f64) -> f64 {
+        let value = match self.range {
+            Some((lo, hi)) => (value - lo) / (hi - lo) * 2.0 - 1.0,
+            None => value,
+        };
+        let value = if self.gamma == 1.0 {
+            value
+        } else {
+            let value = f64_clamp((value + 1.0) / 2.0, 0.0, 1.0);
+            value.powf(1.0 / self.gamma) * 2.0 - 1.0
+        };
+        // Negating an elevation from -1.0 to +1.0 is the same as taking
+        // 1.0 - v of it scaled to [0, 1].
+        if self.invert { -value } else { value }
+    }
+}
+
+// 8x8 ordered dithering matrix.
+const BAYER_MATRIX: [[u8; 8]; 8] = [[0, 32, 8, 40, 2, 34, 10, 42],
+                                    [48, 16, 56, 24, 50, 18, 58, 26],
+                                    [12, 44, 4, 36, 14, 46, 6, 38],
+                                    [60, 28, 52, 20, 62, 30, 54, 22],
+                                    [3, 35, 11, 43, 1, 33, 9, 41],
+                                    [51, 19, 59, 27, 49, 17, 57, 25],
+                                    [15, 47, 7, 39, 13, 45, 5, 37],
+                                    [63, 31, 55, 23, 61, 29, 53, 21]];
+
+// The offset for `quantize_elevation` which dithers pixel (x, y).
+fn dither_offset(x: usize, y: usize) -> f64 {
+    (BAYER_MATRIX[y % 8][x % 8] as f64 + 0.5) / 64.0
+}
+
+// Quantizes the elevations of whole rows of an image, the first of them row
+// `y0`, to the pixels of one of the greyscale or colour formats.
+fn quantize_rows<T: Elevation>(output_format: OutputFormat,
+                               data: &[T],
+                               width: usize,
+                               y0: usize,
+                               tone: Tone,
+                               dither: bool)
+                               -> Vec<u8> {
+    let pixel_size = match output_format {
+        OutputFormat::Greyscale8 | OutputFormat::WebP(_, None) => 1,
+        OutputFormat::Greyscale16 | OutputFormat::Pgm16 => 2,
+        OutputFormat::Colour24 | OutputFormat::Ppm8 => 3,
+        OutputFormat::Colour48 => 6,
+        _ => unreachable!(),
+    };
+    let mut img_data = Vec::with_capacity(data.len() * pixel_size);
+    for (idx, &value) in data.iter().enumerate() {
+        let (x, y) = (idx % width, y0 + idx / width);
+        let offset = if dither { dither_offset(x, y) } else { 0.0 };
+        let value = tone.apply(value.to_f64());
+        match output_format {
+            OutputFormat::Greyscale8 | OutputFormat::WebP(_, None) => {
+                img_data.push(quantize_elevation(value, 0xff, offset) as u8);
+            }
+            OutputFormat::Greyscale16 | OutputFormat::Pgm16 => {
+                let value = quantize_elevation(value, 0xffff, offset);
+                img_data.push(((value & 0xff00) >> 8) as u8);
+                img_data.push((value & 0x00ff) as u8);
+            }
+            OutputFormat::Colour24 | OutputFormat::Ppm8 => {
+                let value = quantize_elevation(value, 0xffffff, offset);
+                img_data.push(((value & 0x00ff0000) >> 16) as u8);
+                img_data.push(((value & 0x0000ff00) >> 8) as u8);
+                img_data.push((value & 0x000000ff) as u8);
+            }
+            OutputFormat::Colour48 => {
+                let value = quantize_elevation48(value, offset);
+                // Three big-endian 16-bit channels, red the most significant.
+                for shift in &[40, 32, 24, 16, 8, 0] {
+                    img_data.push((value >> shift) as u8);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    img_data
+}
+
+// Writes an ENVI header describing a `width` by `height` raw image of
+// little-endian 64-bit floats, which GDAL picks up alongside the image.
+fn write_envi_header(filename: &str, width: usize, height: usize) {
+    let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+    let mut writer = BufWriter::new(file);
+    write!(writer,
+           "ENVI\nsamples = {}\nlines = {}\nbands = 1\nheader offset = 0\n\
+            file type = ENVI Standard\ndata type = 5\ninterleave = bsq\nbyte order = 0\n",
+           width,
+           height)
+        .expect("Failed to write header");
+}
+
+// Whether a --config file is a lock file written by --emit-lock, which is
+// JSON, rather than a planet file.
+fn is_lock_file(filename: &str) -> bool {
+    let mut contents = String::new();
+    File::open(Path::new(filename))
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .expect("Failed to read config file");
+    contents.trim_start().starts_with('{')
+}
+
+// Writes the elevations to an image or heightmap of the given format.  The
+// seed and projection are only recorded by formats with a header for them.
+fn write_output_to_file<T: Elevation>(filename: &str,
+                                      data: &[T],
+                                      width: usize,
+                                      height: usize,
+                                      seed: i32,
+                                      projection: Projection,
+                                      output: OutputOptions) {
+    if let Some(metadata) = output.metadata {
+        let filename = Path::new(filename).with_extension("json");
+        lock::write_metadata(filename.to_str().unwrap(), &metadata, seed, projection, width,
+                             height);
+    }
+
+    let output_format = output.format;
+    let in_band = |value: f64| match output.elevation_band {
+        Some((min, max)) => value >= min && value <= max,
+        None => !value.is_nan(),
+    };
+
+    let tone = if output.auto_normalize {
+        let (lo, hi) = data.iter()
+            .map(|&value| value.to_f64())
+            .filter(|&value| in_band(value))
+            .fold((std::f64::INFINITY, std::f64::NEG_INFINITY),
+                  |(lo, hi), value| (f64::min(lo, value), f64::max(hi, value)));
+        eprintln!("{}: elevations range from {},{}", filename, lo, hi);
+        Tone {
+            range: if lo < hi { Some((lo, hi)) } else { None },
+            ..output.tone
+        }
+    } else {
+        output.tone
+    };
+
+    // Out of band samples, including NaN samples such as the background
+    // outside a map, are made transparent where the format has an alpha
+    // channel, are written as NaN in heightmaps, and are otherwise written as
+    // the lowest elevation.
+    let has_alpha = match output_format {
+        OutputFormat::Greyscale8 |
+        OutputFormat::Greyscale16 |
+        OutputFormat::Colour24 |
+        OutputFormat::Colour48 |
+        OutputFormat::Tinted(_) => output.elevation_band.is_some(),
+        _ => false,
+    };
+    let masked_data: Vec<T>;
+    let data = if !has_alpha &&
+                  (output.elevation_band.is_some() ||
+                   data.iter().any(|value| value.to_f64().is_nan())) {
+        let background = match output_format {
+            OutputFormat::Cph | OutputFormat::RawF64 => std::f64::NAN,
+            _ => -1.0,
+        };
+        masked_data = data.iter()
+            .map(|&value| if in_band(value.to_f64()) { value } else { T::from_f64(background) })
+            .collect();
+        &masked_data[..]
+    } else {
+        data
+    };
+
+    let annotated_data: Vec<T>;
+    let data = if let Some(annotation) = output.annotate {
+        annotated_data = annotate::annotate(&T::widen(data), width, height, seed, projection,
+                                            &annotation)
+            .into_iter()
+            .map(T::from_f64)
+            .collect();
+        &annotated_data[..]
+    } else {
+        data
+    };
+
+    if let OutputFormat::Indexed(colours, method) = output_format {
+        let (palette, indices) = palette::quantize(&T::widen(data), colours, method);
+        indexed_png::write_indexed_png(filename, &indices, width, height, &palette);
+        return;
+    }
+
+    if let OutputFormat::Cph = output_format {
+        let header = cph::CphHeader {
+            data_type: cph::DataType::F32,
+            projection: projection,
+            width: width as u32,
+            height: height as u32,
+            seed: seed,
+        };
+        let filename = Path::new(filename).with_extension(output_format.extension());
+        cph::write_cph(filename.to_str().unwrap(), &header, &T::widen(data))
+            .expect("Failed to write heightmap data");
+        return;
+    }
+
+    if let OutputFormat::Int16Signed(metres_per_unit) = output_format {
+        let filename = Path::new(filename).with_extension(output_format.extension());
+        let file = File::create(filename).expect("Failed to create file for writing");
+        let mut writer = BufWriter::new(file);
+        for y in 0..height {
+            for x in 0..width {
+                // Round to the nearest metre, or dither.
+                let offset = if output.dither { dither_offset(x, y) } else { 0.5 };
+                let metres = (data[y * width + x].to_f64() - SEA_LEVEL) * metres_per_unit +
+                             offset;
+                let value = f64_clamp(metres.floor(), i16::min_value() as f64,
+                                      i16::max_value() as f64) as i16;
+                writer.write_all(&value.to_le_bytes()).expect("Failed to write image data");
+            }
+        }
+        return;
+    }
+
+    if let OutputFormat::RawF64 = output_format {
+        let filename = Path::new(filename).with_extension(output_format.extension());
+        let file = File::create(&filename).expect("Failed to create file for writing");
+        let mut writer = BufWriter::new(file);
+        for &value in data {
+            writer.write_all(&value.to_f64().to_le_bytes()).expect("Failed to write image data");
+        }
+        write_envi_header(filename.with_extension("hdr").to_str().unwrap(), width, height);
+        return;
+    }
+
+    if let OutputFormat::VectorField(spacing) = output_format {
+        vector_field::render_vector_field(&T::widen(data), width, height, spacing)
+            .write_png(filename);
+        return;
+    }
+
+    let img_data = match output_format {
+        OutputFormat::Greyscale8 |
+        OutputFormat::WebP(_, None) |
+        OutputFormat::Greyscale16 |
+        OutputFormat::Pgm16 |
+        OutputFormat::Colour24 |
+        OutputFormat::Ppm8 |
+        OutputFormat::Colour48 => quantize_rows(output_format, data, width, 0, tone, output.dither),
+        OutputFormat::OceanMask => {
+            data.iter()
+                .map(|&value| if value.to_f64() > SEA_LEVEL { 0xff } else { 0x00 })
+                .collect()
+        }
+        OutputFormat::Tinted(gradient) |
+        OutputFormat::WebP(_, Some(gradient)) => {
+            let mut img_data = Vec::with_capacity(width * height * 3);
+            for &value in data {
+                img_data.extend_from_slice(&gradient.colour(value.to_f64()));
+            }
+            img_data
+        }
+        OutputFormat::RgbaNormalHeight(exaggeration) => {
+            let radius = PLANET_CIRCUMFERENCE / (2.0 * std::f64::consts::PI);
+            let spacing = match pixel_scale(projection, width, radius) {
+                Some((spacing, _)) => spacing,
+                None => unreachable!(),
+            };
+            let data = T::widen(data);
+            let normals = normals::image_normals(&data, width, height, spacing, exaggeration);
+            let mut img_data = Vec::with_capacity(width * height * 4);
+            for y in 0..height {
+                for x in 0..width {
+                    let idx = y * width + x;
+                    let offset = if output.dither { dither_offset(x, y) } else { 0.0 };
+                    img_data.extend_from_slice(&normals::encode_normal(normals[idx]));
+                    img_data.push(quantize_elevation(data[idx], 0xff, offset) as u8);
+                }
+            }
+            img_data
+        }
+        OutputFormat::Indexed(..) |
+        OutputFormat::Cph |
+        OutputFormat::VectorField(_) |
+        OutputFormat::DdsCube |
+        OutputFormat::Int16Signed(_) |
+        OutputFormat::RawF64 => unreachable!(),
+    };
+
+    let img_data = if has_alpha {
+        let pixel_size = img_data.len() / (width * height);
+        let alpha_size = match output_format {
+            OutputFormat::Greyscale16 | OutputFormat::Colour48 => 2,
+            _ => 1,
+        };
+        let mut with_alpha = Vec::with_capacity((pixel_size + alpha_size) * width * height);
+        for (pixel, &value) in img_data.chunks(pixel_size).zip(data.iter()) {
+            with_alpha.extend_from_slice(pixel);
+            let alpha = if in_band(value.to_f64()) { 0xff } else { 0x00 };
+            with_alpha.extend(std::iter::repeat(alpha).take(alpha_size));
+        }
+        with_alpha
+    } else {
+        img_data
+    };
+
+    if let OutputFormat::WebP(quality, gradient) = output_format {
+        let rgb = match gradient {
+            Some(_) => img_data,
+            None => img_data.iter().flat_map(|&value| vec![value, value, value]).collect(),
+        };
+        let filename = Path::new(filename).with_extension(output_format.extension());
+        webp::write_webp(filename.to_str().unwrap(), &rgb, width, height, quality);
+        return;
+    }
+
+    // Netpbm samples are big-endian, as they already are in `img_data`.
+    let netpbm = match output_format {
+        OutputFormat::Pgm16 => Some(("P5", 65535)),
+        OutputFormat::Ppm8 => Some(("P6", 255)),
+        _ => None,
+    };
+    if let Some((magic, maxval)) = netpbm {
+        let filename = Path::new(filename).with_extension(output_format.extension());
+        let file = File::create(filename).expect("Failed to create file for writing");
+        let mut writer = BufWriter::new(file);
+        write!(writer, "{}\n{} {}\n{}\n", magic, width, height, maxval)
+            .expect("Failed to write image data");
+        writer.write_all(&img_data).expect("Failed to write image data");
+        return;
+    }
+
+    let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+    let writer = BufWriter::new(file);
+
+    let ct = match output_format {
+        OutputFormat::Greyscale8 if has_alpha => ColorType::GrayA(8),
+        OutputFormat::Greyscale16 if has_alpha => ColorType::GrayA(16),
+        OutputFormat::Colour24 | OutputFormat::Tinted(_) if has_alpha => ColorType::RGBA(8),
+        OutputFormat::Colour48 if has_alpha => ColorType::RGBA(16),
+        OutputFormat::Greyscale8 | OutputFormat::OceanMask => ColorType::Gray(8),
+        OutputFormat::Greyscale16 => ColorType::Gray(16),
+        OutputFormat::Colour24 | OutputFormat::Tinted(_) => ColorType::RGB(8),
+        OutputFormat::Colour48 => ColorType::RGB(16),
+        OutputFormat::RgbaNormalHeight(_) => ColorType::RGBA(8),
+        OutputFormat::Indexed(..) |
+        OutputFormat::WebP(..) |
+        OutputFormat::Cph |
+        OutputFormat::VectorField(_) |
+        OutputFormat::DdsCube |
+        OutputFormat::Int16Signed(_) |
+        OutputFormat::RawF64 |
+        OutputFormat::Pgm16 |
+        OutputFormat::Ppm8 => unreachable!(),
+    };
+
+    let encoder = PNGEncoder::new(writer);
+
+    encoder.encode(&img_data, width as u32, height as u32, ct)
+        .expect("Failed to encode image data");
+}
+
+// Seeds which aren't integers are hashed to one with 32-bit FNV-1a over their
+// UTF-8 bytes, so that memorable words can be used as seeds.  The hash must
+// never change, or the same word would give a different planet.
+fn parse_seed(seed: &str) -> i32 {
+    if let Ok(seed) = i32::from_str(seed) {
+        return seed;
+    }
+    let mut hash: u32 = 0x811c9dc5;
+    for &byte in seed.as_bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash as i32
+}
+
+// Exits with an error if an image is too large to be written as WebP.
+fn check_webp_dimensions(width: usize, height: usize) {
+    if width > webp::MAX_DIMENSION || height > webp::MAX_DIMENSION {
+        println!("A {}x{} image is too large for webp output, which is at most {} pixels wide \
+                  and high",
+                 width,
+                 height,
+                 webp::MAX_DIMENSION);
+        std::process::exit(1);
+    }
+}
+
+fn parse_positive_f64(matches: &ArgMatches, name: &str) -> f64 {
+    match f64::from_str(matches.value_of(name).unwrap()) {
+        Ok(value) if value > 0.0 => value,
+        _ => {
+            println!("--{} must be a positive number", name);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let app = App::new("ComplexPlanet")
         .version(crate_version!())
         .about("Generate maps for a complex planetary surface. Based on the libnoise \
                 complexplanet example")
@@ -1771,52 +2325,2550 @@ fn main() {
             .long("seed")
             .default_value("0")
             .help("Specifies the seed to use to generate the planet, different seeds give \
-                   different planets"))
+                   different planets.  Seeds which aren't integers, such as words, are hashed \
+                   to one"))
+        .arg(Arg::with_name("seed-range")
+            .long("seed-range")
+            .takes_value(true)
+            .value_name("START..END")
+            .allow_hyphen_values(true)
+            .help("Renders each of the seeds from START up to but not including END, prefixing \
+                   the names of each seed's output files with its seed, e.g. seed_5_lat_lon.png, \
+                   or STR_5_lat_lon.png with --prefix STR.  As many seeds are rendered at once \
+                   as there are --threads, or the threads are shared between them if there are \
+                   fewer seeds"))
+        .arg(Arg::with_name("diff-seed")
+            .long("diff-seed")
+            .takes_value(true)
+            .value_name("SEED")
+            .allow_hyphen_values(true)
+            .help("Also outputs diff.png, the difference between the rect map and that of \
+                   SEED, scaled so that the largest difference is white, and prints the RMS \
+                   and largest differences to stderr"))
+        .arg(Arg::with_name("seeds")
+            .long("seeds")
+            .takes_value(true)
+            .value_name("LIST")
+            .requires("contact-sheet")
+            .help("Specifies a comma separated list of seeds for --contact-sheet, hashed as for \
+                   --seed"))
+        .arg(Arg::with_name("contact-sheet")
+            .long("contact-sheet")
+            .takes_value(true)
+            .value_name("COLS")
+            .requires("seeds")
+            .help("Outputs contact_sheet.png, small elevation tinted maps of each of the seeds \
+                   given by --seeds labelled with their seed, in rows of the given number of \
+                   columns"))
         .arg(Arg::with_name("type")
             .long("type")
             .takes_value(true)
             .default_value("cube")
             .possible_value("cube")
             .possible_value("rect")
+            .possible_value("passport")
+            .possible_value("mesh")
             .help("Specifies what format to output in"))
+        .arg(Arg::with_name("cubemap-order")
+            .long("cubemap-order")
+            .default_value("native")
+            .possible_value("native")
+            .possible_value("khronos")
+            .help("Specifies how the faces of cube output are named and oriented, khronos \
+                   writes px.png, nx.png, py.png, ny.png, pz.png and nz.png oriented for use as \
+                   an OpenGL, KTX or VR skybox cubemap.  Native faces share their edge pixels \
+                   exactly, khronos faces are sampled at texel centres so theirs don't"))
+        .arg(Arg::with_name("face")
+            .long("face")
+            .default_value("all")
+            .possible_value("xp")
+            .possible_value("xn")
+            .possible_value("yp")
+            .possible_value("yn")
+            .possible_value("zp")
+            .possible_value("zn")
+            .possible_value("all")
+            .help("Outputs only this face of cube output, e.g. zp for the face written as \
+                   zp.png (pz.png with --cubemap-order khronos)"))
+        .arg(Arg::with_name("cube-layout")
+            .long("cube-layout")
+            .takes_value(true)
+            .possible_value("cross")
+            .possible_value("grid")
+            .help("Outputs the faces of cube output together in one image rather than six, \
+                   cube_cross.png, a horizontal cross with the edges of the faces lined up, or \
+                   cube_grid.png, three faces wide and two high"))
+        .arg(Arg::with_name("mesh-exaggeration")
+            .long("mesh-exaggeration")
+            .default_value("1.0")
+            .help("Multiplies the height of the terrain of the mesh output"))
+        .arg(Arg::with_name("mesh-exaggerate")
+            .long("mesh-exaggerate")
+            .default_value("both")
+            .possible_value("positions")
+            .possible_value("normals")
+            .possible_value("both")
+            .help("Specifies whether --mesh-exaggeration applies to the positions of the \
+                   vertices, the normals used for shading, or both"))
+        .arg(Arg::with_name("mesh-radius")
+            .long("mesh-radius")
+            .default_value("1.0")
+            .help("Specifies the radius of the mesh output at an elevation of 0.0, in OBJ \
+                   or STL units"))
+        .arg(Arg::with_name("mesh-format")
+            .long("mesh-format")
+            .default_value("obj")
+            .possible_value("obj")
+            .possible_value("stl")
+            .possible_value("glb")
+            .help("Specifies the file format of the mesh output: planet.obj, planet.stl, a \
+                   closed binary STL for 3D printing, or planet.glb, a binary glTF textured \
+                   with the elevations coloured by --palette (earth by default)"))
+        .arg(Arg::with_name("mesh-flat-oceans")
+            .long("mesh-flat-oceans")
+            .help("Raises the sea floor of the mesh output to sea level, giving smooth oceans"))
         .arg(Arg::with_name("width")
             .long("width")
             .default_value("1024")
             .help("Specifies the width of the images to generate"))
+        .arg(Arg::with_name("max-pixels")
+            .long("max-pixels")
+            .default_value("268435456")
+            .help("Refuses to output untiled cube and rect images, passports, meshes and \
+                   contact sheets with more pixels than this in all, by default a 16384x16384 \
+                   image, needing about 3 GB, to fail clearly rather than running out of \
+                   memory.  Cube layouts count their whole image, and meshes count their \
+                   vertices and texture"))
+        .arg(Arg::with_name("supersample")
+            .long("supersample")
+            .default_value("1")
+            .value_name("N")
+            .help("Averages N by N samples spread over each pixel of the cube faces and rect \
+                   map, to smooth out sparkly single pixel noise"))
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .takes_value(true)
+            .help("Specifies how many threads generate the cube faces, rect map and XYZ tiles, \
+                   by default one per CPU; the output is the same whatever the count"))
+        .arg(Arg::with_name("stream")
+            .long("stream")
+            .help("Writes the rect map as a PNG a band of rows at a time as it is generated, so \
+                   that the memory used doesn't grow with its height.  Only the greyscale and \
+                   colour formats are supported, and none of the outputs needing the whole map"))
+        .arg(Arg::with_name("precision")
+            .long("precision")
+            .possible_value("f32")
+            .possible_value("f64")
+            .default_value("f64")
+            .help("Specifies the precision the elevations of untiled cube and rect output are \
+                   held in until they are written.  f32 halves the memory of large images, and \
+                   only changes the odd pixel of the 8 and 16-bit formats, but the outputs \
+                   derived from the elevations of rect maps aren't supported with it"))
+        .arg(Arg::with_name("height")
+            .long("height")
+            .takes_value(true)
+            .help("Specifies the height of the rect map, by default half of --width"))
+        .arg(Arg::with_name("projection")
+            .long("projection")
+            .default_value("equirectangular")
+            .possible_value("equirectangular")
+            .possible_value("mollweide")
+            .possible_value("gnomonic")
+            .possible_value("orthographic")
+            .help("Specifies the projection of the rect map, mollweide writes mollweide.png, an \
+                   equal-area map of the globe as an ellipse filling the image, gnomonic writes \
+                   gnomonic.png, the planet seen from orbit above --center, and orthographic \
+                   writes orthographic.png, the globe as a disc centred on --center"))
+        .arg(Arg::with_name("center")
+            .long("center")
+            .default_value("0,0")
+            .value_name("LAT,LON")
+            .allow_hyphen_values(true)
+            .help("Specifies the point at the centre of the image for --projection gnomonic \
+                   and orthographic"))
+        .arg(Arg::with_name("fov")
+            .long("fov")
+            .default_value("60")
+            .value_name("DEGREES")
+            .help("Specifies the horizontal field of view of --projection gnomonic"))
+        .arg(Arg::with_name("view-distance")
+            .long("view-distance")
+            .default_value("3")
+            .value_name("RADII")
+            .help("Specifies the distance of the camera of --projection gnomonic from the centre \
+                   of the planet, in planetary radii"))
+        .arg(Arg::with_name("background")
+            .long("background")
+            .takes_value(true)
+            .value_name("ELEV")
+            .allow_hyphen_values(true)
+            .help("Specifies the elevation written outside the map, around the ellipse of \
+                   --projection mollweide, the planet of --projection gnomonic and orthographic \
+                   or the cross of --cube-layout cross.  By default it is \
+                   treated as out of --elevation-band"))
+        .arg(Arg::with_name("query")
+            .long("query")
+            .takes_value(true)
+            .value_name("LAT,LON")
+            .allow_hyphen_values(true)
+            .help("Prints the elevation at the given latitude and longitude, in degrees, \
+                   instead of outputting any images"))
+        .arg(Arg::with_name("normalized")
+            .long("normalized")
+            .requires("query")
+            .help("Also prints the elevation of --query mapped from -1.0..+1.0 to 0..1"))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .takes_value(true)
+            .value_name("LAT1,LON1:LAT2,LON2")
+            .allow_hyphen_values(true)
+            .help("Prints the elevations along the great circle arc between two points as CSV, \
+                   instead of outputting any images"))
+        .arg(Arg::with_name("samples")
+            .long("samples")
+            .default_value("256")
+            .help("Specifies the number of points sampled along the arc of --profile"))
+        .arg(Arg::with_name("profile-file")
+            .long("profile-file")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("profile")
+            .help("Writes the CSV of --profile to FILE instead of printing it"))
+        .arg(Arg::with_name("search")
+            .long("search")
+            .help("Searches for seeds, counting up from --seed, which produce a planet \
+                   matching --want-land-fraction and --want-continents, instead of outputting \
+                   any images"))
+        .arg(Arg::with_name("want-land-fraction")
+            .long("want-land-fraction")
+            .takes_value(true)
+            .requires("search")
+            .help("Specifies the wanted fraction of the surface covered by land for --search"))
+        .arg(Arg::with_name("want-continents")
+            .long("want-continents")
+            .takes_value(true)
+            .requires("search")
+            .help("Specifies the wanted number of continents for --search"))
+        .arg(Arg::with_name("tolerance")
+            .long("tolerance")
+            .default_value("0.05")
+            .help("Specifies how far from --want-land-fraction the land fraction may be"))
+        .arg(Arg::with_name("find-ocean")
+            .long("find-ocean")
+            .takes_value(true)
+            .value_name("LO..HI")
+            .help("Searches for seeds as --search does, printing those with a fraction of the \
+                   surface covered by ocean between LO and HI, e.g. 0.65..0.75.  Can be \
+                   combined with the other criteria of --search"))
+        .arg(Arg::with_name("search-width")
+            .long("search-width")
+            .default_value("256")
+            .value_name("PIXELS")
+            .help("Specifies the width of the coarse map each seed is measured on by --search \
+                   and --find-ocean, smaller is faster but less accurate"))
+        .arg(Arg::with_name("max-hits")
+            .long("max-hits")
+            .default_value("10")
+            .help("Stops --search and --find-ocean after finding this many matching seeds"))
+        .arg(Arg::with_name("max-attempts")
+            .long("max-attempts")
+            .default_value("1000")
+            .help("Stops --search and --find-ocean after trying this many seeds"))
+        .arg(Arg::with_name("planar-tileable")
+            .long("planar-tileable")
+            .takes_value(true)
+            .value_name("GROUP")
+            .possible_value("mountains")
+            .possible_value("hills")
+            .possible_value("plains")
+            .possible_value("badlands")
+            .help("Outputs planar.png, a texture of the given terrain group which tiles \
+                   seamlessly, --width pixels square, instead of using --type"))
+        .arg(Arg::with_name("mapping-file")
+            .long("mapping-file")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Outputs mapped.png, sampling each pixel in the direction given for it by the \
+                   mapping file, which must be --width pixels wide, instead of using --type"))
+        .arg(Arg::with_name("planar-extent")
+            .long("planar-extent")
+            .default_value("0.01")
+            .help("Specifies the size of the area covered by --planar-tileable, relative to \
+                   the radius of the planet"))
+        .arg(Arg::with_name("region")
+            .long("region")
+            .takes_value(true)
+            .value_name("MINLAT,MINLON,MAXLAT,MAXLON")
+            .allow_hyphen_values(true)
+            .help("Outputs region.png, a map of just the given box of latitudes and longitudes \
+                   in degrees, instead of the whole globe.  A MINLON greater than MAXLON \
+                   crosses the antimeridian.  The height defaults to giving square pixels \
+                   (rect output only)"))
+        .arg(Arg::with_name("elevation-band")
+            .long("elevation-band")
+            .takes_value(true)
+            .value_name("MIN,MAX")
+            .allow_hyphen_values(true)
+            .help("Only outputs samples with an elevation between MIN and MAX, the rest are \
+                   made transparent where the format allows, or else written as the lowest \
+                   elevation"))
+        .arg(Arg::with_name("legend")
+            .long("legend")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Also writes a legend image showing the colour of each elevation in the \
+                   chosen format"))
+        .arg(Arg::with_name("export-coords")
+            .long("export-coords")
+            .help("Also writes the latitude and longitude each pixel was sampled at, as two \
+                   heightmaps named after each image with _lat.cph and _lon.cph appended \
+                   (cube, rect and tile output only)"))
+        .arg(Arg::with_name("dither")
+            .long("dither")
+            .help("Dithers the elevations when quantizing them for integer formats, trading a \
+                   little noise for less banding in flat areas"))
+        .arg(Arg::with_name("gamma")
+            .long("gamma")
+            .default_value("1.0")
+            .help("Brightens the midtones of the greyscale and colour formats by raising the \
+                   elevations, scaled from 0 to 1, to the power 1 / this before quantizing \
+                   them"))
+        .arg(Arg::with_name("invert")
+            .long("invert")
+            .help("Inverts the greyscale and colour formats after applying --gamma and \
+                   --range, so that the deepest ocean is white and the highest mountains \
+                   black"))
+        .arg(Arg::with_name("range")
+            .long("range")
+            .takes_value(true)
+            .value_name("LO,HI")
+            .allow_hyphen_values(true)
+            .help("Stretches the elevations from LO to HI over the full range of the greyscale \
+                   and colour formats, rather than -1.0 to +1.0, clamping those outside it.  \
+                   Overrides --auto-normalize"))
+        .arg(Arg::with_name("auto-normalize")
+            .long("auto-normalize")
+            .help("Stretches the elevations of each image from its lowest to its highest over \
+                   the full range of the greyscale and colour formats, printing the range so \
+                   that it can be given to --range"))
+        .arg(Arg::with_name("strict")
+            .long("strict")
+            .help("Exits with an error, reporting where, if the generator produces a NaN or \
+                   infinite elevation in cube, untiled rect or mollweide output, rather than \
+                   writing it as the lowest elevation"))
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .help("Prints the minimum, maximum, mean and standard deviation of the elevations to \
+                   stderr, along with the percentage of the samples below sea level, for the \
+                   untiled rect map or all the cube faces together"))
+        .arg(Arg::with_name("adaptive-poles")
+            .long("adaptive-poles")
+            .help("Samples fewer points along the rows of the rect map near the poles and \
+                   interpolates between them, which is faster with little loss of detail"))
+        .arg(Arg::with_name("emit-vrt")
+            .long("emit-vrt")
+            .help("Also writes lat_lon.vrt, a GDAL virtual raster which georeferences the rect \
+                   map and combines its tiles into a single dataset"))
+        .arg(Arg::with_name("tile-size")
+            .long("tile-size")
+            .takes_value(true)
+            .help("Outputs the rect map as tiles of at most this many pixels square, named \
+                   lat_lon_X_Y.png, so that only one tile is held in memory at a time"))
+        .arg(Arg::with_name("max-memory")
+            .long("max-memory")
+            .takes_value(true)
+            .value_name("MB")
+            .conflicts_with("tile-size")
+            .help("Switches the rect map to tiled output, as with --tile-size, if outputting \
+                   it as a single image would need more than this many megabytes"))
         .arg(Arg::with_name("format")
             .long("format")
             .default_value("greyscale8")
             .possible_value("greyscale8")
             .possible_value("greyscale16")
-            .possible_value("colour24"))
-        .get_matches();
+            .possible_value("colour24")
+            .possible_value("colour48")
+            .possible_value("pgm16")
+            .possible_value("ppm8")
+            .possible_value("webp")
+            .possible_value("cph")
+            .possible_value("vector-field")
+            .possible_value("dds-cube")
+            .possible_value("int16-signed")
+            .possible_value("rgba-normal-height")
+            .possible_value("rawf64")
+            .possible_value("ocean-mask"))
+        .arg(Arg::with_name("output-dir")
+            .long("output-dir")
+            .takes_value(true)
+            .value_name("DIR")
+            .help("Writes the output files into this directory, creating it if missing"))
+        .arg(Arg::with_name("prefix")
+            .long("prefix")
+            .takes_value(true)
+            .value_name("STR")
+            .help("Prefixes the names of the output files with STR_, e.g. STR_xp.png"))
+        .arg(Arg::with_name("normal-strength")
+            .long("normal-strength")
+            .default_value("1.0")
+            .help("Multiplies the height of the terrain when computing the normals of \
+                   rgba-normal-height output, --normals and --hillshade, and the occlusion of \
+                   --ao"))
+        .arg(Arg::with_name("normals")
+            .long("normals")
+            .help("Also writes a tangent space normal map of each image as an RGB PNG, e.g. \
+                   xp_n.png, computed by sampling the terrain either side of each pixel so \
+                   that the normals match across the cube's seams"))
+        .arg(Arg::with_name("hillshade")
+            .long("hillshade")
+            .help("Also writes each image shaded by the slope of the terrain towards the sun \
+                   as a greyscale PNG, e.g. xp_hillshade.png"))
+        .arg(Arg::with_name("sun-azimuth")
+            .long("sun-azimuth")
+            .default_value("315")
+            .value_name("DEGREES")
+            .help("Specifies the direction of the sun for --hillshade, clockwise from up the \
+                   image (north on rect maps)"))
+        .arg(Arg::with_name("sun-altitude")
+            .long("sun-altitude")
+            .default_value("45")
+            .value_name("DEGREES")
+            .help("Specifies the angle of the sun above the horizon for --hillshade"))
+        .arg(Arg::with_name("ao")
+            .long("ao")
+            .help("Also writes the ambient occlusion of each image as a greyscale PNG, e.g. \
+                   xp_ao.png, white where the sky is open and darker where the surrounding \
+                   terrain rises above the horizon"))
+        .arg(Arg::with_name("ao-radius")
+            .long("ao-radius")
+            .default_value("20000")
+            .value_name("METRES")
+            .help("Specifies how far from each pixel the terrain is sampled for --ao"))
+        .arg(Arg::with_name("ao-rays")
+            .long("ao-rays")
+            .default_value("8")
+            .value_name("N")
+            .help("Specifies the number of directions the terrain is sampled in for --ao"))
+        .arg(Arg::with_name("elevation-range")
+            .long("elevation-range")
+            .default_value("8192")
+            .value_name("METRES")
+            .help("Specifies the elevation in metres of the highest possible terrain, used to \
+                   scale int16-signed output and the --lapse-rate of --temperature"))
+        .arg(Arg::with_name("vector-spacing")
+            .long("vector-spacing")
+            .default_value("16")
+            .help("Specifies the spacing in pixels of the arrows drawn by --format vector-field"))
+        .arg(Arg::with_name("webp-quality")
+            .long("webp-quality")
+            .default_value("100")
+            .help("Specifies the quality of webp output from 0 to 100, 100 is lossless and \
+                   lower qualities round off up to five low bits of each channel before \
+                   encoding, trading precision for smaller files"))
+        .arg(Arg::with_name("peak-variation")
+            .long("peak-variation")
+            .default_value("1.0")
+            .help("Specifies how much the heights of mountain peaks and hilltops vary, 0.0 \
+                   gives peaks of uniform height"))
+        .arg(Arg::with_name("mountain-scale-frequency")
+            .long("mountain-scale-frequency")
+            .default_value("1.0")
+            .help("Multiplies the frequencies of the mountainous terrain, lower values give \
+                   broader mountain ranges"))
+        .arg(Arg::with_name("hill-scale-frequency")
+            .long("hill-scale-frequency")
+            .default_value("1.0")
+            .help("Multiplies the frequencies of the hilly terrain, lower values give broader \
+                   hills"))
+        .arg(Arg::with_name("turbulence-detail")
+            .long("turbulence-detail")
+            .default_value("1.0")
+            .help("Multiplies the roughness of the turbulence warping the terrain, lower values \
+                   give smoother coastlines and ridges and are faster to generate"))
+        .arg(Arg::with_name("turbulence-power")
+            .long("turbulence-power")
+            .default_value("1.0")
+            .help("Multiplies the power of all the turbulence warping the terrain, from the \
+                   coastlines to the mountains and rivers, lower values give smoother, \
+                   straighter terrain and higher values more chaotic"))
+        .arg(Arg::with_name("quality")
+            .long("quality")
+            .default_value("balanced")
+            .possible_value("fast")
+            .possible_value("balanced")
+            .possible_value("best")
+            .help("Specifies the quality of the noise, fast uses standard quality noise \
+                   everywhere for previews and best uses the best quality everywhere for final \
+                   renders"))
+        .arg(Arg::with_name("detail")
+            .long("detail")
+            .default_value("high")
+            .possible_value("low")
+            .possible_value("medium")
+            .possible_value("high")
+            .help("Specifies how many octaves of noise make up the terrain, low uses half as \
+                   many as high for previews several times faster to generate, and medium three \
+                   quarters"))
+        .arg(Arg::with_name("terrain-blend")
+            .long("terrain-blend")
+            .default_value("1.0")
+            .help("Multiplies the width of the transitions between terrain types, 0 gives \
+                   sharp boundaries and higher values blend them more gently"))
+        .arg(Arg::with_name("river-orders")
+            .long("river-orders")
+            .default_value("2")
+            .help("Specifies the number of orders of rivers, each smaller and shallower than \
+                   the last, from 1 to 8"))
+        .arg(Arg::with_name("dune-wind-direction")
+            .long("dune-wind-direction")
+            .takes_value(true)
+            .value_name("DEG")
+            .allow_hyphen_values(true)
+            .help("Aligns the sand dunes of the badlands across a wind blowing from the given \
+                   direction, in degrees clockwise from north"))
+        .arg(Arg::with_name("relief-only")
+            .long("relief-only")
+            .help("Subtracts the base continent elevations, leaving only the relief added by \
+                   the terrain features"))
+        .arg(Arg::with_name("debug-group")
+            .long("debug-group")
+            .takes_value(true)
+            .possible_value("continents")
+            .possible_value("mountains")
+            .possible_value("hills")
+            .possible_value("plains")
+            .possible_value("badlands")
+            .possible_value("rivers")
+            .possible_value("river-depth")
+            .possible_value("terrain-type")
+            .conflicts_with("relief-only")
+            .help("Outputs only this layer of the planet, to see what it contributes: the base \
+                   continents, a terrain group before it is placed on them, the river \
+                   positions, the depth the rivers are carved to (white where they aren't \
+                   carved, black where they are carved the full river depth), or the terrain \
+                   type definition which places the groups"))
+        .arg(Arg::with_name("max-walkable-slope")
+            .long("max-walkable-slope")
+            .takes_value(true)
+            .value_name("DEGREES")
+            .help("Also outputs walkable.png, a mask of the land whose slope is less than the \
+                   given angle (rect output only)"))
+        .arg(Arg::with_name("target-ocean-fraction")
+            .long("target-ocean-fraction")
+            .takes_value(true)
+            .value_name("F")
+            .help("Raises or lowers sea level until the given fraction of the surface is ocean, \
+                   writing elevations relative to the new sea level (rect output only)"))
+        .arg(Arg::with_name("annotate")
+            .long("annotate")
+            .help("Draws the projection and seed into the corner of the images, along with a \
+                   scale bar if --planet-radius is given"))
+        .arg(Arg::with_name("planet-radius")
+            .long("planet-radius")
+            .takes_value(true)
+            .value_name("KM")
+            .requires("annotate")
+            .help("Specifies the radius of the planet in kilometres for the --annotate scale \
+                   bar"))
+        .arg(Arg::with_name("fill-basins")
+            .long("fill-basins")
+            .help("Fills the basins on the land which don't drain to the sea with lakes, \
+                   written at sea level (rect output only)"))
+        .arg(Arg::with_name("crust-threshold")
+            .long("crust-threshold")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("ELEVATION")
+            .help("Flattens everything below the given elevation, in planetary elevation units, \
+                   into a deep ocean floor, leaving only the peaks above it (rect output only)"))
+        .arg(Arg::with_name("temperature")
+            .long("temperature")
+            .help("Also outputs temperature.cph, the mean surface temperature in degrees Celsius \
+                   of the rect map, or xp_temperature.cph and so on for each cube face"))
+        .arg(Arg::with_name("temperature-ramp")
+            .long("temperature-ramp")
+            .requires("temperature")
+            .help("Also draws the temperatures as temperature.png (xp_temperature.png and so \
+                   on for cube faces) with a thermal colour ramp from -40 to +40 degrees Celsius"))
+        .arg(Arg::with_name("style")
+            .long("style")
+            .takes_value(true)
+            .possible_value("earthlike")
+            .help("Also outputs the map, or the globe of --projection orthographic, drawn in the \
+                   given style as earthlike.png and so on, or xp_earthlike.png and so on for \
+                   each cube face. earthlike draws flat blue \
+                   oceans, a light shoreline, green to brown land and snow above a snow line \
+                   which lowers towards the poles"))
+        .arg(Arg::with_name("equator-temp")
+            .long("equator-temp")
+            .default_value("30")
+            .allow_hyphen_values(true)
+            .help("Specifies the temperature at sea level on the equator, in degrees Celsius"))
+        .arg(Arg::with_name("pole-temp")
+            .long("pole-temp")
+            .default_value("-25")
+            .allow_hyphen_values(true)
+            .help("Specifies the temperature at sea level at the poles, in degrees Celsius"))
+        .arg(Arg::with_name("lapse-rate")
+            .long("lapse-rate")
+            .default_value("6.5")
+            .help("Specifies how much colder the land gets with height, in degrees Celsius per \
+                   kilometre"))
+        .arg(Arg::with_name("climate-tilt")
+            .long("climate-tilt")
+            .takes_value(true)
+            .value_name("DEG")
+            .help("Tilts the poles of the climate of --temperature and the snow line of --style \
+                   by DEG degrees (0 to 90) from the geographic poles, the north pole towards \
+                   longitude 0, to show the climate of a planet with that obliquity without \
+                   moving the terrain"))
+        .arg(Arg::with_name("terrain-branches")
+            .long("terrain-branches")
+            .help("Also outputs terrain_branches.png, coloured by which of the plains, hills, \
+                   mountains and badlands the terrain selectors chose, blending where they \
+                   blend (rect output only)"))
+        .arg(Arg::with_name("insolation")
+            .long("insolation")
+            .help("Also outputs insolation.cph, the solar energy reaching the surface averaged \
+                   over a year in W/m², accounting for the slope of the land (rect output only)"))
+        .arg(Arg::with_name("moisture")
+            .long("moisture")
+            .help("Also outputs moisture.png, the moisture from dry (black) to wet (white) \
+                   given by noise independent of the terrain (rect output only)"))
+        .arg(Arg::with_name("moisture-damping")
+            .long("moisture-damping")
+            .takes_value(true)
+            .value_name("KM")
+            .requires("moisture")
+            .help("Dries out the land away from the ocean for --moisture, the moisture falling \
+                   by a factor of e every KM kilometres inland"))
+        .arg(Arg::with_name("contours")
+            .long("contours")
+            .takes_value(true)
+            .value_name("METRES")
+            .help("Also outputs contours.png, a contour line every METRES metres of elevation, \
+                   black on land and blue below sea level, drawn over the colours of --palette \
+                   if given (rect output only)"))
+        .arg(Arg::with_name("obliquity")
+            .long("obliquity")
+            .default_value("23.44")
+            .value_name("DEGREES")
+            .help("Specifies the axial tilt of the planet for --insolation, in degrees"))
+        .arg(Arg::with_name("hypsograph")
+            .long("hypsograph")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Also writes a chart of the share of the surface at each elevation, weighted \
+                   by area, to the given PNG file (rect output only)"))
+        .arg(Arg::with_name("beach-band")
+            .long("beach-band")
+            .takes_value(true)
+            .value_name("HEIGHT")
+            .help("Also outputs beach.png, a mask of the land at most the given height above sea \
+                   level in planetary elevation units (rect output only)"))
+        .arg(Arg::with_name("sun-lat")
+            .long("sun-lat")
+            .takes_value(true)
+            .requires("sun-lon")
+            .help("Also outputs sunlit.png, an elevation tinted map with the night side \
+                   darkened for a sun overhead at this latitude (rect output only)"))
+        .arg(Arg::with_name("sun-lon")
+            .long("sun-lon")
+            .takes_value(true)
+            .requires("sun-lat")
+            .help("Specifies the longitude the sun is overhead for --sun-lat"))
+        .arg(Arg::with_name("city-lights")
+            .long("city-lights")
+            .requires("sun-lat")
+            .help("Lights up coastal land on the night side of sunlit.png"))
+        .arg(Arg::with_name("xyz-tiles")
+            .long("xyz-tiles")
+            .visible_alias("tiles")
+            .takes_value(true)
+            .value_name("ZOOM")
+            .help("Outputs all the Web Mercator slippy map tiles for the given zoom level as \
+                   z/x/y.png under --output-dir, generated in parallel by --threads, instead \
+                   of using --type"))
+        .arg(Arg::with_name("palette")
+            .long("palette")
+            .takes_value(true)
+            .help("Outputs an elevation tinted, indexed-colour PNG using at most the given \
+                   number of colours (2 to 256), or with earth an RGB PNG tinted with realistic \
+                   colours from ocean trenches to snow, instead of using --format"))
+        .arg(Arg::with_name("palette-file")
+            .long("palette-file")
+            .takes_value(true)
+            .value_name("FILE")
+            .conflicts_with("palette")
+            .help("Outputs an RGB PNG tinted with the gradient read from FILE, instead of using \
+                   --format.  Each line is a control point, \"elevation r g b\" with the \
+                   elevation from -1.0 to +1.0, and the colours are interpolated linearly \
+                   between them"))
+        .arg(Arg::with_name("palette-method")
+            .long("palette-method")
+            .default_value("terrain")
+            .possible_value("terrain")
+            .possible_value("median-cut")
+            .help("Specifies how the palette for --palette is chosen, either from the \
+                   built-in terrain colours or by quantizing the colours in the image"))
+        .arg(Arg::with_name("continent-persistence")
+            .long("continent-persistence")
+            .default_value("0.5")
+            .help("Specifies the persistence of the continents, higher values give rougher \
+                   coastlines"))
+        .arg(Arg::with_name("hills-persistence")
+            .long("hills-persistence")
+            .default_value("0.5")
+            .help("Specifies the persistence of the hills, higher values give rougher hills"))
+        .arg(Arg::with_name("plains-persistence")
+            .long("plains-persistence")
+            .default_value("0.5")
+            .help("Specifies the persistence of the plains, higher values give rougher \
+                   plains"))
+        .arg(Arg::with_name("continent-frequency")
+            .long("continent-frequency")
+            .default_value("1.0")
+            .help("Specifies the frequency of the continents, higher values give smaller, more \
+                   numerous continents"))
+        .arg(Arg::with_name("sea-level")
+            .long("sea-level")
+            .default_value("0.0")
+            .allow_hyphen_values(true)
+            .help("Specifies the sea level the terrain is generated around, between -1 and 1, \
+                   higher values give less land.  The output elevations are relative to it"))
+        .arg(Arg::with_name("mountains")
+            .long("mountains")
+            .default_value("0.5")
+            .value_name("AMOUNT")
+            .help("Specifies the amount of the terrain covered by mountains, from 0 (none) to 1 \
+                   (all of it, less any badlands)"))
+        .arg(Arg::with_name("hills")
+            .long("hills")
+            .takes_value(true)
+            .value_name("AMOUNT")
+            .help("Specifies the amount of the terrain covered by hills, from 0 to 1, which \
+                   must be above --mountains as the mountains cover the hills where they \
+                   overlap.  Defaults to halfway between --mountains and 1"))
+        .arg(Arg::with_name("badlands")
+            .long("badlands")
+            .default_value("0.03125")
+            .value_name("AMOUNT")
+            .help("Specifies the amount of the terrain covered by badlands, from 0 to 1, which \
+                   cover any other terrain where they overlap"))
+        .arg(Arg::with_name("shelf-level")
+            .long("shelf-level")
+            .default_value("-0.375")
+            .allow_hyphen_values(true)
+            .help("Specifies the level the continental shelves appear at, between -1 and 1, \
+                   which must be below --sea-level"))
+        .arg(Arg::with_name("metadata")
+            .long("metadata")
+            .help("Also writes a JSON file beside each image, e.g. xp.json, recording the seed, \
+                   output type, projection, size, format and terrain parameters it was \
+                   generated with"))
+        .arg(Arg::with_name("emit-lock")
+            .long("emit-lock")
+            .help("Also writes planet.lock, recording every option of the run including the \
+                   defaults, along with the version of complexplanet, for --config to \
+                   reproduce the output exactly"))
+        .arg(Arg::with_name("config")
+            .long("config")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Runs with the options recorded in a lock file written by --emit-lock, or \
+                   set in a TOML planet file of `option = value` lines giving the seed, \
+                   output type, format, size, projection and terrain options.  Options given \
+                   on the command line override those in the file"));
+    let matches = app.clone().get_matches();
+
+    // The file's arguments are parsed along with the command line's, so they
+    // are validated the same way, leaving out any the command line gives.
+    let matches = match matches.value_of("config") {
+        Some(filename) => {
+            let program = std::env::args().next().unwrap_or_else(|| "complexplanet".to_string());
+            let file_args = if is_lock_file(filename) {
+                lock::read_lock_args(filename).expect("Failed to read lock file")
+            } else {
+                match config::read_planet_file(filename) {
+                    Ok(args) => args,
+                    Err(err) => {
+                        println!("Failed to read planet file {}: {}", filename, err);
+                        std::process::exit(1);
+                    }
+                }
+            };
+
+            // --seed-range rejects --seed, and --find-ocean counting up from
+            // the seed a lock file recorded would only find it again, so the
+            // file's seed is left out of both.
+            let searching = matches.is_present("seed-range") || matches.is_present("find-ocean");
+            let file_args = file_args.into_iter().filter(|arg| {
+                let name = arg.trim_start_matches('-').split('=').next().unwrap();
+                matches.occurrences_of(name) == 0 && !(name == "seed" && searching)
+            });
+            let args = std::iter::once(program)
+                .chain(file_args)
+                .chain(std::env::args().skip(1));
+            app.get_matches_from(args)
+        }
+        None => matches,
+    };
+
+    if let Some(range) = matches.value_of("seed-range") {
+        let bounds: Vec<Result<i32, _>> = range.splitn(2, "..").map(i32::from_str).collect();
+        let (start, end) = match bounds[..] {
+            [Ok(start), Ok(end)] if start < end => (start, end),
+            _ => {
+                println!("Seed range must be given as START..END with integers START < END");
+                std::process::exit(1);
+            }
+        };
+        // Each seed is rendered on its own, so options which aren't about a
+        // single planet's output can't be used.
+        if matches.occurrences_of("seed") > 0 {
+            println!("--seed can't be combined with --seed-range");
+            std::process::exit(1);
+        }
+        for &arg in &["query", "search", "find-ocean", "contact-sheet", "emit-lock"] {
+            if matches.is_present(arg) {
+                println!("--{} is not supported with --seed-range", arg);
+                std::process::exit(1);
+            }
+        }
+        let threads = match matches.value_of("threads") {
+            Some(threads) => {
+                match usize::from_str(threads) {
+                    Ok(threads) if threads > 0 => threads,
+                    _ => {
+                        println!("Thread count must be a positive integer");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+        };
+        let seeds: Vec<i32> = (start..end).collect();
+        batch::run_batch(&matches, &seeds, threads);
+        return;
+    }
+
+    let seed_arg = matches.value_of("seed").unwrap();
+    let seed = parse_seed(seed_arg);
+    if i32::from_str(seed_arg).is_err() {
+        println!("Using seed {} for \"{}\"", seed, seed_arg);
+    }
+    render(matches, seed, None);
+}
+
+// A render of one seed of a --seed-range batch, see `batch::run_batch`.
+#[derive(Clone, Copy)]
+struct BatchJob {
+    // Replaces --prefix, so that each seed's files are named apart.
+    prefix: &'static str,
+    // Replaces --threads, sharing them between the renders run at once.
+    threads: usize,
+}
+
+// Renders the planet of `seed` with the options in `matches`, as one render of
+// a batch if `job` is given.
+fn render(matches: ArgMatches, seed: i32, job: Option<BatchJob>) {
+    // --find-ocean searches for seeds without needing --search.
+    let searching = matches.is_present("search") || matches.is_present("find-ocean");
+
+    let width = match usize::from_str(matches.value_of("width").unwrap()) {
+        Ok(width) if width > 0 => width,
+        _ => {
+            println!("Width must be a positive integer");
+            std::process::exit(1);
+        }
+    };
+
+    let height = match matches.value_of("height") {
+        Some(height) => {
+            match usize::from_str(height) {
+                Ok(height) if height > 0 => height,
+                _ => {
+                    println!("Height must be a positive integer");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => width / 2,
+    };
+    if matches.is_present("height") &&
+       (matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles") ||
+        matches.is_present("mapping-file")) {
+        println!("--height is only supported with --type rect");
+        std::process::exit(1);
+    }
+    if height == 0 && matches.value_of("type").unwrap() == "rect" {
+        println!("Width must be at least 2 for rect output, or give --height");
+        std::process::exit(1);
+    }
+
+    let output_format = match matches.value_of("format").unwrap() {
+        "greyscale8" => OutputFormat::Greyscale8,
+        "greyscale16" => OutputFormat::Greyscale16,
+        "colour24" => OutputFormat::Colour24,
+        "colour48" => OutputFormat::Colour48,
+        "pgm16" => OutputFormat::Pgm16,
+        "ppm8" => OutputFormat::Ppm8,
+        "webp" => {
+            match u32::from_str(matches.value_of("webp-quality").unwrap()) {
+                Ok(quality) if quality <= 100 => OutputFormat::WebP(quality, None),
+                _ => {
+                    println!("WebP quality must be an integer between 0 and 100");
+                    std::process::exit(1);
+                }
+            }
+        }
+        "cph" => OutputFormat::Cph,
+        "rawf64" => OutputFormat::RawF64,
+        "ocean-mask" => OutputFormat::OceanMask,
+        "dds-cube" => OutputFormat::DdsCube,
+        "int16-signed" => {
+            OutputFormat::Int16Signed(parse_positive_f64(&matches, "elevation-range"))
+        }
+        "rgba-normal-height" => {
+            OutputFormat::RgbaNormalHeight(parse_positive_f64(&matches, "normal-strength"))
+        }
+        "vector-field" => {
+            match usize::from_str(matches.value_of("vector-spacing").unwrap()) {
+                Ok(spacing) if spacing >= 4 => OutputFormat::VectorField(spacing),
+                _ => {
+                    println!("Vector spacing must be an integer of at least 4");
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    if let OutputFormat::DdsCube = output_format {
+        if matches.value_of("type").unwrap() != "cube" || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") {
+            println!("--format dds-cube is only supported with --type cube");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.value_of("supersample").unwrap() != "1" {
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+            println!("--supersample is only supported with cube and rect output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output_format {
+            println!("--supersample is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.is_present("auto-normalize") && matches.is_present("range") {
+        eprintln!("Warning: --range overrides --auto-normalize, using the range given");
+    }
+
+    if matches.is_present("auto-normalize") && !matches.is_present("range") {
+        if matches.is_present("legend") {
+            println!("--legend can't be combined with --auto-normalize, as the range of each \
+                      image differs; give the range printed by --auto-normalize to --range \
+                      instead");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output_format {
+            println!("--auto-normalize is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.is_present("cube-layout") {
+        if matches.value_of("type").unwrap() != "cube" || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+            println!("--cube-layout is only supported with --type cube");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output_format {
+            println!("--cube-layout is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+        if matches.value_of("face").unwrap() != "all" {
+            println!("--cube-layout can't be combined with --face");
+            std::process::exit(1);
+        }
+        for &arg in &["export-coords", "temperature", "style", "normals", "hillshade", "ao"] {
+            if matches.is_present(arg) {
+                println!("--cube-layout can't be combined with --{}, which is written per \
+                          face",
+                         arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if matches.value_of("face").unwrap() != "all" {
+        if matches.value_of("type").unwrap() != "cube" || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+            println!("--face is only supported with --type cube");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output_format {
+            println!("--face is not supported with --format dds-cube, which holds every face");
+            std::process::exit(1);
+        }
+    }
+
+    // The gradient palettes tint WebP output in place, and otherwise replace
+    // --format with RGB PNGs.
+    let tinted = |gradient: Gradient| match output_format {
+        OutputFormat::WebP(quality, _) => OutputFormat::WebP(quality, Some(gradient)),
+        _ => OutputFormat::Tinted(gradient),
+    };
+    let output_format = match matches.value_of("palette") {
+        Some("earth") => tinted(Gradient::Earth),
+        Some(_) if output_format.extension() == "webp" => {
+            println!("--palette with a number of colours writes an indexed PNG and can't be \
+                      combined with --format webp");
+            std::process::exit(1);
+        }
+        Some(colours) => {
+            let colours = match usize::from_str(colours) {
+                Ok(colours) if colours >= 2 && colours <= 256 => colours,
+                _ => {
+                    println!("Palette size must be an integer between 2 and 256");
+                    std::process::exit(1);
+                }
+            };
+            let method = match matches.value_of("palette-method").unwrap() {
+                "terrain" => PaletteMethod::Terrain,
+                "median-cut" => PaletteMethod::MedianCut,
+                _ => unreachable!(),
+            };
+            OutputFormat::Indexed(colours, method)
+        }
+        None => output_format,
+    };
+
+    let output_format = match matches.value_of("palette-file") {
+        Some(filename) => {
+            match palette::read_palette_file(filename) {
+                // Kept for the whole run, so that the output options stay Copy.
+                Ok(stops) => tinted(Gradient::Custom(Box::leak(stops.into_boxed_slice()))),
+                Err(err) => {
+                    println!("Failed to read palette file {}: {}", filename, err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => output_format,
+    };
+
+    let elevation_band = match matches.value_of("elevation-band") {
+        Some(band) => {
+            let bounds: Vec<Result<f64, _>> = band.split(',').map(f64::from_str).collect();
+            match bounds[..] {
+                [Ok(min), Ok(max)] if min <= max => Some((min, max)),
+                _ => {
+                    println!("Elevation band must be given as MIN,MAX with MIN <= MAX");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let output_dir = match matches.value_of("output-dir") {
+        Some(dir) => {
+            if let Err(err) = fs::create_dir_all(dir) {
+                println!("Failed to create output directory {}: {}", dir, err);
+                std::process::exit(1);
+            }
+            dir
+        }
+        None => "",
+    };
+    let leak = |s: &str| -> &'static str { Box::leak(s.to_string().into_boxed_str()) };
+
+    let mut output = OutputOptions {
+        format: output_format,
+        paths: OutputPaths {
+            dir: leak(output_dir),
+            prefix: match job {
+                Some(job) => Some(job.prefix),
+                None => matches.value_of("prefix").map(leak),
+            },
+        },
+        elevation_band: elevation_band,
+        background: match matches.value_of("background") {
+            Some(background) => {
+                match f64::from_str(background) {
+                    Ok(background) if background >= -1.0 && background <= 1.0 => background,
+                    _ => {
+                        println!("Background must be an elevation between -1.0 and 1.0");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => std::f64::NAN,
+        },
+        dither: matches.is_present("dither"),
+        precision: match matches.value_of("precision").unwrap() {
+            "f32" => Precision::F32,
+            "f64" => Precision::F64,
+            _ => unreachable!(),
+        },
+        tone: Tone {
+            range: match matches.value_of("range") {
+                Some(range) => {
+                    let bounds: Vec<Result<f64, _>> = range.split(',').map(f64::from_str).collect();
+                    match bounds[..] {
+                        [Ok(lo), Ok(hi)] if lo < hi => Some((lo, hi)),
+                        _ => {
+                            println!("Range must be given as LO,HI with LO < HI");
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                None => None,
+            },
+            gamma: parse_positive_f64(&matches, "gamma"),
+            invert: matches.is_present("invert"),
+        },
+        // --range takes precedence, see below.
+        auto_normalize: matches.is_present("auto-normalize") && !matches.is_present("range"),
+        export_coords: matches.is_present("export-coords"),
+        strict: matches.is_present("strict"),
+        stats: matches.is_present("stats"),
+        annotate: if matches.is_present("annotate") {
+            Some(annotate::Annotation {
+                planet_radius: if matches.is_present("planet-radius") {
+                    Some(parse_positive_f64(&matches, "planet-radius"))
+                } else {
+                    None
+                },
+            })
+        } else {
+            None
+        },
+        normals: matches.is_present("normals"),
+        hillshade: if matches.is_present("hillshade") {
+            let azimuth = match f64::from_str(matches.value_of("sun-azimuth").unwrap()) {
+                Ok(azimuth) if azimuth >= 0.0 && azimuth <= 360.0 => azimuth,
+                _ => {
+                    println!("Sun azimuth must be a number between 0 and 360");
+                    std::process::exit(1);
+                }
+            };
+            let altitude = match f64::from_str(matches.value_of("sun-altitude").unwrap()) {
+                Ok(altitude) if altitude >= 0.0 && altitude <= 90.0 => altitude,
+                _ => {
+                    println!("Sun altitude must be a number between 0 and 90");
+                    std::process::exit(1);
+                }
+            };
+            Some((azimuth, altitude))
+        } else {
+            None
+        },
+        ao: if matches.is_present("ao") {
+            let rays = match usize::from_str(matches.value_of("ao-rays").unwrap()) {
+                Ok(rays) if rays > 0 => rays,
+                _ => {
+                    println!("--ao-rays must be a positive integer");
+                    std::process::exit(1);
+                }
+            };
+            Some((parse_positive_f64(&matches, "ao-radius"), rays))
+        } else {
+            None
+        },
+        normal_strength: parse_positive_f64(&matches, "normal-strength"),
+        metadata: None,
+        temperature: None,
+        temperature_ramp: false,
+        style: None,
+        climate_tilt: 0.0,
+    };
+
+    let mut params = PlanetParams::default();
+    params.continent_persistence = parse_positive_f64(&matches, "continent-persistence");
+    params.hills_persistence = parse_positive_f64(&matches, "hills-persistence");
+    params.plains_persistence = parse_positive_f64(&matches, "plains-persistence");
+    params.continent_frequency = parse_positive_f64(&matches, "continent-frequency");
+    let parse_level = |name: &str| match f64::from_str(matches.value_of(name).unwrap()) {
+        Ok(level) if level >= -1.0 && level <= 1.0 => level,
+        _ => {
+            println!("--{} must be a number between -1 and 1", name);
+            std::process::exit(1);
+        }
+    };
+    params.sea_level = parse_level("sea-level");
+    params.shelf_level = parse_level("shelf-level");
+    if params.shelf_level >= params.sea_level {
+        println!("Shelf level ({}) must be below sea level ({})",
+                 params.shelf_level,
+                 params.sea_level);
+        std::process::exit(1);
+    }
+    let parse_amount = |name: &str| match f64::from_str(matches.value_of(name).unwrap()) {
+        Ok(amount) if amount >= 0.0 && amount <= 1.0 => amount,
+        _ => {
+            println!("--{} must be a number between 0 and 1", name);
+            std::process::exit(1);
+        }
+    };
+    params.mountains_amount = parse_amount("mountains");
+    // The hills follow the mountains unless given, as HILLS_AMOUNT does.
+    params.hills_amount = if matches.is_present("hills") {
+        parse_amount("hills")
+    } else {
+        (1.0 + params.mountains_amount) / 2.0
+    };
+    params.badlands_amount = parse_amount("badlands");
+    if params.hills_amount <= params.mountains_amount {
+        eprintln!("Warning: --hills ({}) isn't above --mountains ({}), so the mountains cover \
+                   all of the hilly terrain and no hills appear",
+                  params.hills_amount,
+                  params.mountains_amount);
+    }
+    params.peak_variation = match f64::from_str(matches.value_of("peak-variation").unwrap()) {
+        Ok(value) if value >= 0.0 => value,
+        _ => {
+            println!("Peak variation must be a non-negative number");
+            std::process::exit(1);
+        }
+    };
+    params.mountain_frequency_scale = parse_positive_f64(&matches, "mountain-scale-frequency");
+    params.hill_frequency_scale = parse_positive_f64(&matches, "hill-scale-frequency");
+    params.turbulence_detail = parse_positive_f64(&matches, "turbulence-detail");
+    params.turbulence_power = parse_positive_f64(&matches, "turbulence-power");
+    params.quality = match matches.value_of("quality").unwrap() {
+        "fast" => Quality::Fast,
+        "balanced" => Quality::Balanced,
+        "best" => Quality::Best,
+        _ => unreachable!(),
+    };
+    params.detail = match matches.value_of("detail").unwrap() {
+        "low" => Detail::Low,
+        "medium" => Detail::Medium,
+        "high" => Detail::High,
+        _ => unreachable!(),
+    };
+    params.terrain_blend = match f64::from_str(matches.value_of("terrain-blend").unwrap()) {
+        Ok(value) if value >= 0.0 => value,
+        _ => {
+            println!("Terrain blend must be a non-negative number");
+            std::process::exit(1);
+        }
+    };
+    params.river_orders = match usize::from_str(matches.value_of("river-orders").unwrap()) {
+        Ok(orders) if orders >= 1 && orders <= MAX_RIVER_ORDERS => orders,
+        _ => {
+            println!("River orders must be an integer between 1 and {}", MAX_RIVER_ORDERS);
+            std::process::exit(1);
+        }
+    };
+    params.dune_wind_direction = match matches.value_of("dune-wind-direction") {
+        Some(direction) => {
+            match f64::from_str(direction) {
+                Ok(direction) if direction.is_finite() => Some(direction),
+                _ => {
+                    println!("Dune wind direction must be a number of degrees");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+    params.relief_only = matches.is_present("relief-only");
+    params.debug_group = match matches.value_of("debug-group") {
+        Some("continents") => Some(DebugGroup::Continents),
+        Some("mountains") => Some(DebugGroup::Mountains),
+        Some("hills") => Some(DebugGroup::Hills),
+        Some("plains") => Some(DebugGroup::Plains),
+        Some("badlands") => Some(DebugGroup::Badlands),
+        Some("rivers") => Some(DebugGroup::Rivers),
+        Some("river-depth") => Some(DebugGroup::RiverDepth),
+        Some("terrain-type") => Some(DebugGroup::TerrainType),
+        None => None,
+        _ => unreachable!(),
+    };
+    // The layers and the relief aren't moved back to SEA_LEVEL as the planet
+    // is, so a sea level would only make the ocean of the other outputs
+    // disagree with them.
+    if params.sea_level != SEA_LEVEL && (params.relief_only || params.debug_group.is_some()) {
+        println!("--sea-level can't be combined with --relief-only or --debug-group");
+        std::process::exit(1);
+    }
+
+    let mut rect_options = RectOptions::default();
+
+    rect_options.max_walkable_slope = match matches.value_of("max-walkable-slope") {
+        Some(slope) => {
+            match f64::from_str(slope) {
+                Ok(slope) if slope > 0.0 && slope <= 90.0 => Some(slope),
+                _ => {
+                    println!("Maximum walkable slope must be an angle between 0 and 90 degrees");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    rect_options.beach_band = match matches.value_of("beach-band") {
+        Some(band) => {
+            match f64::from_str(band) {
+                Ok(band) if band > 0.0 => Some(band),
+                _ => {
+                    println!("Beach band must be a positive number");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    if let (Some(lat), Some(lon)) = (matches.value_of("sun-lat"), matches.value_of("sun-lon")) {
+        let lat = match f64::from_str(lat) {
+            Ok(lat) if lat >= -90.0 && lat <= 90.0 => lat,
+            _ => {
+                println!("Sun latitude must be between -90 and 90 degrees");
+                std::process::exit(1);
+            }
+        };
+        let lon = match f64::from_str(lon) {
+            Ok(lon) => lon,
+            Err(_) => {
+                println!("Sun longitude must be a number");
+                std::process::exit(1);
+            }
+        };
+        rect_options.sun = Some(SunPosition {
+            lat: lat,
+            lon: lon,
+            city_lights: matches.is_present("city-lights"),
+        });
+    }
+
+    if (rect_options.max_walkable_slope.is_some() || rect_options.beach_band.is_some() ||
+        rect_options.sun.is_some()) && matches.value_of("type").unwrap() != "rect" {
+        println!("--max-walkable-slope, --beach-band and --sun-lat are only supported with --type \
+                  rect");
+        std::process::exit(1);
+    }
+
+    if (matches.is_present("tile-size") || matches.is_present("max-memory")) &&
+       (matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles")) {
+        println!("--tile-size and --max-memory are only supported with --type rect");
+        std::process::exit(1);
+    }
+
+    rect_options.tile_size = match matches.value_of("tile-size") {
+        Some(tile_size) => {
+            match usize::from_str(tile_size) {
+                Ok(tile_size) if tile_size > 0 => Some(tile_size),
+                _ => {
+                    println!("Tile size must be a positive integer");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    if let Some(max_memory) = matches.value_of("max-memory") {
+        let max_memory = match usize::from_str(max_memory) {
+            Ok(max_memory) if max_memory > 0 => max_memory.saturating_mul(1024 * 1024),
+            _ => {
+                println!("Maximum memory must be a positive integer number of megabytes");
+                std::process::exit(1);
+            }
+        };
+        let estimate = estimate_rect_memory(width, height, output_format);
+        if estimate > max_memory {
+            let size = tile_size_for_memory(max_memory, output_format);
+            println!("Outputting as a single image needs about {} MB, outputting {}x{} tiles",
+                     estimate / (1024 * 1024),
+                     size,
+                     size);
+            rect_options.tile_size = Some(size);
+        } else {
+            println!("Outputting as a single image needs about {} MB, which fits",
+                     estimate / (1024 * 1024));
+        }
+    }
+
+    rect_options.adaptive_poles = matches.is_present("adaptive-poles");
+    rect_options.emit_vrt = matches.is_present("emit-vrt");
+
+    rect_options.supersample = match usize::from_str(matches.value_of("supersample").unwrap()) {
+        Ok(supersample) if supersample >= 1 && supersample <= 16 => supersample,
+        _ => {
+            println!("Supersampling factor must be an integer between 1 and 16");
+            std::process::exit(1);
+        }
+    };
+
+    rect_options.threads = match matches.value_of("threads") {
+        Some(threads) => {
+            match usize::from_str(threads) {
+                Ok(threads) if threads > 0 => threads,
+                _ => {
+                    println!("Thread count must be a positive integer");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+    };
+    if let Some(job) = job {
+        rect_options.threads = job.threads;
+    }
+
+    if rect_options.emit_vrt {
+        if matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles") {
+            println!("--emit-vrt is only supported with --type rect");
+            std::process::exit(1);
+        }
+        match output.format {
+            OutputFormat::Greyscale8 |
+            OutputFormat::Greyscale16 |
+            OutputFormat::Colour24 |
+            OutputFormat::Colour48 |
+            OutputFormat::OceanMask |
+            OutputFormat::Tinted(_) => {}
+            _ => {
+                println!("--emit-vrt is only supported with the greyscale8, greyscale16, \
+                          colour24, colour48 and ocean-mask formats and --palette earth");
+                std::process::exit(1);
+            }
+        }
+    }
+    rect_options.hypsograph = matches.value_of("hypsograph");
+
+    if rect_options.hypsograph.is_some() {
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--hypsograph is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--hypsograph cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    rect_options.target_ocean_fraction = match matches.value_of("target-ocean-fraction") {
+        Some(fraction) => {
+            match f64::from_str(fraction) {
+                Ok(fraction) if fraction >= 0.0 && fraction <= 1.0 => Some(fraction),
+                _ => {
+                    println!("Target ocean fraction must be a number between 0 and 1");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    if rect_options.target_ocean_fraction.is_some() {
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--target-ocean-fraction is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--target-ocean-fraction cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    rect_options.fill_basins = matches.is_present("fill-basins");
+
+    if rect_options.fill_basins {
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--fill-basins is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--fill-basins cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    rect_options.crust_threshold = match matches.value_of("crust-threshold") {
+        Some(threshold) => {
+            match f64::from_str(threshold) {
+                Ok(threshold) if threshold >= -1.0 && threshold <= 1.0 => Some(threshold),
+                _ => {
+                    println!("Crust threshold must be a number between -1 and 1");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    if rect_options.crust_threshold.is_some() {
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--crust-threshold is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--crust-threshold cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.is_present("temperature") {
+        let parse_temperature = |name: &str| match f64::from_str(matches.value_of(name).unwrap()) {
+            Ok(value) => value,
+            Err(_) => {
+                println!("--{} must be a number", name);
+                std::process::exit(1);
+            }
+        };
+        let lapse_rate = match f64::from_str(matches.value_of("lapse-rate").unwrap()) {
+            Ok(value) if value >= 0.0 => value,
+            _ => {
+                println!("Lapse rate must be a non-negative number");
+                std::process::exit(1);
+            }
+        };
+        output.temperature = Some(TemperatureModel {
+            equator: parse_temperature("equator-temp"),
+            pole: parse_temperature("pole-temp"),
+            lapse_rate: lapse_rate,
+            elevation_range: parse_positive_f64(&matches, "elevation-range"),
+        });
+        output.temperature_ramp = matches.is_present("temperature-ramp");
+
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+            println!("--temperature is only supported with cube and rect output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output.format {
+            println!("--temperature is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--temperature cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(tilt) = matches.value_of("climate-tilt") {
+        output.climate_tilt = match f64::from_str(tilt) {
+            Ok(tilt) if tilt >= 0.0 && tilt <= 90.0 => tilt,
+            _ => {
+                println!("Climate tilt must be a number of degrees between 0 and 90");
+                std::process::exit(1);
+            }
+        };
+        if !matches.is_present("temperature") && !matches.is_present("style") {
+            println!("--climate-tilt needs --temperature or --style");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(style) = matches.value_of("style") {
+        output.style = match style {
+            "earthlike" => Some(Style::Earthlike),
+            _ => unreachable!(),
+        };
+
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+            println!("--style is only supported with cube and rect output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output.format {
+            println!("--style is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--style cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.is_present("stats") {
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+            println!("--stats is only supported with cube and rect output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output.format {
+            println!("--stats is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--stats cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    rect_options.terrain_branches = matches.is_present("terrain-branches");
+
+    if rect_options.terrain_branches {
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--terrain-branches is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--terrain-branches cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.is_present("insolation") {
+        rect_options.insolation = match f64::from_str(matches.value_of("obliquity").unwrap()) {
+            Ok(obliquity) if obliquity >= 0.0 && obliquity <= 90.0 => Some(obliquity),
+            _ => {
+                println!("Obliquity must be a number between 0 and 90");
+                std::process::exit(1);
+            }
+        };
+
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--insolation is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--insolation cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    rect_options.moisture = matches.is_present("moisture");
+
+    if rect_options.moisture {
+        rect_options.moisture_damping = if matches.is_present("moisture-damping") {
+            Some(parse_positive_f64(&matches, "moisture-damping"))
+        } else {
+            None
+        };
+
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--moisture is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--moisture cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(other_seed) = matches.value_of("diff-seed") {
+        rect_options.diff_seed = Some(parse_seed(other_seed));
+
+        if matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") ||
+           searching || matches.is_present("contact-sheet") {
+            println!("--diff-seed is only supported with --type rect");
+            std::process::exit(1);
+        }
+        if rect_options.tile_size.is_some() {
+            println!("--diff-seed cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    if matches.is_present("contours") {
+        rect_options.contours = Some(parse_positive_f64(&matches, "contours"));
 
-    let seed = match i32::from_str(matches.value_of("seed").unwrap()) {
-        Ok(seed) => seed,
-        Err(_) => {
-            println!("Seed must be an integer");
+        if matches.value_of("type").unwrap() != "rect" {
+            println!("--contours is only supported with --type rect");
             std::process::exit(1);
         }
+        if rect_options.tile_size.is_some() {
+            println!("--contours cannot be used with tiled output");
+            std::process::exit(1);
+        }
+    }
+
+    let center = || {
+        let coords: Vec<Result<f64, _>> = matches.value_of("center")
+            .unwrap()
+            .split(',')
+            .map(f64::from_str)
+            .collect();
+        match coords[..] {
+            [Ok(lat), Ok(lon)] if lat.abs() <= 90.0 && lon.abs() <= 180.0 => (lat, lon),
+            _ => {
+                println!("Center must be given as LAT,LON with LAT between -90 and 90 and LON \
+                          between -180 and 180");
+                std::process::exit(1);
+            }
+        }
+    };
+    rect_options.projection = match matches.value_of("projection").unwrap() {
+        "equirectangular" => RectProjection::Equirectangular,
+        "mollweide" => RectProjection::Mollweide,
+        "gnomonic" => {
+            let (lat, lon) = center();
+            let fov = match f64::from_str(matches.value_of("fov").unwrap()) {
+                Ok(fov) if fov > 0.0 && fov < 180.0 => fov,
+                _ => {
+                    println!("Field of view must be a number of degrees between 0 and 180");
+                    std::process::exit(1);
+                }
+            };
+            let distance = match f64::from_str(matches.value_of("view-distance").unwrap()) {
+                Ok(distance) if distance > 1.0 => distance,
+                _ => {
+                    println!("View distance must be a number of planetary radii greater than 1");
+                    std::process::exit(1);
+                }
+            };
+            RectProjection::Gnomonic(gnomonic::View {
+                lat: lat,
+                lon: lon,
+                fov: fov,
+                distance: distance,
+            })
+        }
+        "orthographic" => {
+            let (lat, lon) = center();
+            RectProjection::Orthographic(orthographic::View { lat: lat, lon: lon })
+        }
+        _ => unreachable!(),
     };
+    let (centered, gnomonic) = match rect_options.projection {
+        RectProjection::Gnomonic(_) => (true, true),
+        RectProjection::Orthographic(_) => (true, false),
+        _ => (false, false),
+    };
+    if !centered && matches.occurrences_of("center") > 0 {
+        println!("--center is only supported with --projection gnomonic and orthographic");
+        std::process::exit(1);
+    }
+    if !gnomonic {
+        for &arg in &["fov", "view-distance"] {
+            if matches.occurrences_of(arg) > 0 {
+                println!("--{} is only supported with --projection gnomonic", arg);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let width = match usize::from_str(matches.value_of("width").unwrap()) {
-        Ok(seed) => seed,
-        Err(_) => {
-            println!("Width must be an integer");
+    rect_options.stream = matches.is_present("stream");
+    if rect_options.stream {
+        if matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") ||
+           searching || matches.is_present("contact-sheet") {
+            println!("--stream is only supported with --type rect");
+            std::process::exit(1);
+        }
+        match output.format {
+            OutputFormat::Greyscale8 |
+            OutputFormat::Greyscale16 |
+            OutputFormat::Colour24 |
+            OutputFormat::Colour48 => {}
+            _ => {
+                println!("--stream is only supported with --format greyscale8, greyscale16, \
+                          colour24 and colour48");
+                std::process::exit(1);
+            }
+        }
+        match rect_options.projection {
+            RectProjection::Equirectangular => {}
+            projection => {
+                println!("--stream is not supported with --projection {}", projection.name());
+                std::process::exit(1);
+            }
+        }
+        // Only a band of the map is ever held, so nothing which needs the
+        // whole map, or transparency, can be used.
+        for &arg in &["tile-size", "max-memory", "elevation-band", "auto-normalize", "annotate",
+                      "strict", "target-ocean-fraction", "crust-threshold", "fill-basins",
+                      "max-walkable-slope", "beach-band", "sun-lat", "terrain-branches",
+                      "insolation", "moisture", "contours", "hypsograph", "temperature",
+                      "style", "stats", "diff-seed"] {
+            if matches.is_present(arg) {
+                println!("--{} is not supported with --stream", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    match rect_options.projection {
+        RectProjection::Equirectangular => {}
+        projection => {
+            let name = projection.name();
+            if matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles") ||
+               matches.is_present("planar-tileable") || matches.is_present("mapping-file") {
+                println!("--projection is only supported with --type rect");
+                std::process::exit(1);
+            }
+            if rect_options.supersample != 1 {
+                println!("--supersample is not supported with --projection {}", name);
+                std::process::exit(1);
+            }
+            if let OutputFormat::RgbaNormalHeight(_) = output.format {
+                println!("--format rgba-normal-height is not supported with --projection {}",
+                         name);
+                std::process::exit(1);
+            }
+            // The other outputs of the rect map are only written in the
+            // equirectangular projection.
+            for &arg in &["tile-size", "max-memory", "adaptive-poles", "emit-vrt",
+                          "hypsograph", "target-ocean-fraction", "fill-basins", "crust-threshold",
+                          "terrain-branches", "insolation", "moisture", "contours",
+                          "max-walkable-slope", "beach-band", "sun-lat", "export-coords",
+                          "normals", "hillshade", "ao", "temperature", "stats",
+                          "diff-seed"] {
+                if matches.is_present(arg) {
+                    println!("--{} is not supported with --projection {}", arg, name);
+                    std::process::exit(1);
+                }
+            }
+            // Only the globe has a style drawn.
+            let styled = match projection {
+                RectProjection::Orthographic(_) => true,
+                _ => false,
+            };
+            if !styled && matches.is_present("style") {
+                println!("--style is not supported with --projection {}", name);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(region) = matches.value_of("region") {
+        let bounds: Vec<Result<f64, _>> = region.split(',').map(f64::from_str).collect();
+        let region = match bounds[..] {
+            [Ok(min_lat), Ok(min_lon), Ok(max_lat), Ok(max_lon)]
+                if min_lat >= -90.0 && min_lat < max_lat && max_lat <= 90.0 &&
+                   min_lon.abs() <= 180.0 && max_lon.abs() <= 180.0 && min_lon != max_lon => {
+                Region {
+                    min_lat: min_lat,
+                    min_lon: min_lon,
+                    max_lat: max_lat,
+                    max_lon: max_lon,
+                }
+            }
+            _ => {
+                println!("Region must be given as MINLAT,MINLON,MAXLAT,MAXLON with MINLAT < \
+                          MAXLAT between -90 and 90 and different longitudes between -180 and \
+                          180");
+                std::process::exit(1);
+            }
+        };
+        rect_options.region = Some(region);
+
+        if matches.value_of("type").unwrap() != "rect" || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") ||
+           searching || matches.is_present("contact-sheet") {
+            println!("--region is only supported with --type rect");
+            std::process::exit(1);
+        }
+        match rect_options.projection {
+            RectProjection::Equirectangular => {}
+            projection => {
+                println!("--region is not supported with --projection {}", projection.name());
+                std::process::exit(1);
+            }
+        }
+        if let Precision::F32 = output.precision {
+            println!("--region is not supported with --precision f32");
             std::process::exit(1);
         }
+        // The other outputs of the rect map are only written for the whole
+        // globe.
+        for &arg in &["tile-size", "max-memory", "adaptive-poles", "emit-vrt", "stream",
+                      "hypsograph", "target-ocean-fraction", "fill-basins", "crust-threshold",
+                      "terrain-branches", "insolation", "moisture", "contours",
+                      "max-walkable-slope", "beach-band", "sun-lat", "export-coords", "normals",
+                      "hillshade", "ao", "temperature", "style", "stats", "diff-seed"] {
+            if matches.is_present(arg) {
+                println!("--{} is not supported with --region", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Without --height, a region is given square pixels in degrees, and the
+    // view of the gnomonic projection is square.
+    let height = match (rect_options.region, rect_options.projection) {
+        _ if matches.is_present("height") => height,
+        (Some(region), _) => {
+            let height = (width as f64 * region.lat_span() / region.lon_span()).round();
+            f64::max(height, 1.0) as usize
+        }
+        (None, RectProjection::Gnomonic(_)) |
+        (None, RectProjection::Orthographic(_)) => width,
+        _ => height,
     };
 
-    let output_format = match matches.value_of("format").unwrap() {
-        "greyscale8" => OutputFormat::Greyscale8,
-        "greyscale16" => OutputFormat::Greyscale16,
-        "colour24" => OutputFormat::Colour24,
-        _ => unreachable!(),
+    if rect_options.tile_size.is_some() &&
+       (rect_options.max_walkable_slope.is_some() || rect_options.beach_band.is_some() ||
+        rect_options.sun.is_some()) {
+        println!("--max-walkable-slope, --beach-band and --sun-lat cannot be used with tiled \
+                  output");
+        std::process::exit(1);
+    }
+
+    // A VP8L image is at most webp::MAX_DIMENSION pixels each way, which
+    // applies to the image actually written rather than --width.
+    if let OutputFormat::WebP(..) = output.format {
+        let (image_width, image_height) = match matches.value_of("type").unwrap() {
+            _ if matches.is_present("planar-tileable") => (width, width),
+            _ if matches.is_present("xyz-tiles") => (XYZ_TILE_SIZE, XYZ_TILE_SIZE),
+            "cube" => {
+                match matches.value_of("cube-layout") {
+                    Some("cross") => (width.saturating_mul(4), width.saturating_mul(3)),
+                    Some("grid") => (width.saturating_mul(3), width.saturating_mul(2)),
+                    _ => (width, width),
+                }
+            }
+            "rect" => {
+                match rect_options.tile_size {
+                    Some(size) => (usize::min(size, width), usize::min(size, height)),
+                    None => (width, height),
+                }
+            }
+            _ => (width, width),
+        };
+        check_webp_dimensions(image_width, image_height);
+    }
+
+    if output.export_coords {
+        let unsupported = match matches.value_of("type").unwrap() {
+            "passport" | "mesh" => !matches.is_present("xyz-tiles"),
+            _ => false,
+        };
+        if unsupported || matches.is_present("planar-tileable") ||
+           matches.is_present("mapping-file") || searching ||
+           matches.is_present("contact-sheet") {
+            println!("--export-coords is only supported with cube, rect and tile output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output.format {
+            println!("--export-coords is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+    }
+
+    // The normals need to know the distance between the pixels.
+    if let OutputFormat::RgbaNormalHeight(_) = output.format {
+        if matches.is_present("xyz-tiles") || matches.is_present("planar-tileable") ||
+           matches.is_present("mapping-file") || rect_options.tile_size.is_some() {
+            println!("--format rgba-normal-height is only supported with untiled cube and rect \
+                      output");
+            std::process::exit(1);
+        }
+    }
+
+    if output.normals || output.hillshade.is_some() || output.ao.is_some() {
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") ||
+           searching || matches.is_present("contact-sheet") ||
+           rect_options.tile_size.is_some() {
+            println!("--normals, --hillshade and --ao are only supported with untiled cube and \
+                      rect output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output.format {
+            println!("--normals, --hillshade and --ao are not supported with --format \
+                      dds-cube");
+            std::process::exit(1);
+        }
+    }
+
+    if output.annotate.is_some() {
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") ||
+           searching || matches.is_present("contact-sheet") ||
+           rect_options.tile_size.is_some() {
+            println!("--annotate is only supported with untiled cube and rect output");
+            std::process::exit(1);
+        }
+        let image_format = match output.format {
+            OutputFormat::Cph |
+            OutputFormat::Int16Signed(_) |
+            OutputFormat::RawF64 |
+            OutputFormat::VectorField(_) |
+            OutputFormat::DdsCube |
+            OutputFormat::RgbaNormalHeight(_) => false,
+            _ => true,
+        };
+        if !image_format || output.elevation_band.is_some() {
+            println!("--annotate is only supported with image formats and no --elevation-band");
+            std::process::exit(1);
+        }
+    }
+
+    if let Precision::F32 = output.precision {
+        let unsupported_type = match matches.value_of("type").unwrap() {
+            "cube" | "rect" => false,
+            _ => true,
+        };
+        if unsupported_type || matches.is_present("xyz-tiles") ||
+           matches.is_present("planar-tileable") || matches.is_present("mapping-file") ||
+           searching || matches.is_present("contact-sheet") ||
+           rect_options.tile_size.is_some() || matches.is_present("cube-layout") {
+            println!("--precision f32 is only supported with untiled cube and rect output");
+            std::process::exit(1);
+        }
+        if let OutputFormat::DdsCube = output.format {
+            println!("--precision f32 is not supported with --format dds-cube");
+            std::process::exit(1);
+        }
+        match rect_options.projection {
+            RectProjection::Equirectangular => {}
+            projection => {
+                println!("--precision f32 is not supported with --projection {}",
+                         projection.name());
+                std::process::exit(1);
+            }
+        }
+        if matches.value_of("type").unwrap() == "rect" {
+            if let Some(arg) = rect_f64_option(output, &rect_options) {
+                println!("--{} is not supported with --precision f32", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Queries only print the elevation, so are answered before any output is
+    // written.
+    if let Some(query) = matches.value_of("query") {
+        let coords: Vec<Result<f64, _>> = query.split(',').map(f64::from_str).collect();
+        let (lat, lon) = match coords[..] {
+            [Ok(lat), Ok(lon)] if lat.abs() <= 90.0 && lon.abs() <= 180.0 => (lat, lon),
+            _ => {
+                println!("Query must be given as LAT,LON with LAT between -90 and 90 and LON \
+                          between -180 and 180");
+                std::process::exit(1);
+            }
+        };
+        let generator = create_generator(seed, &params);
+        let value = sample_sphere(&*generator, lat, lon);
+        if matches.is_present("normalized") {
+            println!("{} {}", value, f64_clamp((value + 1.0) / 2.0, 0.0, 1.0));
+        } else {
+            println!("{}", value);
+        }
+        return;
+    }
+
+    if let Some(profile) = matches.value_of("profile") {
+        let parse_point = |point: &str| {
+            let coords: Vec<Result<f64, _>> = point.split(',').map(f64::from_str).collect();
+            match coords[..] {
+                [Ok(lat), Ok(lon)] if lat.abs() <= 90.0 && lon.abs() <= 180.0 => Some((lat, lon)),
+                _ => None,
+            }
+        };
+        let points: Vec<Option<(f64, f64)>> = profile.split(':').map(parse_point).collect();
+        let (start, end) = match points[..] {
+            [Some(start), Some(end)] => (start, end),
+            _ => {
+                println!("Profile must be given as LAT1,LON1:LAT2,LON2 with latitudes between \
+                          -90 and 90 and longitudes between -180 and 180");
+                std::process::exit(1);
+            }
+        };
+        let samples = match usize::from_str(matches.value_of("samples").unwrap()) {
+            Ok(samples) if samples >= 2 => samples,
+            _ => {
+                println!("Number of profile samples must be an integer of at least 2");
+                std::process::exit(1);
+            }
+        };
+
+        let generator = create_generator(seed, &params);
+        let result = match matches.value_of("profile-file") {
+            Some(filename) => {
+                let file = File::create(Path::new(filename))
+                    .expect("Failed to create file for writing");
+                profile::write_profile(&mut BufWriter::new(file), &*generator, start, end,
+                                       samples)
+            }
+            None => {
+                let stdout = std::io::stdout();
+                profile::write_profile(&mut stdout.lock(), &*generator, start, end, samples)
+            }
+        };
+        result.expect("Failed to write profile");
+        return;
+    }
+
+    if matches.is_present("metadata") {
+        output.metadata = Some(Metadata {
+            output_type: leak(matches.value_of("type").unwrap()),
+            format: output.format.name(),
+            params: params,
+        });
+    }
+
+    if matches.is_present("emit-lock") {
+        lock::write_lock(&output.paths.path("planet.lock"), &matches, seed, &params);
+    }
+
+    if let Some(filename) = matches.value_of("legend") {
+        legend::output_legend(filename, output.format, output.tone);
+    }
+
+    if searching {
+        let land_fraction = match matches.value_of("want-land-fraction") {
+            Some(fraction) => {
+                match f64::from_str(fraction) {
+                    Ok(fraction) if fraction >= 0.0 && fraction <= 1.0 => Some(fraction),
+                    _ => {
+                        println!("Wanted land fraction must be a number between 0 and 1");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => None,
+        };
+        let continents = match matches.value_of("want-continents") {
+            Some(continents) => {
+                match usize::from_str(continents) {
+                    Ok(continents) => Some(continents),
+                    Err(_) => {
+                        println!("Wanted number of continents must be an integer");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => None,
+        };
+        let ocean_fraction = match matches.value_of("find-ocean") {
+            Some(range) => {
+                let bounds: Vec<Result<f64, _>> = range.splitn(2, "..")
+                    .map(f64::from_str)
+                    .collect();
+                match bounds[..] {
+                    [Ok(lo), Ok(hi)] if lo >= 0.0 && lo <= hi && hi <= 1.0 => Some((lo, hi)),
+                    _ => {
+                        println!("Ocean fraction range must be given as LO..HI with 0 <= LO <= \
+                                  HI <= 1");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => None,
+        };
+        if land_fraction.is_none() && continents.is_none() && ocean_fraction.is_none() {
+            println!("--search needs --want-land-fraction, --want-continents or --find-ocean");
+            std::process::exit(1);
+        }
+
+        let tolerance = match f64::from_str(matches.value_of("tolerance").unwrap()) {
+            Ok(tolerance) if tolerance >= 0.0 => tolerance,
+            _ => {
+                println!("Tolerance must be a non-negative number");
+                std::process::exit(1);
+            }
+        };
+        let parse_count = |name: &str| match usize::from_str(matches.value_of(name).unwrap()) {
+            Ok(count) if count > 0 => count,
+            _ => {
+                println!("--{} must be a positive integer", name);
+                std::process::exit(1);
+            }
+        };
+        let criteria = search::SearchCriteria {
+            width: parse_count("search-width"),
+            land_fraction: land_fraction,
+            tolerance: tolerance,
+            ocean_fraction: ocean_fraction,
+            continents: continents,
+            max_hits: parse_count("max-hits"),
+            max_attempts: parse_count("max-attempts"),
+        };
+        search::search_seeds(seed, &params, &criteria);
+        return;
+    }
+
+    // Refuse images too large to hold in memory up front, rather than being
+    // killed part way through generating them.  All six faces of the cube
+    // are counted together whatever the number of threads, as the layouts
+    // hold them all at once.
+    let max_pixels = match usize::from_str(matches.value_of("max-pixels").unwrap()) {
+        Ok(max_pixels) if max_pixels > 0 => max_pixels,
+        _ => {
+            println!("Maximum pixels must be a positive integer");
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(columns) = matches.value_of("contact-sheet") {
+        let columns = match usize::from_str(columns) {
+            Ok(columns) if columns > 0 => columns,
+            _ => {
+                println!("Contact sheet columns must be a positive integer");
+                std::process::exit(1);
+            }
+        };
+        let seeds: Vec<i32> = matches.value_of("seeds")
+            .unwrap()
+            .split(',')
+            .map(|seed| parse_seed(seed.trim()))
+            .collect();
+        let (sheet_width, sheet_height) = contact_sheet::sheet_size(seeds.len(), columns);
+        check_max_pixels(&format!("A {}x{} contact sheet is", sheet_width, sheet_height),
+                         sheet_width.saturating_mul(sheet_height),
+                         estimate_rect_memory(sheet_width, sheet_height, OutputFormat::Colour24),
+                         max_pixels,
+                         "Give fewer --seeds, or raise --max-pixels if there is the memory for \
+                          it");
+        contact_sheet::output_contact_sheet(&seeds, &params, columns, output.paths);
+        return;
+    }
+
+    if let Some(group) = matches.value_of("planar-tileable") {
+        let group = match group {
+            "mountains" => TerrainGroup::Mountains,
+            "hills" => TerrainGroup::Hills,
+            "plains" => TerrainGroup::Plains,
+            "badlands" => TerrainGroup::Badlands,
+            _ => unreachable!(),
+        };
+        let extent = parse_positive_f64(&matches, "planar-extent");
+        planar::output_planar_tileable(seed, &params, group, width, extent, output);
+        return;
+    }
+
+    if let Some(filename) = matches.value_of("mapping-file") {
+        let mapping = mapping::read_mapping(filename).expect("Failed to read mapping file");
+        if mapping.width != width {
+            println!("Mapping file is {} pixels wide, not --width {}", mapping.width, width);
+            std::process::exit(1);
+        }
+        if let OutputFormat::WebP(..) = output.format {
+            check_webp_dimensions(mapping.width, mapping.height);
+        }
+        mapping::output_mapped(seed, &params, &mapping, output);
+        return;
+    }
+
+    if let Some(zoom) = matches.value_of("xyz-tiles") {
+        let zoom = match u32::from_str(zoom) {
+            Ok(zoom) if zoom <= 24 => zoom,
+            _ => {
+                println!("Zoom level must be an integer between 0 and 24");
+                std::process::exit(1);
+            }
+        };
+        output_xyz_tiles(seed, &params, zoom, rect_options.threads, output);
+        return;
+    }
+
+    let image = match matches.value_of("type").unwrap() {
+        "cube" => {
+            let layout = match matches.value_of("cube-layout") {
+                Some("cross") => Some(("cross", 4, 3)),
+                Some("grid") => Some(("grid", 3, 2)),
+                _ => None,
+            };
+            match (output.format, layout) {
+                (OutputFormat::DdsCube, _) => {
+                    Some((format!("A DDS cubemap of six {}x{} faces is", width, width),
+                          width.saturating_mul(6),
+                          width,
+                          RAISE_MAX_PIXELS))
+                }
+                (_, Some((name, columns, rows))) => {
+                    let (image_width, image_height) =
+                        (width.saturating_mul(columns), width.saturating_mul(rows));
+                    Some((format!("A {}x{} cube {} is", image_width, image_height, name),
+                          image_width,
+                          image_height,
+                          "Output the faces separately without --cube-layout, or raise \
+                           --max-pixels if there is the memory for it"))
+                }
+                _ if matches.value_of("face").unwrap() == "all" => {
+                    Some((format!("Six {}x{} cube faces are", width, width),
+                          width.saturating_mul(6),
+                          width,
+                          "Output one face at a time with --face, or raise --max-pixels if \
+                           there is the memory for it"))
+                }
+                _ => {
+                    Some((format!("A {}x{} cube face is", width, width),
+                          width,
+                          width,
+                          RAISE_MAX_PIXELS))
+                }
+            }
+        }
+        "rect" if rect_options.tile_size.is_none() && !rect_options.stream => {
+            Some((format!("A {}x{} map is", width, height),
+                  width,
+                  height,
+                  "Output the map in tiles with --tile-size or --max-memory, or as it is \
+                   generated with --stream, or raise --max-pixels if there is the memory for \
+                   it"))
+        }
+        "passport" => {
+            let (image_width, image_height) = passport::canvas_size(width);
+            Some((format!("A {}x{} passport is", image_width, image_height),
+                  image_width,
+                  image_height,
+                  RAISE_MAX_PIXELS))
+        }
+        _ => None,
     };
+    if let Some((description, image_width, image_height, advice)) = image {
+        check_max_pixels(&description,
+                         image_width.saturating_mul(image_height),
+                         estimate_rect_memory(image_width, image_height, output.format),
+                         max_pixels,
+                         advice);
+    }
+
+    // The vertices of the mesh are counted as pixels, along with the texture
+    // of planet.glb, twice as wide as the mesh is around.
+    if matches.value_of("type").unwrap() == "mesh" {
+        let vertices = width.saturating_mul((width / 2).saturating_sub(1)).saturating_add(2);
+        let texture = match matches.value_of("mesh-format").unwrap() {
+            "glb" => width.saturating_mul(2).saturating_mul(width),
+            _ => 0,
+        };
+        check_max_pixels(&format!("A mesh {} vertices around is", width),
+                         vertices.saturating_add(texture),
+                         vertices.saturating_mul(mesh::BYTES_PER_VERTEX)
+                             .saturating_add(texture.saturating_mul(mesh::BYTES_PER_TEXEL)),
+                         max_pixels,
+                         RAISE_MAX_PIXELS);
+    }
 
     match matches.value_of("type").unwrap() {
-        "cube" => output_cube(seed, width, output_format),
-        "rect" => output_rect(seed, width, output_format),
+        "cube" => {
+            let order = match matches.value_of("cubemap-order").unwrap() {
+                "native" => CubemapOrder::Native,
+                "khronos" => CubemapOrder::Khronos,
+                _ => unreachable!(),
+            };
+            let face = match matches.value_of("face").unwrap() {
+                "xp" => Some(Plane::XP),
+                "xn" => Some(Plane::XN),
+                "yp" => Some(Plane::YP),
+                "yn" => Some(Plane::YN),
+                "zp" => Some(Plane::ZP),
+                "zn" => Some(Plane::ZN),
+                "all" => None,
+                _ => unreachable!(),
+            };
+            let layout = match matches.value_of("cube-layout") {
+                Some("cross") => Some(CubeLayout::Cross),
+                Some("grid") => Some(CubeLayout::Grid),
+                None => None,
+                _ => unreachable!(),
+            };
+            output_cube(seed,
+                        &params,
+                        width,
+                        order,
+                        face,
+                        layout,
+                        rect_options.threads,
+                        rect_options.supersample,
+                        output);
+        }
+        "rect" => output_rect(seed, &params, width, height, output, &rect_options),
+        "passport" => passport::output_passport(seed, &params, width, output.paths),
+        "mesh" => {
+            let options = mesh::MeshOptions {
+                exaggeration: parse_positive_f64(&matches, "mesh-exaggeration"),
+                exaggerate: match matches.value_of("mesh-exaggerate").unwrap() {
+                    "positions" => mesh::Exaggerate::Positions,
+                    "normals" => mesh::Exaggerate::Normals,
+                    "both" => mesh::Exaggerate::Both,
+                    _ => unreachable!(),
+                },
+                radius: parse_positive_f64(&matches, "mesh-radius"),
+                flat_oceans: matches.is_present("mesh-flat-oceans"),
+                format: match matches.value_of("mesh-format").unwrap() {
+                    "obj" => mesh::MeshFormat::Obj,
+                    "stl" => mesh::MeshFormat::Stl,
+                    // Textured with --palette or --palette-file if given.
+                    "glb" => {
+                        match output.format {
+                            OutputFormat::Tinted(gradient) => mesh::MeshFormat::Glb(gradient),
+                            _ => mesh::MeshFormat::Glb(Gradient::Earth),
+                        }
+                    }
+                    _ => unreachable!(),
+                },
+            };
+            if width < 4 {
+                println!("Width must be at least 4 for mesh output");
+                std::process::exit(1);
+            }
+            mesh::output_mesh(seed, &params, width, &options, output.paths);
+        }
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImage;
+
+    use super::*;
+
+    const SIZE: usize = 9;
+
+    // Samples all six faces, returning the position and elevation of each
+    // pixel on the border of a face.
+    fn face_borders(order: CubemapOrder) -> Vec<((f64, f64, f64), f64)> {
+        let generator = create_generator(0, &PlanetParams::default());
+        let mut borders = Vec::new();
+        for &plane in &[Plane::XP, Plane::XN, Plane::YP, Plane::YN, Plane::ZP, Plane::ZN] {
+            let face = generate_cube_face::<f64>(&*generator, plane, order, SIZE, 1);
+            for row in 0..SIZE {
+                for a in 0..SIZE {
+                    if row == 0 || row == SIZE - 1 || a == 0 || a == SIZE - 1 {
+                        let pos = cube_face_pos(plane, order, a, row, SIZE);
+                        borders.push((pos, face[row * SIZE + a]));
+                    }
+                }
+            }
+        }
+        borders
+    }
+
+    // The elevations of pairs of border pixels sampled at the same position,
+    // which can only be on different faces.
+    fn shared_samples(order: CubemapOrder) -> Vec<(f64, f64)> {
+        let borders = face_borders(order);
+        let mut shared = Vec::new();
+        for (i, &(pos_a, value_a)) in borders.iter().enumerate() {
+            for &(pos_b, value_b) in &borders[i + 1..] {
+                if pos_a == pos_b {
+                    shared.push((value_a, value_b));
+                }
+            }
+        }
+        shared
+    }
+
+    #[test]
+    fn native_faces_match_along_every_edge() {
+        let shared = shared_samples(CubemapOrder::Native);
+        // Each of the 12 edges is SIZE pixels long.  The corners, shared by
+        // three faces, give three pairs, one for each edge they end.
+        assert_eq!(shared.len(), 12 * SIZE);
+        for (a, b) in shared {
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn khronos_faces_share_no_samples() {
+        assert!(shared_samples(CubemapOrder::Khronos).is_empty());
+    }
+
+    // The options of a plain image of the given format written to the
+    // temporary directory.
+    fn plain_output(format: OutputFormat) -> OutputOptions {
+        let temp_dir = std::env::temp_dir().to_str().unwrap().to_string();
+        OutputOptions {
+            format: format,
+            paths: OutputPaths {
+                dir: Box::leak(temp_dir.into_boxed_str()),
+                prefix: Some("complexplanet-test"),
+            },
+            elevation_band: None,
+            background: std::f64::NAN,
+            dither: false,
+            tone: Tone {
+                range: None,
+                gamma: 1.0,
+                invert: false,
+            },
+            auto_normalize: false,
+            export_coords: false,
+            annotate: None,
+            normals: false,
+            hillshade: None,
+            ao: None,
+            normal_strength: 1.0,
+            metadata: None,
+            strict: false,
+            stats: false,
+            temperature: None,
+            temperature_ramp: false,
+            style: None,
+            climate_tilt: 0.0,
+            precision: Precision::F64,
+        }
+    }
+
+    #[test]
+    fn streamed_rect_matches_buffered() {
+        let generator = create_generator(0, &PlanetParams::default());
+        // More than one band, the last of them partial.
+        let (width, height) = (24, STREAM_BAND_ROWS * 2 + 5);
+        let options = RectOptions {
+            threads: 2,
+            supersample: 1,
+            ..RectOptions::default()
+        };
+        for &format in &[OutputFormat::Greyscale8,
+                         OutputFormat::Greyscale16,
+                         OutputFormat::Colour24,
+                         OutputFormat::Colour48] {
+            let output = plain_output(format);
+            let streamed = output.paths.path("streamed.png");
+            stream_rect(0, &generator, width, height, &streamed, output, &options);
+
+            let buffered = output.paths.path("buffered.png");
+            let dest_buffer: Vec<f64> =
+                generate_rect_threaded(&generator, width, height, 0, height, false, 1, 2);
+            write_output_to_file(&buffered,
+                                 &dest_buffer,
+                                 width,
+                                 height,
+                                 0,
+                                 Projection::Equirectangular,
+                                 output);
+
+            let streamed_image = image::open(&streamed).unwrap();
+            let buffered_image = image::open(&buffered).unwrap();
+            std::fs::remove_file(&streamed).unwrap();
+            std::fs::remove_file(&buffered).unwrap();
+            assert_eq!(streamed_image.dimensions(), (width as u32, height as u32));
+            assert_eq!(streamed_image.color(), buffered_image.color());
+            assert!(streamed_image.raw_pixels() == buffered_image.raw_pixels());
+        }
+    }
+
+    #[test]
+    fn climate_lat_tilts_towards_longitude_0() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert_eq!(climate_lat((12.5, 34.5), 0.0), 12.5);
+        assert!(close(climate_lat((90.0, 0.0), 30.0), 60.0));
+        assert!(close(climate_lat((0.0, 0.0), 30.0), 30.0));
+        assert!(close(climate_lat((0.0, 180.0), 30.0), -30.0));
+        assert!(close(climate_lat((0.0, 90.0), 30.0), 0.0));
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Output sampled through a custom pixel to direction mapping read from a
+//! file, so that other tools can define arbitrary projections.
+//!
+//! A mapping file is a 12 byte header followed by a direction for each
+//! pixel, row by row with the top row first.  All values are little-endian.
+//!
+//! | Offset | Size | Field                                       |
+//! |--------|------|---------------------------------------------|
+//! | 0      | 4    | Magic bytes, `CPMP`                         |
+//! | 4      | 4    | Width, `u32`                                |
+//! | 8      | 4    | Height, `u32`                               |
+//! | 12     | 12   | Direction of each pixel, three `f32`s       |
+//!
+//! The directions are from the centre of the planet and needn't be
+//! normalised.  A zero direction marks a pixel outside the mapped area, such
+//! as the corners around a fisheye image, which is given the lowest
+//! elevation.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use cph::Projection;
+use super::{create_generator, write_output_to_file, OutputOptions, PlanetParams};
+
+pub const MAGIC: [u8; 4] = *b"CPMP";
+pub const HEADER_SIZE: usize = 12;
+
+/// A pixel to direction mapping.
+pub struct Mapping {
+    pub width: usize,
+    pub height: usize,
+    pub directions: Vec<(f64, f64, f64)>,
+}
+
+/// Reads a mapping file.
+pub fn read_mapping(filename: &str) -> io::Result<Mapping> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let file = File::open(Path::new(filename))?;
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+    if header[0..4] != MAGIC {
+        return Err(invalid("Not a complexplanet mapping"));
+    }
+    let width = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let height = u32::from_le_bytes([header[8], header[9], header[10], header[11]]) as usize;
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.len() != width * height * 12 {
+        return Err(invalid("Mapping doesn't hold a direction for every pixel"));
+    }
+
+    let directions = data.chunks(12)
+        .map(|bytes| {
+            let component = |i: usize| {
+                f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]) as f64
+            };
+            (component(0), component(4), component(8))
+        })
+        .collect();
+
+    Ok(Mapping {
+        width: width,
+        height: height,
+        directions: directions,
+    })
+}
+
+/// Outputs "mapped.png", the planet sampled in the direction given for each
+/// pixel by `mapping`.
+pub fn output_mapped(seed: i32, params: &PlanetParams, mapping: &Mapping, output: OutputOptions) {
+    let generator = create_generator(seed, params);
+
+    let dest_buffer: Vec<f64> = mapping.directions
+        .iter()
+        .map(|&(x, y, z)| {
+            let len = f64::sqrt(x * x + y * y + z * z);
+            if len > 0.0 {
+                generator.get_value(x / len, y / len, z / len)
+            } else {
+                -1.0
+            }
+        })
+        .collect();
+
+    write_output_to_file(&output.paths.path("mapped.png"),
+                         &dest_buffer,
+                         mapping.width,
+                         mapping.height,
+                         seed,
+                         Projection::Custom,
+                         output);
+}
@@ -0,0 +1,510 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Output of the planet as a displaced globe mesh in Wavefront OBJ, binary
+//! STL or binary glTF format.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use image::ColorType;
+use image::png::PNGEncoder;
+use noise::module::Module;
+
+use palette::Gradient;
+use super::{create_generator, lat_lon_to_pos, OutputPaths, PlanetParams, MAX_ELEV,
+            PLANET_CIRCUMFERENCE, SEA_LEVEL};
+
+type Vec3 = (f64, f64, f64);
+
+// A corner of a face, its vertex index and texture coordinate index.
+type Corner = (usize, usize);
+
+/// Roughly the memory held for each vertex of the mesh, its direction and
+/// elevation, both displaced positions and its normal, in bytes.
+pub const BYTES_PER_VERTEX: usize = 104;
+
+/// Roughly the memory held for each texel of the planet.glb texture, its
+/// colour and the PNG encoding it, in bytes.
+pub const BYTES_PER_TEXEL: usize = 4;
+
+#[derive(Clone, Copy)]
+pub enum MeshFormat {
+    Obj,
+    // Binary STL, for 3D printing.  Facet normals are computed from the
+    // positions, so the normals of `Exaggerate` have no effect.
+    Stl,
+    // Binary glTF 2.0, the mesh textured with an equirectangular map of the
+    // elevations coloured by the given gradient.
+    Glb(Gradient),
+}
+
+/// Which parts of the mesh the vertical exaggeration is applied to.
+#[derive(Clone, Copy)]
+pub enum Exaggerate {
+    // Exaggerated vertex positions, shaded with the true normals.
+    Positions,
+    // True vertex positions, shaded as if exaggerated.
+    Normals,
+    Both,
+}
+
+#[derive(Clone, Copy)]
+pub struct MeshOptions {
+    // Multiplier for the height of the terrain above or below the sphere.
+    pub exaggeration: f64,
+    pub exaggerate: Exaggerate,
+    // Radius of the sphere the terrain is displaced from, the radius at an
+    // elevation of 0.0.
+    pub radius: f64,
+    // Whether elevations below sea level are raised to it, giving smooth
+    // oceans.
+    pub flat_oceans: bool,
+    pub format: MeshFormat,
+}
+
+// The vertices of the mesh form a latitude/longitude grid plus a vertex at
+// each pole.
+struct Grid {
+    columns: usize,
+    // Rows of the grid, not including the poles.
+    rows: usize,
+}
+
+impl Grid {
+    fn vertex_count(&self) -> usize {
+        self.columns * self.rows + 2
+    }
+
+    fn index(&self, row: usize, column: usize) -> usize {
+        row * self.columns + column % self.columns
+    }
+
+    fn north_pole(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn south_pole(&self) -> usize {
+        self.columns * self.rows + 1
+    }
+
+    // The indices into `texture_coords`.
+    fn texture_index(&self, row: usize, column: usize) -> usize {
+        row * (self.columns + 1) + column
+    }
+
+    fn north_pole_texture(&self, column: usize) -> usize {
+        self.rows * (self.columns + 1) + column
+    }
+
+    fn south_pole_texture(&self, column: usize) -> usize {
+        self.rows * (self.columns + 1) + self.columns + column
+    }
+}
+
+/// Outputs "planet.obj" or "planet.stl", a sphere displaced by the planet's
+/// elevations with `columns` vertices around each line of latitude.  The
+/// texture coordinates of the OBJ map an equirectangular image, such as rect
+/// output, onto the sphere.  Either way the mesh is closed, with every face
+/// wound anticlockwise seen from outside the planet.
+pub fn output_mesh(seed: i32,
+                  params: &PlanetParams,
+                  columns: usize,
+                  options: &MeshOptions,
+                  paths: OutputPaths) {
+    let generator = create_generator(seed, params);
+    let grid = Grid {
+        columns: columns,
+        rows: columns / 2 - 1,
+    };
+
+    // Directions from the centre of the planet, and the elevations there.
+    let mut directions = Vec::with_capacity(grid.vertex_count());
+    for row in 0..grid.rows {
+        let lat = 90.0 - (row + 1) as f64 / (grid.rows + 1) as f64 * 180.0;
+        for column in 0..grid.columns {
+            let lon = -180.0 + column as f64 / grid.columns as f64 * 360.0;
+            directions.push(lat_lon_to_pos(lat, lon));
+        }
+    }
+    directions.push((0.0, 1.0, 0.0));
+    directions.push((0.0, -1.0, 0.0));
+    let elevations: Vec<f64> = directions.iter()
+        .map(|&(x, y, z)| generator.get_value(x, y, z))
+        .map(|elev| if options.flat_oceans { f64::max(elev, SEA_LEVEL) } else { elev })
+        .collect();
+
+    // Heights are relative to a unit radius.
+    let height_scale = MAX_ELEV / (PLANET_CIRCUMFERENCE / (2.0 * ::std::f64::consts::PI));
+    let displace = |exaggeration: f64| -> Vec<Vec3> {
+        directions.iter()
+            .zip(elevations.iter())
+            .map(|(&(x, y, z), &elev)| {
+                let radius = options.radius * (1.0 + elev * height_scale * exaggeration);
+                (x * radius, y * radius, z * radius)
+            })
+            .collect()
+    };
+    let exaggerated = displace(options.exaggeration);
+    let true_positions = displace(1.0);
+
+    let (positions, normals) = match options.exaggerate {
+        Exaggerate::Positions => (&exaggerated, vertex_normals(&grid, &true_positions)),
+        Exaggerate::Normals => (&true_positions, vertex_normals(&grid, &exaggerated)),
+        Exaggerate::Both => (&exaggerated, vertex_normals(&grid, &exaggerated)),
+    };
+
+    let name = match options.format {
+        MeshFormat::Obj => "planet.obj",
+        MeshFormat::Stl => "planet.stl",
+        MeshFormat::Glb(_) => "planet.glb",
+    };
+    let file = File::create(Path::new(&paths.path(name)))
+        .expect("Failed to create file for writing");
+    let mut writer = BufWriter::new(file);
+    match options.format {
+        MeshFormat::Obj => write_obj(&mut writer, &grid, positions, &normals),
+        MeshFormat::Stl => write_stl(&mut writer, &grid, positions),
+        MeshFormat::Glb(gradient) => {
+            // Two texels for each column of vertices.
+            let texture = colour_texture(&generator, grid.columns * 2, grid.columns, gradient);
+            write_glb(&mut writer, &grid, positions, &normals, &texture)
+        }
+    }.expect("Failed to write mesh data");
+}
+
+// Computes the normal at each vertex from the positions of its neighbours.
+fn vertex_normals(grid: &Grid, positions: &[Vec3]) -> Vec<Vec3> {
+    let mut normals = Vec::with_capacity(positions.len());
+    for row in 0..grid.rows {
+        for column in 0..grid.columns {
+            let north = if row == 0 {
+                grid.north_pole()
+            } else {
+                grid.index(row - 1, column)
+            };
+            let south = if row + 1 == grid.rows {
+                grid.south_pole()
+            } else {
+                grid.index(row + 1, column)
+            };
+            let east = grid.index(row, column + 1);
+            let west = grid.index(row, column + grid.columns - 1);
+
+            let towards_east = sub(positions[east], positions[west]);
+            let towards_north = sub(positions[north], positions[south]);
+            normals.push(normalize(cross(towards_north, towards_east)));
+        }
+    }
+
+    // The poles take the average of the normals around them.
+    for &row in &[0, grid.rows - 1] {
+        let mut sum = (0.0, 0.0, 0.0);
+        for column in 0..grid.columns {
+            let n = normals[grid.index(row, column)];
+            sum = (sum.0 + n.0, sum.1 + n.1, sum.2 + n.2);
+        }
+        normals.push(normalize(sum));
+    }
+
+    normals
+}
+
+fn write_obj<W: Write>(writer: &mut W,
+                       grid: &Grid,
+                       positions: &[Vec3],
+                       normals: &[Vec3])
+                       -> ::std::io::Result<()> {
+    for &(x, y, z) in positions {
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+    for &(x, y, z) in normals {
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+    for (u, v) in texture_coords(grid) {
+        writeln!(writer, "vt {} {}", u, v)?;
+    }
+
+    // OBJ indices start at 1, vertex normals share the vertex index.
+    for &[a, b, c] in &faces(grid) {
+        writeln!(writer,
+                 "f {}/{}/{} {}/{}/{} {}/{}/{}",
+                 a.0 + 1,
+                 a.1 + 1,
+                 a.0 + 1,
+                 b.0 + 1,
+                 b.1 + 1,
+                 b.0 + 1,
+                 c.0 + 1,
+                 c.1 + 1,
+                 c.0 + 1)?;
+    }
+
+    Ok(())
+}
+
+// Writes binary STL: an 80 byte header, the number of triangles, then each
+// triangle's normal and corners as little-endian f32s with two bytes of
+// padding.
+fn write_stl<W: Write>(writer: &mut W, grid: &Grid, positions: &[Vec3]) -> ::std::io::Result<()> {
+    let faces = faces(grid);
+    let mut header = [0u8; 80];
+    header[..19].copy_from_slice(b"complexplanet globe");
+    writer.write_all(&header)?;
+    writer.write_all(&(faces.len() as u32).to_le_bytes())?;
+
+    for &[a, b, c] in &faces {
+        let (a, b, c) = (positions[a.0], positions[b.0], positions[c.0]);
+        let normal = normalize(cross(sub(b, a), sub(c, a)));
+        for &(x, y, z) in &[normal, a, b, c] {
+            for &value in &[x, y, z] {
+                writer.write_all(&(value as f32).to_le_bytes())?;
+            }
+        }
+        writer.write_all(&[0, 0])?;
+    }
+
+    Ok(())
+}
+
+// Writes binary glTF: a 12 byte header followed by a JSON chunk describing
+// the scene and a binary chunk holding the vertices, indices and texture.
+// glTF has a single index per vertex, so a vertex is written for each
+// distinct pair of position and texture coordinate.
+fn write_glb<W: Write>(writer: &mut W,
+                       grid: &Grid,
+                       positions: &[Vec3],
+                       normals: &[Vec3],
+                       texture: &[u8])
+                       -> ::std::io::Result<()> {
+    let coords = texture_coords(grid);
+    let mut corners = Vec::new();
+    let mut corner_indices = HashMap::new();
+    let mut indices = Vec::new();
+    for face in faces(grid) {
+        for &corner in &face {
+            let index = *corner_indices.entry(corner).or_insert_with(|| {
+                corners.push(corner);
+                corners.len() - 1
+            });
+            indices.push(index as u32);
+        }
+    }
+
+    let mut position_data = Vec::new();
+    let mut normal_data = Vec::new();
+    let mut coord_data = Vec::new();
+    let mut min = [::std::f32::INFINITY; 3];
+    let mut max = [::std::f32::NEG_INFINITY; 3];
+    for &(vertex, coord) in &corners {
+        let (x, y, z) = positions[vertex];
+        let position = [x as f32, y as f32, z as f32];
+        for i in 0..3 {
+            min[i] = f32::min(min[i], position[i]);
+            max[i] = f32::max(max[i], position[i]);
+        }
+        let (nx, ny, nz) = normals[vertex];
+        // glTF texture coordinates start at the top of the image.
+        let (u, v) = coords[coord];
+        for &value in &position {
+            position_data.extend_from_slice(&value.to_le_bytes());
+        }
+        for &value in &[nx as f32, ny as f32, nz as f32] {
+            normal_data.extend_from_slice(&value.to_le_bytes());
+        }
+        for &value in &[u as f32, (1.0 - v) as f32] {
+            coord_data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let mut index_data = Vec::with_capacity(indices.len() * 4);
+    for &index in &indices {
+        index_data.extend_from_slice(&index.to_le_bytes());
+    }
+
+    // Each buffer view starts on a 4 byte boundary.
+    let mut bin = Vec::new();
+    let mut views = Vec::new();
+    for data in &[&position_data[..], &normal_data, &coord_data, &index_data, texture] {
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+        views.push(format!("{{\"buffer\": 0, \"byteOffset\": {}, \"byteLength\": {}}}",
+                           bin.len(),
+                           data.len()));
+        bin.extend_from_slice(data);
+    }
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    // Accessors 0 to 3 are the positions, normals, texture coordinates and
+    // indices, in buffer views of the same numbers.  5126 is FLOAT and 5125
+    // UNSIGNED_INT.
+    let count = corners.len();
+    let accessors = [(5126, count, "VEC3"), (5126, count, "VEC3"), (5126, count, "VEC2"),
+                     (5125, indices.len(), "SCALAR")];
+    let accessors: Vec<String> = accessors.iter()
+        .enumerate()
+        .map(|(view, &(component_type, count, kind))| {
+            // The positions must give their bounds.
+            let bounds = if view == 0 {
+                format!(", \"min\": [{}, {}, {}], \"max\": [{}, {}, {}]",
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2])
+            } else {
+                String::new()
+            };
+            format!("{{\"bufferView\": {}, \"componentType\": {}, \"count\": {}, \
+                     \"type\": \"{}\"{}}}",
+                    view,
+                    component_type,
+                    count,
+                    kind,
+                    bounds)
+        })
+        .collect();
+
+    let mut json = Vec::new();
+    writeln!(json, "{{")?;
+    writeln!(json,
+             "    \"asset\": {{\"version\": \"2.0\", \"generator\": \"complexplanet {}\"}},",
+             env!("CARGO_PKG_VERSION"))?;
+    writeln!(json, "    \"scene\": 0,")?;
+    writeln!(json, "    \"scenes\": [{{\"nodes\": [0]}}],")?;
+    writeln!(json, "    \"nodes\": [{{\"mesh\": 0}}],")?;
+    writeln!(json,
+             "    \"meshes\": [{{\"primitives\": [{{\"attributes\": {{\"POSITION\": 0, \
+              \"NORMAL\": 1, \"TEXCOORD_0\": 2}}, \"indices\": 3, \"material\": 0}}]}}],")?;
+    writeln!(json,
+             "    \"materials\": [{{\"pbrMetallicRoughness\": {{\"baseColorTexture\": \
+              {{\"index\": 0}}, \"metallicFactor\": 0}}}}],")?;
+    writeln!(json, "    \"textures\": [{{\"source\": 0}}],")?;
+    writeln!(json, "    \"images\": [{{\"bufferView\": 4, \"mimeType\": \"image/png\"}}],")?;
+    writeln!(json, "    \"accessors\": [{}],", accessors.join(", "))?;
+    writeln!(json, "    \"bufferViews\": [{}],", views.join(", "))?;
+    writeln!(json, "    \"buffers\": [{{\"byteLength\": {}}}]", bin.len())?;
+    writeln!(json, "}}")?;
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    writer.write_all(b"glTF")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(b"JSON")?;
+    writer.write_all(&json)?;
+    writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+    writer.write_all(b"BIN\0")?;
+    writer.write_all(&bin)
+}
+
+// A `width` by `height` equirectangular PNG of the elevations coloured by
+// `gradient`, sampled at the centre of each texel.
+fn colour_texture(generator: &Module,
+                  width: usize,
+                  height: usize,
+                  gradient: Gradient)
+                  -> Vec<u8> {
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        let lat = 90.0 - (y as f64 + 0.5) / height as f64 * 180.0;
+        for x in 0..width {
+            let lon = -180.0 + (x as f64 + 0.5) / width as f64 * 360.0;
+            let (px, py, pz) = lat_lon_to_pos(lat, lon);
+            data.extend_from_slice(&gradient.colour(generator.get_value(px, py, pz)));
+        }
+    }
+
+    let mut png = Vec::new();
+    PNGEncoder::new(&mut png).encode(&data, width as u32, height as u32, ColorType::RGB(8))
+        .expect("Failed to encode image data");
+    png
+}
+
+// The texture coordinates of the mesh, with V increasing northwards.  Each
+// row has an extra coordinate at the end for the seam at 180 degrees
+// longitude, and each pole has one for every column, between the columns
+// either side.
+fn texture_coords(grid: &Grid) -> Vec<(f64, f64)> {
+    let mut coords = Vec::new();
+    for row in 0..grid.rows {
+        let v = 1.0 - (row + 1) as f64 / (grid.rows + 1) as f64;
+        for column in 0..(grid.columns + 1) {
+            coords.push((column as f64 / grid.columns as f64, v));
+        }
+    }
+    for &v in &[1.0, 0.0] {
+        for column in 0..grid.columns {
+            coords.push(((column as f64 + 0.5) / grid.columns as f64, v));
+        }
+    }
+    coords
+}
+
+// The triangles of the mesh, wound anticlockwise seen from outside the
+// planet.
+fn faces(grid: &Grid) -> Vec<[Corner; 3]> {
+    let mut faces = Vec::with_capacity(grid.columns * grid.rows * 2);
+    let corner = |row: usize, column: usize| {
+        (grid.index(row, column), grid.texture_index(row, column))
+    };
+
+    let last = grid.rows - 1;
+    for column in 0..grid.columns {
+        let next = column + 1;
+        faces.push([(grid.north_pole(), grid.north_pole_texture(column)),
+                    corner(0, next),
+                    corner(0, column)]);
+        faces.push([(grid.south_pole(), grid.south_pole_texture(column)),
+                    corner(last, column),
+                    corner(last, next)]);
+    }
+    for row in 0..last {
+        for column in 0..grid.columns {
+            let next = column + 1;
+            let nw = corner(row, column);
+            let ne = corner(row, next);
+            let sw = corner(row + 1, column);
+            let se = corner(row + 1, next);
+            faces.push([nw, se, sw]);
+            faces.push([nw, ne, se]);
+        }
+    }
+
+    faces
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    (a.1 * b.2 - a.2 * b.1, a.2 * b.0 - a.0 * b.2, a.0 * b.1 - a.1 * b.0)
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    let len = f64::sqrt(a.0 * a.0 + a.1 * a.1 + a.2 * a.2);
+    (a.0 / len, a.1 / len, a.2 / len)
+}
@@ -0,0 +1,145 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Moisture, from its own noise independent of the terrain, for biome
+//! classification.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::ColorType;
+use image::png::PNGEncoder;
+
+use super::{create_moisture_generator, generate_rect, rect_lat_lon, OutputPaths, PlanetParams,
+            PLANET_CIRCUMFERENCE, SEA_LEVEL};
+
+// A sample waiting to be reached from the coast, ordered so that the nearest
+// is visited first.
+struct Node {
+    distance: f64,
+    index: usize,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other.distance.partial_cmp(&self.distance).unwrap_or(Ordering::Equal)
+    }
+}
+
+// Computes the distance in kilometres from each sample of an equirectangular
+// grid to the nearest ocean sample, travelling between neighbouring samples.
+// The grid wraps around in longitude.  Ocean samples are at distance 0, and
+// everything is infinitely far on a planet without an ocean.
+fn distance_inland(elevations: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let lat_spacing = PLANET_CIRCUMFERENCE / 1000.0 / 2.0 / height as f64;
+    let lon_spacing = |y: usize| {
+        let (lat, _) = rect_lat_lon(width, height, 0, y);
+        PLANET_CIRCUMFERENCE / 1000.0 * f64::cos(lat.to_radians()) / width as f64
+    };
+
+    let mut distances = vec![std::f64::INFINITY; width * height];
+    let mut queue = BinaryHeap::new();
+    for (index, &elevation) in elevations.iter().enumerate() {
+        if elevation <= SEA_LEVEL {
+            distances[index] = 0.0;
+            queue.push(Node {
+                distance: 0.0,
+                index: index,
+            });
+        }
+    }
+
+    while let Some(Node { distance, index }) = queue.pop() {
+        if distance > distances[index] {
+            continue;
+        }
+        let (x, y) = (index % width, index / width);
+        for dy in -1i32..2 {
+            let ny = y as i32 + dy;
+            if ny < 0 || ny >= height as i32 {
+                continue;
+            }
+            // Diagonal steps use the mean east-west spacing of the two rows.
+            let dx_spacing = (lon_spacing(y) + lon_spacing(ny as usize)) / 2.0;
+            for dx in -1i32..2 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let nx = (x as i32 + dx + width as i32) as usize % width;
+                let step = f64::hypot(dx as f64 * dx_spacing, dy as f64 * lat_spacing);
+                let neighbour = ny as usize * width + nx;
+                if distance + step < distances[neighbour] {
+                    distances[neighbour] = distance + step;
+                    queue.push(Node {
+                        distance: distance + step,
+                        index: neighbour,
+                    });
+                }
+            }
+        }
+    }
+
+    distances
+}
+
+/// Outputs "moisture.png", the moisture from 0.0 (black) to 1.0 (white) of
+/// each sample of an equirectangular grid of elevations produced by
+/// `generate_rect`.  If `damping` is given the land dries out away from the
+/// ocean, the moisture falling by a factor of e every `damping` kilometres
+/// inland, so the interiors of continents are drier than their coasts.
+pub fn output_moisture(seed: i32,
+                       params: &PlanetParams,
+                       elevations: &[f64],
+                       width: usize,
+                       height: usize,
+                       damping: Option<f64>,
+                       paths: OutputPaths) {
+    let generator = create_moisture_generator(seed, params);
+    let mut moisture = generate_rect(&*generator, width, height);
+
+    if let Some(damping) = damping {
+        let distances = distance_inland(elevations, width, height);
+        for (value, &distance) in moisture.iter_mut().zip(distances.iter()) {
+            *value *= f64::exp(-distance / damping);
+        }
+    }
+
+    let img_data: Vec<u8> = moisture.iter().map(|&value| (value * 255.0).round() as u8).collect();
+    let file = File::create(Path::new(&paths.path("moisture.png")))
+        .expect("Failed to create file for writing");
+    PNGEncoder::new(BufWriter::new(file))
+        .encode(&img_data, width as u32, height as u32, ColorType::Gray(8))
+        .expect("Failed to write image data");
+}
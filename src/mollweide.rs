@@ -0,0 +1,97 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Output of the planet in the Mollweide projection, an equal-area
+//! projection of the whole globe into an ellipse twice as wide as it is high.
+
+use std::f64::consts::{PI, SQRT_2};
+
+use noise::module::Module;
+
+use cph::Projection;
+use super::{check_finite, clamp, lat_lon_to_pos, write_output_to_file, OutputOptions, SharedModule};
+
+/// Outputs "mollweide.png", a `width` by `height` Mollweide map with the
+/// ellipse of the globe filling the image, sampling the rows in `threads`
+/// worker threads.  Pixels outside the ellipse are set to the background
+/// elevation of `output`.
+pub fn output_mollweide(seed: i32,
+                        generator: &SharedModule,
+                        width: usize,
+                        height: usize,
+                        threads: usize,
+                        output: OutputOptions) {
+    let threads = clamp(threads, 1, height);
+    let mut joins = Vec::new();
+    for i in 0..threads {
+        let y0 = i * height / threads;
+        let y1 = (i + 1) * height / threads;
+        let generator = generator.clone();
+        let background = output.background;
+        joins.push(::std::thread::spawn(move || {
+            let mut rows = Vec::with_capacity(width * (y1 - y0));
+            for y in y0..y1 {
+                for x in 0..width {
+                    rows.push(match pixel_lat_lon(width, height, x, y) {
+                        Some((lat, lon)) => {
+                            let pos = lat_lon_to_pos(lat, lon);
+                            generator.get_value(pos.0, pos.1, pos.2)
+                        }
+                        None => background,
+                    });
+                }
+            }
+            rows
+        }));
+    }
+
+    let mut dest_buffer = Vec::with_capacity(width * height);
+    for join in joins {
+        dest_buffer.extend(join.join().unwrap());
+    }
+
+    let filename = output.paths.path("mollweide.png");
+    if output.strict {
+        check_finite(&filename, &dest_buffer, width, |x, y| pixel_lat_lon(width, height, x, y));
+    }
+    write_output_to_file(&filename,
+                         &dest_buffer,
+                         width,
+                         height,
+                         seed,
+                         Projection::Mollweide,
+                         output);
+}
+
+// The latitude and longitude, in degrees, at the centre of pixel (x, y) of a
+// `width` by `height` map, or None if it is outside the ellipse.  The map
+// spans x from -2√2 to 2√2 and y from -√2 to √2 on a unit sphere.
+fn pixel_lat_lon(width: usize, height: usize, x: usize, y: usize) -> Option<(f64, f64)> {
+    let map_x = ((x as f64 + 0.5) / width as f64 * 2.0 - 1.0) * 2.0 * SQRT_2;
+    let map_y = (1.0 - (y as f64 + 0.5) / height as f64 * 2.0) * SQRT_2;
+    if map_x * map_x / 8.0 + map_y * map_y / 2.0 > 1.0 {
+        return None;
+    }
+
+    // The inverse of the forward projection, where the auxiliary angle theta
+    // satisfies 2θ + sin 2θ = π sin φ.
+    let theta = f64::asin(map_y / SQRT_2);
+    let lat = f64::asin((2.0 * theta + f64::sin(2.0 * theta)) / PI);
+    let lon = PI * map_x / (2.0 * SQRT_2 * f64::cos(theta));
+    Some((lat.to_degrees(), lon.to_degrees()))
+}
@@ -0,0 +1,162 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Tangent space normals of images of elevations, for normal maps.
+
+use noise::module::Module;
+
+use canvas::Canvas;
+use super::{MAX_ELEV, PLANET_CIRCUMFERENCE};
+
+type Vec3 = (f64, f64, f64);
+
+/// Computes the normal of each sample of an image of elevations by central
+/// differences, in tangent space with +X to the right and +Y up the image as
+/// for OpenGL normal maps.  `spacing` is the distance between neighbouring
+/// samples in metres, and the elevations are multiplied by `exaggeration`.
+/// Samples on the edges of the image use one sided differences.
+pub fn image_normals(data: &[f64],
+                     width: usize,
+                     height: usize,
+                     spacing: f64,
+                     exaggeration: f64)
+                     -> Vec<(f64, f64, f64)> {
+    let scale = MAX_ELEV * exaggeration / spacing;
+    let mut normals = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let (y0, y1) = (if y > 0 { y - 1 } else { y }, usize::min(y + 1, height - 1));
+        for x in 0..width {
+            let (x0, x1) = (if x > 0 { x - 1 } else { x }, usize::min(x + 1, width - 1));
+            let dx = if x1 > x0 {
+                (data[y * width + x1] - data[y * width + x0]) / (x1 - x0) as f64
+            } else {
+                0.0
+            };
+            // Rows count down the image, against +Y.
+            let dy = if y1 > y0 {
+                (data[y0 * width + x] - data[y1 * width + x]) / (y1 - y0) as f64
+            } else {
+                0.0
+            };
+
+            let (nx, ny, nz) = (-dx * scale, -dy * scale, 1.0);
+            let len = f64::sqrt(nx * nx + ny * ny + nz * nz);
+            normals.push((nx / len, ny / len, nz / len));
+        }
+    }
+    normals
+}
+
+/// Encodes a unit normal as an RGB colour, mapping each component from
+/// [-1, 1] to [0, 255].
+pub fn encode_normal(normal: (f64, f64, f64)) -> [u8; 3] {
+    let encode = |component: f64| ((component + 1.0) / 2.0 * 255.0).round() as u8;
+    [encode(normal.0), encode(normal.1), encode(normal.2)]
+}
+
+fn normalize(v: Vec3) -> Vec3 {
+    let len = f64::sqrt(v.0 * v.0 + v.1 * v.1 + v.2 * v.2);
+    (v.0 / len, v.1 / len, v.2 / len)
+}
+
+// The component of `v` along the surface of the unit sphere at `pos`.
+fn tangent(pos: Vec3, v: Vec3) -> Vec3 {
+    let d = pos.0 * v.0 + pos.1 * v.1 + pos.2 * v.2;
+    normalize((v.0 - d * pos.0, v.1 - d * pos.1, v.2 - d * pos.2))
+}
+
+/// Turns a frame as given to `sphere_normals` into an orthonormal one: the
+/// position normalized onto the unit sphere, and the right and up directions
+/// made tangent to the sphere there and perpendicular to each other.
+pub fn tangent_frame(pos: Vec3, right: Vec3, up: Vec3) -> (Vec3, Vec3, Vec3) {
+    let pos = normalize(pos);
+    // Make up perpendicular to right, as tangent space assumes, which the
+    // cube's faces aren't away from their centres.
+    let right = tangent(pos, right);
+    let d = right.0 * up.0 + right.1 * up.1 + right.2 * up.2;
+    let up = tangent(pos, (up.0 - d * right.0, up.1 - d * right.1, up.2 - d * right.2));
+    (pos, right, up)
+}
+
+/// Computes the normals of an image sampled from the sphere by central
+/// differences, sampling the generator itself a small angle `step` (in
+/// radians) either side of each sample rather than using the neighbouring
+/// pixels.  This avoids one sided differences at the edges of the image, so
+/// the normals of images which share an edge, like the faces of a cube, agree
+/// along it.
+///
+/// `frame` gives the position on the unit sphere of each pixel along with the
+/// directions on the sphere of the right of the image and up the image there
+/// (they need not be normalized, tangent to the sphere or perpendicular).  The
+/// normals are in tangent space as for `image_normals`, and the elevations are
+/// multiplied by `exaggeration`.
+pub fn sphere_normals<F>(generator: &Module,
+                         width: usize,
+                         height: usize,
+                         step: f64,
+                         exaggeration: f64,
+                         frame: F)
+                         -> Vec<(f64, f64, f64)>
+    where F: Fn(usize, usize) -> (Vec3, Vec3, Vec3)
+{
+    let radius = PLANET_CIRCUMFERENCE / (2.0 * std::f64::consts::PI);
+    let scale = MAX_ELEV * exaggeration / (2.0 * step.tan() * radius);
+    let sample = |pos: Vec3, dir: Vec3, sign: f64| {
+        let (x, y, z) = normalize((pos.0 + sign * step.tan() * dir.0,
+                                   pos.1 + sign * step.tan() * dir.1,
+                                   pos.2 + sign * step.tan() * dir.2));
+        generator.get_value(x, y, z)
+    };
+
+    let mut normals = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (pos, right, up) = frame(x, y);
+            let (pos, right, up) = tangent_frame(pos, right, up);
+            let dx = (sample(pos, right, 1.0) - sample(pos, right, -1.0)) * scale;
+            let dy = (sample(pos, up, 1.0) - sample(pos, up, -1.0)) * scale;
+            normals.push(normalize((-dx, -dy, 1.0)));
+        }
+    }
+    normals
+}
+
+/// Writes normals as an RGB PNG, encoded with `encode_normal`.
+pub fn write_normal_map(filename: &str, normals: &[(f64, f64, f64)], width: usize, height: usize) {
+    let mut canvas = Canvas::new(width, height, [0, 0, 0]);
+    for y in 0..height {
+        for x in 0..width {
+            canvas.set_pixel(x, y, encode_normal(normals[y * width + x]));
+        }
+    }
+    canvas.write_png(filename);
+}
+
+/// Shades tangent space normals by the cosine of the angle to a sun at the
+/// given azimuth (clockwise from +Y) and altitude in degrees, as brightnesses
+/// from 0 to 255.  Slopes facing away from the sun are black.
+pub fn hillshade(normals: &[(f64, f64, f64)], azimuth: f64, altitude: f64) -> Vec<u8> {
+    let (azimuth, altitude) = (azimuth.to_radians(), altitude.to_radians());
+    let sun = (azimuth.sin() * altitude.cos(), azimuth.cos() * altitude.cos(), altitude.sin());
+    normals.iter()
+        .map(|n| {
+            let light = n.0 * sun.0 + n.1 * sun.1 + n.2 * sun.2;
+            (f64::max(light, 0.0) * 255.0).round() as u8
+        })
+        .collect()
+}
@@ -0,0 +1,94 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Ambient occlusion of the terrain, for baking alongside normal maps.
+
+use noise::module::Module;
+
+use normals::tangent_frame;
+use super::{MAX_ELEV, PLANET_CIRCUMFERENCE};
+
+type Vec3 = (f64, f64, f64);
+
+// Number of samples taken along each ray, evenly spaced out to the radius.
+const RAY_STEPS: usize = 8;
+
+/// Estimates the ambient occlusion of each pixel of an image sampled from the
+/// sphere, as brightnesses from 0 (fully occluded) to 255 (open sky).
+///
+/// From each pixel `rays` rays are cast in directions evenly spaced around
+/// the tangent plane, sampling the generator out to `radius` metres along
+/// the surface.  The horizon in each direction is the highest angle above
+/// the tangent plane of those samples, allowing for the curve of the planet,
+/// and the sky is occluded by the sine of it averaged over the rays.  `frame`
+/// is as for `normals::sphere_normals`, and the elevations are multiplied by
+/// `exaggeration`.
+pub fn sphere_occlusion<F>(generator: &Module,
+                           width: usize,
+                           height: usize,
+                           radius: f64,
+                           rays: usize,
+                           exaggeration: f64,
+                           frame: F)
+                           -> Vec<u8>
+    where F: Fn(usize, usize) -> (Vec3, Vec3, Vec3)
+{
+    let planet_radius = PLANET_CIRCUMFERENCE / (2.0 * std::f64::consts::PI);
+    let scale = MAX_ELEV * exaggeration;
+    let directions: Vec<(f64, f64)> = (0..rays)
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / rays as f64;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+
+    let mut occlusion = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (pos, right, up) = frame(x, y);
+            let (pos, right, up) = tangent_frame(pos, right, up);
+            let origin = generator.get_value(pos.0, pos.1, pos.2) * scale;
+
+            let mut occluded = 0.0;
+            for &(cos, sin) in &directions {
+                let dir = (cos * right.0 + sin * up.0,
+                           cos * right.1 + sin * up.1,
+                           cos * right.2 + sin * up.2);
+                let mut horizon: f64 = 0.0;
+                for step in 1..RAY_STEPS + 1 {
+                    let distance = radius * step as f64 / RAY_STEPS as f64;
+                    // Follow the great circle in the direction of the ray.
+                    let angle = distance / planet_radius;
+                    let (a, b) = (angle.cos(), angle.sin());
+                    let (sx, sy, sz) = (a * pos.0 + b * dir.0,
+                                        a * pos.1 + b * dir.1,
+                                        a * pos.2 + b * dir.2);
+                    // The surface falls away from the tangent plane by about
+                    // distance² / 2R.
+                    let rise = generator.get_value(sx, sy, sz) * scale - origin -
+                               distance * distance / (2.0 * planet_radius);
+                    horizon = f64::max(horizon, f64::atan2(rise, distance));
+                }
+                occluded += horizon.sin();
+            }
+            let open = 1.0 - occluded / rays as f64;
+            occlusion.push((open * 255.0).round() as u8);
+        }
+    }
+    occlusion
+}
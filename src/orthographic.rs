@@ -0,0 +1,123 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Output of the planet as a globe, the visible hemisphere seen from far away
+//! in the orthographic projection.
+
+use cph::Projection;
+use style;
+use super::{check_finite, clamp, lat_lon_to_pos, pos_to_lat_lon, write_output_to_file,
+            OutputOptions, SharedModule};
+
+/// The point on the surface at the centre of the globe, in degrees.
+#[derive(Clone, Copy)]
+pub struct View {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl View {
+    // The point on the unit sphere at the centre of pixel (x, y) of a `width`
+    // by `height` image, or None if it is outside the disc of the globe,
+    // which fills the shorter side of the image.  North is up and east to
+    // the right.
+    fn pixel_pos(&self,
+                 width: usize,
+                 height: usize,
+                 x: usize,
+                 y: usize)
+                 -> Option<(f64, f64, f64)> {
+        let radius = usize::min(width, height) as f64 / 2.0;
+        let u = (x as f64 + 0.5 - width as f64 / 2.0) / radius;
+        let v = (height as f64 / 2.0 - (y as f64 + 0.5)) / radius;
+        if u * u + v * v > 1.0 {
+            return None;
+        }
+
+        // Back onto the visible hemisphere, facing the centre.
+        let w = f64::sqrt(1.0 - u * u - v * v);
+        let (lat, lon) = (self.lat.to_radians(), self.lon.to_radians());
+        let centre = lat_lon_to_pos(self.lat, self.lon);
+        let east = (-f64::sin(lon), 0.0, f64::cos(lon));
+        let north = (-f64::sin(lat) * f64::cos(lon), f64::cos(lat), -f64::sin(lat) * f64::sin(lon));
+        Some((centre.0 * w + east.0 * u + north.0 * v,
+              centre.1 * w + east.1 * u + north.1 * v,
+              centre.2 * w + east.2 * u + north.2 * v))
+    }
+}
+
+/// Outputs "orthographic.png", a `width` by `height` image of the globe
+/// centred on `view`, sampling the rows in `threads` worker threads.  Pixels
+/// outside the globe are set to the background elevation of `output`.  The
+/// globe is also drawn in the style of `output`, if any, as
+/// "earthlike.png" and so on.
+pub fn output_orthographic(seed: i32,
+                           generator: &SharedModule,
+                           view: View,
+                           width: usize,
+                           height: usize,
+                           threads: usize,
+                           output: OutputOptions) {
+    let threads = clamp(threads, 1, height);
+    let mut joins = Vec::new();
+    for i in 0..threads {
+        let y0 = i * height / threads;
+        let y1 = (i + 1) * height / threads;
+        let generator = generator.clone();
+        let background = output.background;
+        joins.push(::std::thread::spawn(move || {
+            let mut rows = Vec::with_capacity(width * (y1 - y0));
+            for y in y0..y1 {
+                for x in 0..width {
+                    rows.push(match view.pixel_pos(width, height, x, y) {
+                        Some(pos) => generator.get_value(pos.0, pos.1, pos.2),
+                        None => background,
+                    });
+                }
+            }
+            rows
+        }));
+    }
+
+    let mut dest_buffer = Vec::with_capacity(width * height);
+    for join in joins {
+        dest_buffer.extend(join.join().unwrap());
+    }
+
+    // As with `region::output_region`, the header has no room for the view,
+    // so the image is recorded as custom sampling.
+    let filename = output.paths.path("orthographic.png");
+    if output.strict {
+        check_finite(&filename, &dest_buffer, width, |x, y| {
+            view.pixel_pos(width, height, x, y).map(pos_to_lat_lon)
+        });
+    }
+    write_output_to_file(&filename,
+                         &dest_buffer,
+                         width,
+                         height,
+                         seed,
+                         Projection::Custom,
+                         output);
+
+    if let Some(style) = output.style {
+        let lat_lon = |x, y| view.pixel_pos(width, height, x, y).map_or((0.0, 0.0), pos_to_lat_lon);
+        style::render_style(&dest_buffer, width, height, style, lat_lon, output.climate_tilt)
+            .write_png(&output.paths.path(&format!("{}.png", style.name())));
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Hypsometric (elevation) tinting and reduction of tinted images to a small
+//! fixed palette.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use canvas::Rgb;
+use super::{f64_clamp, SEA_LEVEL};
+
+#[derive(Clone, Copy)]
+pub enum PaletteMethod {
+    // Evenly spaced samples of the hypsometric tint, independent of the
+    // image.
+    Terrain,
+    // Median cut quantization of the colours actually present in the image.
+    MedianCut,
+}
+
+/// A colour gradient over elevation, for `--palette earth` and
+/// `--palette-file`.
+#[derive(Clone, Copy)]
+pub enum Gradient {
+    // Realistic colours, see `earth_colour`.
+    Earth,
+    // Linear interpolation between control points sorted by elevation, see
+    // `read_palette_file`.
+    Custom(&'static [(f64, Rgb)]),
+}
+
+impl Gradient {
+    pub fn colour(&self, value: f64) -> Rgb {
+        match *self {
+            Gradient::Earth => earth_colour(value),
+            Gradient::Custom(stops) => interpolate(stops, value),
+        }
+    }
+}
+
+// The colours of the earth gradient below sea level, from the sea level down
+// to -1.0, at fractions of the depth.
+const EARTH_WATER: [(f64, Rgb); 4] = [(0.0, [104, 168, 216]),
+                                      (0.1, [56, 120, 188]),
+                                      (0.5, [24, 64, 140]),
+                                      (1.0, [8, 16, 64])];
+
+// The colours of the earth gradient above sea level, from the sea level up to
+// +1.0, at fractions of the height.  The snow line is the sharp step to white.
+const EARTH_LAND: [(f64, Rgb); 8] = [(0.0, [84, 148, 72]),
+                                     (0.15, [128, 168, 88]),
+                                     (0.3, [164, 156, 96]),
+                                     (0.45, [144, 112, 72]),
+                                     (0.6, [112, 84, 60]),
+                                     (0.72, [136, 128, 120]),
+                                     (0.74, [240, 240, 244]),
+                                     (1.0, [255, 255, 255])];
+
+// Interpolates linearly between control points sorted by position, clamping
+// to the first and last.
+fn interpolate(stops: &[(f64, Rgb)], position: f64) -> Rgb {
+    let last = stops.len() - 1;
+    if position <= stops[0].0 {
+        return stops[0].1;
+    }
+    for i in 1..stops.len() {
+        let (p0, c0) = stops[i - 1];
+        let (p1, c1) = stops[i];
+        if position <= p1 {
+            let t = if p1 > p0 { (position - p0) / (p1 - p0) } else { 1.0 };
+            return [(c0[0] as f64 + (c1[0] as f64 - c0[0] as f64) * t).round() as u8,
+                    (c0[1] as f64 + (c1[1] as f64 - c0[1] as f64) * t).round() as u8,
+                    (c0[2] as f64 + (c1[2] as f64 - c0[2] as f64) * t).round() as u8];
+        }
+    }
+    stops[last].1
+}
+
+/// Realistic elevation tint: deep blue in the trenches lightening towards
+/// sea level, greens on the lowlands, browns and grey rock in the mountains
+/// and white above the snow line.  The bands are placed relative to
+/// `SEA_LEVEL`, so that everything at or below it is water.
+pub fn earth_colour(value: f64) -> Rgb {
+    if value <= SEA_LEVEL {
+        interpolate(&EARTH_WATER, (SEA_LEVEL - value) / (SEA_LEVEL + 1.0))
+    } else {
+        interpolate(&EARTH_LAND, (value - SEA_LEVEL) / (1.0 - SEA_LEVEL))
+    }
+}
+
+/// Reads the control points of a custom gradient from a text file, one per
+/// line as `elevation r g b`, with the elevation in planetary elevation units
+/// (-1.0 to +1.0) and the colour components from 0 to 255.  Blank lines and
+/// lines starting with `#` are ignored.  The points are returned sorted by
+/// elevation.
+pub fn read_palette_file(filename: &str) -> io::Result<Vec<(f64, Rgb)>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let reader = BufReader::new(File::open(Path::new(filename))?);
+    let mut stops = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let elevation = fields.get(0).and_then(|field| field.parse::<f64>().ok());
+        let components: Vec<Option<u8>> = fields.iter()
+            .skip(1)
+            .map(|field| field.parse::<u8>().ok())
+            .collect();
+        let stop = match (elevation, &components[..]) {
+            (Some(elevation), &[Some(r), Some(g), Some(b)]) => Some((elevation, [r, g, b])),
+            _ => None,
+        };
+        match stop {
+            Some((elevation, colour)) if elevation >= -1.0 && elevation <= 1.0 => {
+                stops.push((elevation, colour));
+            }
+            _ => {
+                return Err(invalid(format!("Line {} isn't an elevation between -1 and 1 \
+                                            followed by three colour components from 0 to 255",
+                                           i + 1)));
+            }
+        }
+    }
+
+    if stops.is_empty() {
+        return Err(invalid("No control points".to_string()));
+    }
+    stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    Ok(stops)
+}
+
+/// Simple elevation tint: shades of blue below sea level, green through
+/// brown to white above.
+pub fn hypsometric_colour(value: f64) -> Rgb {
+    fn lerp(a: Rgb, b: Rgb, t: f64) -> Rgb {
+        let t = f64_clamp(t, 0.0, 1.0);
+        [(a[0] as f64 + (b[0] as f64 - a[0] as f64) * t) as u8,
+         (a[1] as f64 + (b[1] as f64 - a[1] as f64) * t) as u8,
+         (a[2] as f64 + (b[2] as f64 - a[2] as f64) * t) as u8]
+    }
+
+    if value <= SEA_LEVEL {
+        let t = (value - SEA_LEVEL) / (-1.0 - SEA_LEVEL);
+        lerp([64, 128, 192], [8, 24, 72], t)
+    } else {
+        let t = (value - SEA_LEVEL) / (1.0 - SEA_LEVEL);
+        if t < 0.5 {
+            lerp([56, 128, 56], [144, 112, 72], t * 2.0)
+        } else {
+            lerp([144, 112, 72], [255, 255, 255], (t - 0.5) * 2.0)
+        }
+    }
+}
+
+/// Tints `data` and reduces it to at most `colours` palette entries,
+/// returning the palette and one palette index per sample.
+pub fn quantize(data: &[f64], colours: usize, method: PaletteMethod) -> (Vec<Rgb>, Vec<u8>) {
+    assert!(colours >= 2 && colours <= 256);
+
+    let tinted: Vec<Rgb> = data.iter().map(|&v| hypsometric_colour(v)).collect();
+    let palette = match method {
+        PaletteMethod::Terrain => terrain_palette(colours),
+        PaletteMethod::MedianCut => median_cut_palette(&tinted, colours),
+    };
+
+    let mut lookup: HashMap<Rgb, u8> = HashMap::new();
+    let indices = tinted.iter()
+        .map(|colour| *lookup.entry(*colour).or_insert_with(|| nearest(&palette, *colour)))
+        .collect();
+
+    (palette, indices)
+}
+
+// Samples the tint at evenly spaced elevations, so that half the entries go
+// to the oceans and half to the land with the default sea level.
+fn terrain_palette(colours: usize) -> Vec<Rgb> {
+    (0..colours)
+        .map(|i| hypsometric_colour(-1.0 + 2.0 * (i as f64 + 0.5) / colours as f64))
+        .collect()
+}
+
+fn median_cut_palette(tinted: &[Rgb], colours: usize) -> Vec<Rgb> {
+    let mut histogram: HashMap<Rgb, usize> = HashMap::new();
+    for colour in tinted {
+        *histogram.entry(*colour).or_insert(0) += 1;
+    }
+
+    let mut boxes: Vec<Vec<(Rgb, usize)>> = vec![histogram.into_iter().collect()];
+    while boxes.len() < colours {
+        // Split the box with the widest range along any channel.
+        let (idx, channel, range) = boxes.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+            .unwrap();
+        if range == 0 {
+            break;
+        }
+
+        let mut entries = boxes.swap_remove(idx);
+        entries.sort_by_key(|&(colour, _)| colour[channel]);
+
+        // Split at the weighted median, keeping at least one entry per side.
+        let total: usize = entries.iter().map(|&(_, count)| count).sum();
+        let mut acc = 0;
+        let mut split = 1;
+        for (i, &(_, count)) in entries.iter().enumerate() {
+            acc += count;
+            if acc * 2 >= total {
+                split = usize::max(1, usize::min(i + 1, entries.len() - 1));
+                break;
+            }
+        }
+
+        let upper = entries.split_off(split);
+        boxes.push(entries);
+        boxes.push(upper);
+    }
+
+    boxes.iter()
+        .map(|b| {
+            let total: usize = b.iter().map(|&(_, count)| count).sum();
+            let mut sum = [0usize; 3];
+            for &(colour, count) in b {
+                for c in 0..3 {
+                    sum[c] += colour[c] as usize * count;
+                }
+            }
+            [(sum[0] / total) as u8, (sum[1] / total) as u8, (sum[2] / total) as u8]
+        })
+        .collect()
+}
+
+fn widest_channel(entries: &[(Rgb, usize)]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = entries.iter().map(|&(colour, _)| colour[c]).min().unwrap();
+            let max = entries.iter().map(|&(colour, _)| colour[c]).max().unwrap();
+            (c, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap()
+}
+
+fn nearest(palette: &[Rgb], colour: Rgb) -> u8 {
+    let distance = |p: &Rgb| -> i32 {
+        let dr = p[0] as i32 - colour[0] as i32;
+        let dg = p[1] as i32 - colour[1] as i32;
+        let db = p[2] as i32 - colour[2] as i32;
+        dr * dr + dg * dg + db * db
+    };
+
+    let (idx, _) = palette.iter()
+        .enumerate()
+        .min_by_key(|&(_, p)| distance(p))
+        .unwrap();
+    idx as u8
+}
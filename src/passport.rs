@@ -0,0 +1,223 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! The planet "passport": a single composite image showing an
+//! equirectangular map, stereographic views of both poles and a panel of
+//! statistics about the planet.
+
+use noise::module::Module;
+
+use canvas::{self, Canvas, Rgb, GLYPH_HEIGHT};
+use palette::hypsometric_colour;
+use super::{create_generator, generate_rect, lat_lon_to_pos, OutputPaths, PlanetParams,
+            SEA_LEVEL};
+
+// Landmasses covering less than this fraction of the planet's surface are
+// considered islands rather than continents.
+const CONTINENT_MIN_AREA: f64 = 0.01;
+
+const BACKGROUND: Rgb = [24, 24, 32];
+const PANEL: Rgb = [40, 40, 52];
+const TEXT: Rgb = [230, 230, 230];
+
+pub struct PlanetStats {
+    pub land_fraction: f64,
+    pub continents: usize,
+    pub peak: f64,
+    pub peak_lat: f64,
+    pub peak_lon: f64,
+}
+
+/// The width and height of the passport output for a map `width` pixels
+/// wide.
+pub fn canvas_size(width: usize) -> (usize, usize) {
+    let margin = usize::max(width / 64, 4);
+    (width + 2 * margin, width / 2 + width / 4 + 3 * margin)
+}
+
+pub fn output_passport(seed: i32, params: &PlanetParams, width: usize, paths: OutputPaths) {
+    let generator = create_generator(seed, params);
+
+    let map_width = width;
+    let map_height = width / 2;
+    let inset_size = width / 4;
+    let margin = usize::max(width / 64, 4);
+    let scale = usize::max(width / 512, 1);
+
+    let elevations = generate_rect(&*generator, map_width, map_height);
+    let stats = compute_stats(&elevations, map_width, map_height);
+
+    let mut map = Canvas::new(map_width, map_height, BACKGROUND);
+    for y in 0..map_height {
+        for x in 0..map_width {
+            map.set_pixel(x, y, hypsometric_colour(elevations[y * map_width + x]));
+        }
+    }
+
+    let north = polar_inset(&*generator, inset_size, true);
+    let south = polar_inset(&*generator, inset_size, false);
+
+    let (canvas_width, canvas_height) = canvas_size(width);
+    let mut passport = Canvas::new(canvas_width, canvas_height, BACKGROUND);
+
+    passport.blit(&map, margin, margin);
+
+    let row_y = map_height + 2 * margin;
+    passport.blit(&north, margin, row_y);
+    passport.draw_text(margin + scale, row_y + scale, scale, TEXT, "N");
+    passport.blit(&south, margin + inset_size + margin, row_y);
+    passport.draw_text(margin + inset_size + margin + scale, row_y + scale, scale, TEXT, "S");
+
+    let panel_x = margin + 2 * (inset_size + margin);
+    let panel_width = canvas_width - margin - panel_x;
+    passport.fill_rect(panel_x, row_y, panel_width, inset_size, PANEL);
+
+    let lines = [format!("SEED {}", seed),
+                 format!("LAND {:.1}%", stats.land_fraction * 100.0),
+                 format!("CONTINENTS {}", stats.continents),
+                 format!("PEAK {:+.3}", stats.peak),
+                 format!("AT {}, {}", format_lat(stats.peak_lat), format_lon(stats.peak_lon))];
+    let line_height = (GLYPH_HEIGHT + 3) * scale;
+    let mut text_y = row_y + margin;
+    for line in lines.iter() {
+        if canvas::text_width(line, scale) + 2 * margin <= panel_width {
+            passport.draw_text(panel_x + margin, text_y, scale, TEXT, line);
+        }
+        text_y += line_height;
+    }
+
+    passport.write_png(&paths.path("passport.png"));
+}
+
+// Renders a polar stereographic view of one hemisphere into a square inset.
+// The equator lies on the edge of the inscribed disc; the area outside the
+// disc is left as background.
+fn polar_inset(generator: &Module, size: usize, north: bool) -> Canvas {
+    let mut inset = Canvas::new(size, size, BACKGROUND);
+
+    for j in 0..size {
+        for i in 0..size {
+            let u = (2 * i + 1) as f64 / size as f64 - 1.0;
+            let v = 1.0 - (2 * j + 1) as f64 / size as f64;
+            let r = f64::sqrt(u * u + v * v);
+            if r > 1.0 {
+                continue;
+            }
+
+            // Inverse stereographic projection, with the tangent plane at
+            // the pole scaled so that the equator maps to r = 1.0.
+            let colat = 2.0 * f64::atan(r).to_degrees();
+            let (lat, lon) = if north {
+                (90.0 - colat, f64::atan2(u, -v).to_degrees())
+            } else {
+                (colat - 90.0, f64::atan2(u, v).to_degrees())
+            };
+
+            let pos = lat_lon_to_pos(lat, lon);
+            inset.set_pixel(i, j, hypsometric_colour(generator.get_value(pos.0, pos.1, pos.2)));
+        }
+    }
+
+    inset
+}
+
+/// Measures the land, landmasses and highest peak of an equirectangular grid
+/// of elevations produced by `generate_rect`.
+pub fn compute_stats(elevations: &[f64], width: usize, height: usize) -> PlanetStats {
+    // Latitude of the centre of each row, for area weighting, matching the
+    // sampling in `generate_rect`.
+    let row_lat = |y: usize| -> f64 {
+        let row = height - 1 - y;
+        -90.0 + (row as f64 / height as f64) * 180.0
+    };
+
+    let mut total_area = 0.0;
+    let mut land_area = 0.0;
+    let mut peak = ::std::f64::MIN;
+    let mut peak_idx = 0;
+    for y in 0..height {
+        let weight = f64::cos(row_lat(y).to_radians());
+        for x in 0..width {
+            let idx = y * width + x;
+            let value = elevations[idx];
+            total_area += weight;
+            if value > SEA_LEVEL {
+                land_area += weight;
+            }
+            if value > peak {
+                peak = value;
+                peak_idx = idx;
+            }
+        }
+    }
+
+    // Count landmasses by flood filling the land pixels, wrapping around in
+    // longitude.
+    let mut visited = vec![false; width * height];
+    let mut continents = 0;
+    let mut stack = Vec::new();
+    for start in 0..(width * height) {
+        if visited[start] || elevations[start] <= SEA_LEVEL {
+            continue;
+        }
+
+        let mut area = 0.0;
+        visited[start] = true;
+        stack.push(start);
+        while let Some(idx) = stack.pop() {
+            let x = idx % width;
+            let y = idx / width;
+            area += f64::cos(row_lat(y).to_radians());
+
+            let mut neighbours = vec![y * width + (x + 1) % width,
+                                      y * width + (x + width - 1) % width];
+            if y > 0 {
+                neighbours.push(idx - width);
+            }
+            if y + 1 < height {
+                neighbours.push(idx + width);
+            }
+
+            for n in neighbours {
+                if !visited[n] && elevations[n] > SEA_LEVEL {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        if area / total_area >= CONTINENT_MIN_AREA {
+            continents += 1;
+        }
+    }
+
+    PlanetStats {
+        land_fraction: land_area / total_area,
+        continents: continents,
+        peak: peak,
+        peak_lat: row_lat(peak_idx / width),
+        peak_lon: -180.0 + ((peak_idx % width) as f64 / width as f64) * 360.0,
+    }
+}
+
+fn format_lat(lat: f64) -> String {
+    format!("{:.1}{}", lat.abs(), if lat >= 0.0 { "N" } else { "S" })
+}
+
+fn format_lon(lon: f64) -> String {
+    format!("{:.1}{}", lon.abs(), if lon >= 0.0 { "E" } else { "W" })
+}
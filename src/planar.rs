@@ -0,0 +1,71 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Seamlessly tiling planar textures sampled from a single terrain group.
+
+use noise::module::Module;
+
+use cph::Projection;
+use super::{create_layers, write_output_to_file, OutputOptions, PlanetParams, TerrainGroup};
+
+/// Outputs "planar.png", a `size` by `size` texture of the given terrain
+/// group which tiles seamlessly.  The texture covers an `extent` by `extent`
+/// square of the plane z = 0, in the same units as the planet's radius.
+pub fn output_planar_tileable(seed: i32,
+                              params: &PlanetParams,
+                              group: TerrainGroup,
+                              size: usize,
+                              extent: f64,
+                              output: OutputOptions) {
+    let layers = create_layers(seed, params);
+    let terrain = layers.terrain_group(group);
+
+    let mut dest_buffer: Vec<f64> = vec![0.0; size * size];
+    for y in 0..size {
+        for x in 0..size {
+            let u = x as f64 / size as f64 * extent;
+            let v = y as f64 / size as f64 * extent;
+            dest_buffer[y * size + x] = seamless_value(&*terrain, u, v, extent);
+        }
+    }
+
+    write_output_to_file(&output.paths.path("planar.png"),
+                         &dest_buffer,
+                         size,
+                         size,
+                         seed,
+                         Projection::Planar,
+                         output);
+}
+
+// Blends the values at (u, v) and its three copies one `extent` away in u, v
+// and both.  The copies are weighted so that samples on opposite edges of the
+// texture get their values from the same points, so the texture wraps
+// without a seam.  This is the same technique as the seamless option of
+// libnoise's plane noise map builder.
+fn seamless_value(module: &Module, u: f64, v: f64, extent: f64) -> f64 {
+    let sw = module.get_value(u, v, 0.0);
+    let se = module.get_value(u + extent, v, 0.0);
+    let nw = module.get_value(u, v + extent, 0.0);
+    let ne = module.get_value(u + extent, v + extent, 0.0);
+
+    let u_blend = 1.0 - u / extent;
+    let v_blend = 1.0 - v / extent;
+    let south = sw + (se - sw) * u_blend;
+    let north = nw + (ne - nw) * u_blend;
+    south + (north - south) * v_blend
+}
@@ -0,0 +1,117 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! A PNG writer which is given the image a band of rows at a time, so that
+//! the whole image never needs to be held in memory.
+//!
+//! Each band is compressed on its own into a fixed-Huffman deflate block, as
+//! for `indexed_png`, followed by an empty stored block to align it to a byte
+//! so that it can be written out as an IDAT chunk straight away.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bits::BitWriter;
+use indexed_png::{deflate_fixed_block, u32_be, write_chunk, Adler32};
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// A PNG file being written, see the module documentation.
+pub struct PngStream {
+    writer: BufWriter<File>,
+    adler: Adler32,
+    row_bytes: usize,
+    rows_left: usize,
+}
+
+impl PngStream {
+    /// Creates a `width` by `height` PNG of the given colour type and bit
+    /// depth (as in the IHDR chunk), writing its header.
+    pub fn create(filename: &str,
+                  width: usize,
+                  height: usize,
+                  colour_type: u8,
+                  bit_depth: u8)
+                  -> PngStream {
+        let channels = match colour_type {
+            0 => 1,
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => panic!("Unsupported PNG colour type {}", colour_type),
+        };
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&u32_be(width as u32));
+        ihdr.extend_from_slice(&u32_be(height as u32));
+        ihdr.extend_from_slice(&[bit_depth, colour_type, 0, 0, 0]);
+
+        let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&PNG_SIGNATURE).expect("Failed to write image data");
+        write_chunk(&mut writer, b"IHDR", &ihdr);
+        // The zlib header.
+        write_chunk(&mut writer, b"IDAT", &[0x78, 0x01]);
+
+        PngStream {
+            writer: writer,
+            adler: Adler32::new(),
+            row_bytes: width * channels * bit_depth as usize / 8,
+            rows_left: height,
+        }
+    }
+
+    /// Writes the next rows of the image, the pixels packed as in the image
+    /// data of a PNG but without the filter type of each row.
+    pub fn write_rows(&mut self, data: &[u8]) {
+        assert_eq!(data.len() % self.row_bytes, 0);
+        let rows = data.len() / self.row_bytes;
+        assert!(rows <= self.rows_left);
+        self.rows_left -= rows;
+
+        // Prefix each row with filter type 0 (None).
+        let mut raw = Vec::with_capacity((self.row_bytes + 1) * rows);
+        for row in data.chunks(self.row_bytes) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        self.adler.update(&raw);
+
+        let mut bits = BitWriter::new();
+        deflate_fixed_block(&mut bits, &raw, false);
+        // An empty stored block, BFINAL = 0 and BTYPE = 00, padded to a byte
+        // and followed by its length and the length's complement.
+        bits.write_bits(0, 3);
+        let mut idat = bits.finish();
+        idat.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+        write_chunk(&mut self.writer, b"IDAT", &idat);
+    }
+
+    /// Ends the image once all of its rows have been written.
+    pub fn finish(mut self) {
+        assert_eq!(self.rows_left, 0);
+
+        let mut bits = BitWriter::new();
+        deflate_fixed_block(&mut bits, &[], true);
+        let mut idat = bits.finish();
+        idat.extend_from_slice(&u32_be(self.adler.finish()));
+        write_chunk(&mut self.writer, b"IDAT", &idat);
+        write_chunk(&mut self.writer, b"IEND", &[]);
+        self.writer.flush().expect("Failed to write image data");
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Elevation profiles along great circle arcs.
+
+use std::io::{self, Write};
+
+use noise::module::Module;
+
+use super::{lat_lon_to_pos, pos_to_lat_lon};
+
+/// Writes the elevations of `samples` points spaced evenly along the shorter
+/// great circle arc from `start` to `end` (latitude and longitude in degrees)
+/// as CSV, with a header row and the columns `distance_deg,lat,lon,elevation`.
+/// The distance is the angle from `start` in degrees.  When the points are
+/// antipodal the arc is ambiguous, and the one through +Y is taken.
+pub fn write_profile<W: Write>(writer: &mut W,
+                               generator: &Module,
+                               start: (f64, f64),
+                               end: (f64, f64),
+                               samples: usize)
+                               -> io::Result<()> {
+    assert!(samples >= 2);
+
+    let a = lat_lon_to_pos(start.0, start.1);
+    let b = lat_lon_to_pos(end.0, end.1);
+    let dot = a.0 * b.0 + a.1 * b.1 + a.2 * b.2;
+    let angle = f64::acos(f64::max(-1.0, f64::min(dot, 1.0)));
+
+    // Spherical interpolation, the points of the arc are a * cos(theta) +
+    // c * sin(theta) for the unit vector c perpendicular to a in the plane of
+    // the arc.
+    let c = if angle > 1e-9 && angle < std::f64::consts::PI - 1e-9 {
+        (b.0 - dot * a.0, b.1 - dot * a.1, b.2 - dot * a.2)
+    } else {
+        // Any perpendicular will do, through +Y unless a is on the Y axis.
+        let up = if a.1.abs() < 0.9 { (0.0, 1.0, 0.0) } else { (1.0, 0.0, 0.0) };
+        let d = a.0 * up.0 + a.1 * up.1 + a.2 * up.2;
+        (up.0 - d * a.0, up.1 - d * a.1, up.2 - d * a.2)
+    };
+    let len = f64::sqrt(c.0 * c.0 + c.1 * c.1 + c.2 * c.2);
+    let c = (c.0 / len, c.1 / len, c.2 / len);
+
+    writeln!(writer, "distance_deg,lat,lon,elevation")?;
+    for i in 0..samples {
+        let theta = angle * i as f64 / (samples - 1) as f64;
+        let (sin, cos) = theta.sin_cos();
+        let pos = (a.0 * cos + c.0 * sin, a.1 * cos + c.1 * sin, a.2 * cos + c.2 * sin);
+        let (lat, lon) = pos_to_lat_lon(pos);
+        let elevation = generator.get_value(pos.0, pos.1, pos.2);
+        writeln!(writer, "{},{},{},{}", theta.to_degrees(), lat, lon, elevation)?;
+    }
+    writer.flush()
+}
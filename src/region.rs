@@ -0,0 +1,127 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Output of a latitude/longitude box of the planet, an equirectangular map
+//! of just that part of the globe.
+
+use cph::Projection;
+use super::{check_finite, clamp, lat_lon_to_pos, write_output_to_file, OutputOptions, SharedModule};
+
+/// A box of latitudes and longitudes, in degrees.  If `min_lon` is greater
+/// than `max_lon` the box crosses the antimeridian, running east from
+/// `min_lon` through ±180.
+#[derive(Clone, Copy)]
+pub struct Region {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl Region {
+    /// The height of the box, in degrees of latitude.
+    pub fn lat_span(&self) -> f64 {
+        self.max_lat - self.min_lat
+    }
+
+    /// The width of the box, in degrees of longitude.
+    pub fn lon_span(&self) -> f64 {
+        if self.min_lon < self.max_lon {
+            self.max_lon - self.min_lon
+        } else {
+            self.max_lon - self.min_lon + 360.0
+        }
+    }
+
+    // The latitude and longitude, in degrees, of pixel (x, y) of a `width` by
+    // `height` map of the box.  Like `generate_rect`, each pixel is sampled
+    // at its south-west corner, so that a box of the whole globe gives the
+    // same map.
+    fn pixel_lat_lon(&self, width: usize, height: usize, x: usize, y: usize) -> (f64, f64) {
+        let lat = self.max_lat - ((y + 1) as f64 / height as f64) * self.lat_span();
+        let mut lon = self.min_lon + (x as f64 / width as f64) * self.lon_span();
+        if lon >= 180.0 {
+            lon -= 360.0;
+        }
+        (lat, lon)
+    }
+}
+
+/// Outputs "region.png", a `width` by `height` equirectangular map of
+/// `region`, sampling the rows in `threads` worker threads.  Each pixel is
+/// the average of `supersample` by `supersample` samples, as with
+/// `generate_rect_region`.
+pub fn output_region(seed: i32,
+                     generator: &SharedModule,
+                     region: Region,
+                     width: usize,
+                     height: usize,
+                     threads: usize,
+                     supersample: usize,
+                     output: OutputOptions) {
+    let lat_step = region.lat_span() / height as f64;
+    let lon_step = region.lon_span() / width as f64;
+
+    let threads = clamp(threads, 1, height);
+    let mut joins = Vec::new();
+    for i in 0..threads {
+        let y0 = i * height / threads;
+        let y1 = (i + 1) * height / threads;
+        let generator = generator.clone();
+        joins.push(::std::thread::spawn(move || {
+            let offset = |i: usize| (i as f64 + 0.5) / supersample as f64 - 0.5;
+            let mut rows = Vec::with_capacity(width * (y1 - y0));
+            for y in y0..y1 {
+                for x in 0..width {
+                    let (lat, lon) = region.pixel_lat_lon(width, height, x, y);
+                    let mut total = 0.0;
+                    for i in 0..supersample {
+                        for j in 0..supersample {
+                            let pos = lat_lon_to_pos(lat + offset(i) * lat_step,
+                                                     lon + offset(j) * lon_step);
+                            total += generator.get_value(pos.0, pos.1, pos.2);
+                        }
+                    }
+                    rows.push(total / (supersample * supersample) as f64);
+                }
+            }
+            rows
+        }));
+    }
+
+    let mut dest_buffer = Vec::with_capacity(width * height);
+    for join in joins {
+        dest_buffer.extend(join.join().unwrap());
+    }
+
+    // The header has no room for the bounds of the box, so the map is
+    // recorded as custom sampling rather than as the whole globe.
+    let filename = output.paths.path("region.png");
+    if output.strict {
+        check_finite(&filename, &dest_buffer, width, |x, y| {
+            Some(region.pixel_lat_lon(width, height, x, y))
+        });
+    }
+    write_output_to_file(&filename,
+                         &dest_buffer,
+                         width,
+                         height,
+                         seed,
+                         Projection::Custom,
+                         output);
+}
@@ -0,0 +1,88 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Searching for seeds which produce planets with wanted properties.
+
+use passport::compute_stats;
+use super::{create_generator, generate_rect, PlanetParams};
+
+#[derive(Clone, Copy)]
+pub struct SearchCriteria {
+    // Width of the equirectangular grid each seed is measured on.  Coarse, as
+    // only the overall shape of the continents matters.
+    pub width: usize,
+
+    // Wanted fraction of the surface covered by land, within `tolerance`.
+    pub land_fraction: Option<f64>,
+    pub tolerance: f64,
+
+    // Wanted range of the fraction of the surface covered by ocean,
+    // inclusive.
+    pub ocean_fraction: Option<(f64, f64)>,
+
+    // Wanted number of continents, as counted for the planet passport.
+    pub continents: Option<usize>,
+
+    // Stop after finding this many matching seeds or trying this many seeds,
+    // whichever comes first.
+    pub max_hits: usize,
+    pub max_attempts: usize,
+}
+
+/// Tries seeds counting up from `first_seed`, printing those which match the
+/// criteria.
+pub fn search_seeds(first_seed: i32, params: &PlanetParams, criteria: &SearchCriteria) {
+    let width = criteria.width;
+    let height = usize::max(width / 2, 1);
+    let mut hits = 0;
+    let mut attempts = 0;
+
+    while hits < criteria.max_hits && attempts < criteria.max_attempts {
+        let seed = first_seed.wrapping_add(attempts as i32);
+        attempts += 1;
+
+        let generator = create_generator(seed, params);
+        let elevations = generate_rect(&*generator, width, height);
+        let stats = compute_stats(&elevations, width, height);
+
+        let land_matches = match criteria.land_fraction {
+            Some(wanted) => f64::abs(stats.land_fraction - wanted) <= criteria.tolerance,
+            None => true,
+        };
+        let ocean_matches = match criteria.ocean_fraction {
+            Some((lo, hi)) => {
+                let ocean_fraction = 1.0 - stats.land_fraction;
+                ocean_fraction >= lo && ocean_fraction <= hi
+            }
+            None => true,
+        };
+        let continents_match = match criteria.continents {
+            Some(wanted) => stats.continents == wanted,
+            None => true,
+        };
+
+        if land_matches && ocean_matches && continents_match {
+            hits += 1;
+            println!("Seed {}: {:.1}% land, {} continents",
+                     seed,
+                     stats.land_fraction * 100.0,
+                     stats.continents);
+        }
+    }
+
+    println!("Found {} matching seeds in {} attempts", hits, attempts);
+}
@@ -0,0 +1,128 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Noise modules which can be shared between threads, so that a single
+//! planet can be sampled by several threads at once.
+
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use noise::module::Module;
+
+/// A noise module used as the source of several others, or by several
+/// threads.  Cloning it only clones the reference to the module.
+#[derive(Clone)]
+pub struct SharedModule(Arc<Module + Send + Sync>);
+
+impl SharedModule {
+    pub fn new<M: Module + Send + Sync + 'static>(module: M) -> SharedModule {
+        SharedModule(Arc::new(module))
+    }
+}
+
+impl Deref for SharedModule {
+    type Target = Module + Send + Sync;
+
+    fn deref(&self) -> &(Module + Send + Sync + 'static) {
+        &*self.0
+    }
+}
+
+impl Module for SharedModule {
+    fn get_value(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.0.get_value(x, y, z)
+    }
+}
+
+// The next id to give a cache.  Ids aren't reused, so the values left behind
+// in a slot by a cache which has been dropped are never mistaken for those of
+// the next cache given the slot.
+static NEXT_CACHE_ID: AtomicUsize = AtomicUsize::new(0);
+
+// The next slot never given to a cache, and those given back by caches which
+// have been dropped.  Reusing the slots bounds the values each thread keeps
+// to the number of caches alive at once.
+static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+static FREE_SLOTS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+// The id of the cache, the point it was last sampled at by a thread and the
+// value there.
+type LastValue = (usize, (f64, f64, f64), f64);
+
+thread_local! {
+    // The last value of each cache sampled by this thread, by its slot.
+    static LAST_VALUES: RefCell<Vec<Option<LastValue>>> = RefCell::new(Vec::new());
+}
+
+/// Caches the last value of its source module, like libnoise's cache module,
+/// but keeps the last value separately for each thread sampling it.  Threads
+/// sampling the same module at the same time then each still benefit from
+/// the cache, without seeing each other's values.
+pub struct Cache<S> {
+    source: S,
+    id: usize,
+    slot: usize,
+}
+
+impl<S: Module> Cache<S> {
+    pub fn new(source: S) -> Cache<S> {
+        let slot = FREE_SLOTS.lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| NEXT_SLOT.fetch_add(1, Ordering::Relaxed));
+        Cache {
+            source: source,
+            id: NEXT_CACHE_ID.fetch_add(1, Ordering::Relaxed),
+            slot: slot,
+        }
+    }
+}
+
+impl<S: Module> Module for Cache<S> {
+    fn get_value(&self, x: f64, y: f64, z: f64) -> f64 {
+        let cached = LAST_VALUES.with(|values| match values.borrow().get(self.slot) {
+            Some(&Some((id, pos, value))) if id == self.id && pos == (x, y, z) => Some(value),
+            _ => None,
+        });
+        if let Some(value) = cached {
+            return value;
+        }
+
+        // The source may contain caches of its own, so the values aren't
+        // borrowed while it is sampled.
+        let value = self.source.get_value(x, y, z);
+        LAST_VALUES.with(|values| {
+            let mut values = values.borrow_mut();
+            if values.len() <= self.slot {
+                values.resize(self.slot + 1, None);
+            }
+            values[self.slot] = Some((self.id, (x, y, z), value));
+        });
+        value
+    }
+}
+
+impl<S> Drop for Cache<S> {
+    fn drop(&mut self) {
+        // The values other threads keep in the slot are left behind, to be
+        // overwritten by the next cache given it.
+        FREE_SLOTS.lock().unwrap().push(self.slot);
+    }
+}
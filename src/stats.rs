@@ -0,0 +1,89 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Summary statistics of the elevations of an output, for --stats.
+
+use super::SEA_LEVEL;
+
+/// The minimum, maximum, mean and standard deviation of a set of elevations,
+/// along with the fraction of them below sea level.  Non-finite elevations
+/// are left out.  Statistics of separate images, such as the faces of the
+/// cube, can be merged.
+#[derive(Clone, Copy)]
+pub struct ElevationStats {
+    count: usize,
+    below_sea_level: usize,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl ElevationStats {
+    pub fn new() -> ElevationStats {
+        ElevationStats {
+            count: 0,
+            below_sea_level: 0,
+            min: ::std::f64::INFINITY,
+            max: ::std::f64::NEG_INFINITY,
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    pub fn from_elevations(elevations: &[f64]) -> ElevationStats {
+        let mut stats = ElevationStats::new();
+        for &value in elevations.iter().filter(|value| value.is_finite()) {
+            stats.count += 1;
+            if value < SEA_LEVEL {
+                stats.below_sea_level += 1;
+            }
+            stats.min = f64::min(stats.min, value);
+            stats.max = f64::max(stats.max, value);
+            stats.sum += value;
+            stats.sum_squares += value * value;
+        }
+        stats
+    }
+
+    pub fn merge(&mut self, other: &ElevationStats) {
+        self.count += other.count;
+        self.below_sea_level += other.below_sea_level;
+        self.min = f64::min(self.min, other.min);
+        self.max = f64::max(self.max, other.max);
+        self.sum += other.sum;
+        self.sum_squares += other.sum_squares;
+    }
+
+    /// Writes the statistics to stderr as a single line, after `label`.
+    pub fn print(&self, label: &str) {
+        if self.count == 0 {
+            eprintln!("{}: no finite elevations", label);
+            return;
+        }
+        let mean = self.sum / self.count as f64;
+        let variance = f64::max(self.sum_squares / self.count as f64 - mean * mean, 0.0);
+        eprintln!("{}: min {:+.6} max {:+.6} mean {:+.6} stddev {:.6} ocean {:.2}%",
+                  label,
+                  self.min,
+                  self.max,
+                  mean,
+                  f64::sqrt(variance),
+                  self.below_sea_level as f64 / self.count as f64 * 100.0);
+    }
+}
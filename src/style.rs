@@ -0,0 +1,108 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! Quick composited renders of a map in a fixed style, without a palette.
+
+use canvas::{Canvas, Rgb};
+use super::{climate_lat, f64_clamp, SEA_LEVEL};
+
+/// The styles of `render_style`.
+#[derive(Clone, Copy)]
+pub enum Style {
+    // Flat blue oceans, a light shoreline, green to brown land and snow
+    // above a snow line which lowers towards the poles.
+    Earthlike,
+}
+
+impl Style {
+    /// The name of the style, used for the output filenames.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Style::Earthlike => "earthlike",
+        }
+    }
+}
+
+const OCEAN: Rgb = [36, 84, 156];
+const SHORE: Rgb = [214, 204, 156];
+const LOWLAND: Rgb = [72, 136, 60];
+const HIGHLAND: Rgb = [132, 104, 68];
+const SNOW: Rgb = [244, 246, 250];
+
+// The height of the shoreline band, as a fraction of the height between sea
+// level and +1.0.
+const SHORE_BAND: f64 = 0.015;
+
+// The height of the snow line on the equator, as a fraction of the height
+// between sea level and +1.0.  It falls with the cosine of the latitude, down
+// to sea level at the poles.
+const EQUATOR_SNOW_LINE: f64 = 0.7;
+
+fn lerp(a: Rgb, b: Rgb, t: f64) -> Rgb {
+    let t = f64_clamp(t, 0.0, 1.0);
+    [(a[0] as f64 + (b[0] as f64 - a[0] as f64) * t).round() as u8,
+     (a[1] as f64 + (b[1] as f64 - a[1] as f64) * t).round() as u8,
+     (a[2] as f64 + (b[2] as f64 - a[2] as f64) * t).round() as u8]
+}
+
+fn earthlike_colour(lat: f64, value: f64) -> Rgb {
+    if value <= SEA_LEVEL {
+        return OCEAN;
+    }
+    let height = (value - SEA_LEVEL) / (1.0 - SEA_LEVEL);
+    let snow_line = EQUATOR_SNOW_LINE * f64::cos(lat.to_radians());
+    if height >= snow_line {
+        SNOW
+    } else if height < SHORE_BAND {
+        SHORE
+    } else {
+        lerp(LOWLAND, HIGHLAND, (height - SHORE_BAND) / (snow_line - SHORE_BAND))
+    }
+}
+
+/// Draws an image of elevations in the given style.  `lat_lon` gives the
+/// latitude and longitude of each pixel, in degrees, and the climate poles
+/// lowering the snow line are tilted `climate_tilt` degrees from the
+/// geographic poles.  NaN samples, such as the background around a globe, are
+/// left black.
+pub fn render_style<F>(data: &[f64],
+                       width: usize,
+                       height: usize,
+                       style: Style,
+                       lat_lon: F,
+                       climate_tilt: f64)
+                       -> Canvas
+    where F: Fn(usize, usize) -> (f64, f64)
+{
+    let mut canvas = Canvas::new(width, height, [0, 0, 0]);
+    for y in 0..height {
+        for x in 0..width {
+            let value = data[y * width + x];
+            if value.is_nan() {
+                continue;
+            }
+            let colour = match style {
+                Style::Earthlike => {
+                    earthlike_colour(climate_lat(lat_lon(x, y), climate_tilt), value)
+                }
+            };
+            canvas.set_pixel(x, y, colour);
+        }
+    }
+    canvas
+}
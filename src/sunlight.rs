@@ -0,0 +1,106 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Day/night shading of the elevation tinted map for a given sun position.
+
+use canvas::{Canvas, Rgb};
+use palette::hypsometric_colour;
+use super::{f64_clamp, lat_lon_to_pos, SEA_LEVEL};
+
+// Half the width of the twilight band, as the sine of the sun's elevation
+// angle.  0.1 gives a band of roughly six degrees either side of the
+// terminator.
+const TWILIGHT_HALF_WIDTH: f64 = 0.1;
+
+// Brightness of the night side relative to the day side.
+const NIGHT_BRIGHTNESS: f64 = 0.08;
+
+const CITY_LIGHT: Rgb = [255, 204, 128];
+
+#[derive(Clone, Copy)]
+pub struct SunPosition {
+    pub lat: f64,
+    pub lon: f64,
+    // Whether to light up coastal land on the night side.
+    pub city_lights: bool,
+}
+
+/// Tints an equirectangular elevation grid (as produced by `generate_rect`)
+/// and darkens the side of the planet facing away from the sun.
+pub fn render_sunlit(data: &[f64], width: usize, height: usize, sun: SunPosition) -> Canvas {
+    let (sx, sy, sz) = lat_lon_to_pos(sun.lat, sun.lon);
+    let mut canvas = Canvas::new(width, height, [0, 0, 0]);
+
+    for y in 0..height {
+        let lat = -90.0 + ((height - 1 - y) as f64 / height as f64) * 180.0;
+        for x in 0..width {
+            let lon = -180.0 + (x as f64 / width as f64) * 360.0;
+            let (px, py, pz) = lat_lon_to_pos(lat, lon);
+
+            // Smoothly blend from night to day across the twilight band.
+            let sun_height = px * sx + py * sy + pz * sz;
+            let t = f64_clamp((sun_height + TWILIGHT_HALF_WIDTH) / (2.0 * TWILIGHT_HALF_WIDTH),
+                              0.0,
+                              1.0);
+            let day = t * t * (3.0 - 2.0 * t);
+            let brightness = NIGHT_BRIGHTNESS + (1.0 - NIGHT_BRIGHTNESS) * day;
+
+            let idx = y * width + x;
+            let colour = hypsometric_colour(data[idx]);
+            let mut lit = [0.0; 3];
+            for c in 0..3 {
+                lit[c] = colour[c] as f64 * brightness;
+            }
+
+            if sun.city_lights && day < 0.5 && is_coast(data, width, height, x, y) &&
+               settlement_hash(idx) {
+                let glow = 1.0 - 2.0 * day;
+                for c in 0..3 {
+                    lit[c] += CITY_LIGHT[c] as f64 * glow;
+                }
+            }
+
+            canvas.set_pixel(x, y,
+                             [f64::min(lit[0], 255.0) as u8,
+                              f64::min(lit[1], 255.0) as u8,
+                              f64::min(lit[2], 255.0) as u8]);
+        }
+    }
+
+    canvas
+}
+
+// Whether the sample is land with an ocean sample beside it, wrapping around
+// in longitude.
+fn is_coast(data: &[f64], width: usize, height: usize, x: usize, y: usize) -> bool {
+    if data[y * width + x] <= SEA_LEVEL {
+        return false;
+    }
+
+    let left = y * width + (x + width - 1) % width;
+    let right = y * width + (x + 1) % width;
+    let up = if y > 0 { (y - 1) * width + x } else { y * width + x };
+    let down = if y + 1 < height { (y + 1) * width + x } else { y * width + x };
+    [left, right, up, down].iter().any(|&n| data[n] <= SEA_LEVEL)
+}
+
+// Lights only some coastal samples, so the lights look like scattered
+// settlements rather than a continuous outline.
+fn settlement_hash(idx: usize) -> bool {
+    let h = (idx as u32).wrapping_mul(0x9e3779b1);
+    (h >> 29) < 3
+}
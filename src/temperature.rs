@@ -0,0 +1,123 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! A simple model of the planet's mean surface temperature, from the latitude
+//! and the height of the land.
+
+use canvas::{Canvas, Rgb};
+use cph::{self, CphHeader, DataType, Projection};
+use super::{climate_lat, f64_clamp, SEA_LEVEL};
+
+// Colours of the thermal ramp at the given temperatures, in degrees Celsius,
+// interpolated between.  The ramp is fixed so that maps of different planets
+// can be compared.
+const RAMP: [(f64, Rgb); 7] = [(-40.0, [60, 0, 110]),
+                               (-20.0, [30, 60, 220]),
+                               (0.0, [120, 200, 255]),
+                               (10.0, [80, 190, 80]),
+                               (20.0, [250, 220, 60]),
+                               (30.0, [240, 120, 30]),
+                               (40.0, [180, 20, 20])];
+
+#[derive(Clone, Copy)]
+pub struct TemperatureModel {
+    // Temperatures at sea level on the equator and at the poles, in degrees
+    // Celsius.
+    pub equator: f64,
+    pub pole: f64,
+
+    // Drop in temperature with height above sea level, in degrees Celsius
+    // per kilometre.
+    pub lapse_rate: f64,
+
+    // Elevation in metres of +1.0 planetary elevation units.
+    pub elevation_range: f64,
+}
+
+impl TemperatureModel {
+    /// The temperature in degrees Celsius at the given latitude and
+    /// elevation.  The sea level temperature falls from the equator to the
+    /// poles with the cosine of the latitude, and the land is cooled by the
+    /// lapse rate; the sea is at its sea level temperature.
+    pub fn temperature(&self, lat: f64, elevation: f64) -> f64 {
+        let sea_level = self.pole + (self.equator - self.pole) * f64::cos(lat.to_radians());
+        let height_km = f64::max(elevation - SEA_LEVEL, 0.0) * self.elevation_range / 1000.0;
+        sea_level - self.lapse_rate * height_km
+    }
+}
+
+fn ramp_colour(temperature: f64) -> Rgb {
+    let (first, last) = (RAMP[0], RAMP[RAMP.len() - 1]);
+    let temperature = f64_clamp(temperature, first.0, last.0);
+    for stops in RAMP.windows(2) {
+        let ((t0, c0), (t1, c1)) = (stops[0], stops[1]);
+        if temperature <= t1 {
+            let t = (temperature - t0) / (t1 - t0);
+            let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+            return [lerp(c0[0], c1[0]), lerp(c0[1], c1[1]), lerp(c0[2], c1[2])];
+        }
+    }
+    last.1
+}
+
+/// Outputs "`stem`.cph", the temperature in degrees Celsius of each sample of
+/// an image of elevations in the given projection, and if `ramp` is set
+/// "`stem`.png", the temperatures drawn with a thermal colour ramp from -40 to
+/// +40 degrees.  `lat_lon` gives the latitude and longitude of each pixel, and
+/// the climate poles are tilted `climate_tilt` degrees from the geographic
+/// poles.
+pub fn output_temperature<F>(elevations: &[f64],
+                             width: usize,
+                             height: usize,
+                             seed: i32,
+                             projection: Projection,
+                             lat_lon: F,
+                             climate_tilt: f64,
+                             model: &TemperatureModel,
+                             ramp: bool,
+                             stem: &str)
+    where F: Fn(usize, usize) -> (f64, f64)
+{
+    let mut temperatures = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let lat = climate_lat(lat_lon(x, y), climate_tilt);
+            temperatures.push(model.temperature(lat, elevations[y * width + x]));
+        }
+    }
+
+    let header = CphHeader {
+        data_type: DataType::F32,
+        projection: projection,
+        width: width as u32,
+        height: height as u32,
+        seed: seed,
+    };
+    cph::write_cph(&format!("{}.cph", stem), &header, &temperatures)
+        .expect("Failed to write temperature data");
+
+    if ramp {
+        let mut canvas = Canvas::new(width, height, [0, 0, 0]);
+        for y in 0..height {
+            for x in 0..width {
+                canvas.set_pixel(x, y, ramp_colour(temperatures[y * width + x]));
+            }
+        }
+        canvas.write_png(&format!("{}.png", stem));
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! Visualization of the downhill direction of the terrain as a field of
+//! arrows over a hillshade.
+
+use canvas::{Canvas, Rgb};
+
+const ARROW_COLOUR: Rgb = [140, 20, 20];
+
+// How far the hillshade is allowed to darken the white background, out of 255.
+const SHADE_DEPTH: f64 = 72.0;
+
+// Arrows steeper than this percentile of the sampled steepnesses are drawn at
+// full length.
+const ARROW_PERCENTILE: usize = 90;
+
+// The steepest sample is shaded as if it had this slope, in degrees.
+const SHADE_MAX_SLOPE: f64 = 60.0;
+
+/// Draws an arrow for every `spacing` by `spacing` cell of the elevation
+/// grid, pointing downhill with a length proportional to the steepness at
+/// the centre of the cell.  The steepness is measured in elevation per
+/// pixel, so arrows are comparable within an image but not between images.
+pub fn render_vector_field(data: &[f64], width: usize, height: usize, spacing: usize) -> Canvas {
+    let gradient = |x: usize, y: usize| {
+        let (x0, x1) = (if x > 0 { x - 1 } else { x }, usize::min(x + 1, width - 1));
+        let (y0, y1) = (if y > 0 { y - 1 } else { y }, usize::min(y + 1, height - 1));
+        let gx = if x1 > x0 {
+            (data[y * width + x1] - data[y * width + x0]) / (x1 - x0) as f64
+        } else {
+            0.0
+        };
+        let gy = if y1 > y0 {
+            (data[y1 * width + x] - data[y0 * width + x]) / (y1 - y0) as f64
+        } else {
+            0.0
+        };
+        (gx, gy)
+    };
+
+    let mut gradients = Vec::with_capacity(width * height);
+    let mut max_gradient: f64 = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let (gx, gy) = gradient(x, y);
+            max_gradient = f64::max(max_gradient, f64::sqrt(gx * gx + gy * gy));
+            gradients.push((gx, gy));
+        }
+    }
+
+    // Faint hillshade, lit from the north west.
+    let mut canvas = Canvas::new(width, height, [255, 255, 255]);
+    if max_gradient > 0.0 {
+        let z_factor = SHADE_MAX_SLOPE.to_radians().tan() / max_gradient;
+        let light = (-0.5, -0.5, f64::sqrt(0.5));
+        for y in 0..height {
+            for x in 0..width {
+                let (gx, gy) = gradients[y * width + x];
+                let (nx, ny, nz) = (-gx * z_factor, -gy * z_factor, 1.0);
+                let len = f64::sqrt(nx * nx + ny * ny + nz * nz);
+                let shade = (nx * light.0 + ny * light.1 + nz * light.2) / len;
+                let value = 255.0 - SHADE_DEPTH * (1.0 - f64::max(shade, 0.0));
+                let value = value as u8;
+                canvas.set_pixel(x, y, [value, value, value]);
+            }
+        }
+    }
+
+    // Scale the arrows so that all but the steepest few nearly fill their
+    // cell, otherwise a handful of cliffs would leave every other arrow too
+    // short to see.  Steeper arrows are clamped to the same length.
+    let mut sampled = Vec::new();
+    for cy in 0..(height / spacing) {
+        for cx in 0..(width / spacing) {
+            let (gx, gy) = gradients[(cy * spacing + spacing / 2) * width + cx * spacing +
+                                     spacing / 2];
+            sampled.push(f64::sqrt(gx * gx + gy * gy));
+        }
+    }
+    sampled.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let full_length = match sampled.get(sampled.len() * ARROW_PERCENTILE / 100) {
+        Some(&magnitude) if magnitude > 0.0 => magnitude,
+        _ => return canvas,
+    };
+
+    for cy in 0..(height / spacing) {
+        for cx in 0..(width / spacing) {
+            let x = cx * spacing + spacing / 2;
+            let y = cy * spacing + spacing / 2;
+            let (gx, gy) = gradients[y * width + x];
+            let magnitude = f64::sqrt(gx * gx + gy * gy);
+            let length = f64::min(magnitude / full_length, 1.0) * spacing as f64 * 0.9;
+            let (x, y) = (x as f64, y as f64);
+            if length < 1.0 {
+                canvas.set_pixel(x as usize, y as usize, ARROW_COLOUR);
+                continue;
+            }
+
+            // Downhill is against the gradient.
+            let (dx, dy) = (-gx / magnitude, -gy / magnitude);
+            let (tip_x, tip_y) = (x + dx * length / 2.0, y + dy * length / 2.0);
+            canvas.draw_line(x - dx * length / 2.0, y - dy * length / 2.0, tip_x, tip_y,
+                             ARROW_COLOUR);
+
+            // Arrow head, two barbs swept back 30 degrees either side.
+            let barb = f64::min(length * 0.35, spacing as f64 * 0.3);
+            let (sin, cos) = (0.5, f64::sqrt(0.75));
+            for &side in &[-1.0, 1.0] {
+                let bx = -(dx * cos - dy * sin * side);
+                let by = -(dy * cos + dx * sin * side);
+                canvas.draw_line(tip_x, tip_y, tip_x + bx * barb, tip_y + by * barb, ARROW_COLOUR);
+            }
+        }
+    }
+
+    canvas
+}
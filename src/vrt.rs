@@ -0,0 +1,118 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+
+//! GDAL virtual raster (VRT) files, which georeference the equirectangular
+//! map and stitch its tiles back into one dataset for GIS software.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::{OutputFormat, OutputOptions, PLANET_CIRCUMFERENCE};
+
+/// An image making up part of the map, with its position and size in
+/// pixels.
+pub struct VrtSource {
+    pub filename: String,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// The GDAL data type and colour interpretation of each band of the images
+// written in the given output format.
+fn bands(output: OutputOptions) -> Vec<(&'static str, &'static str)> {
+    let mut bands = match output.format {
+        OutputFormat::Greyscale8 | OutputFormat::OceanMask => vec![("Byte", "Gray")],
+        OutputFormat::Greyscale16 => vec![("UInt16", "Gray")],
+        OutputFormat::Colour24 | OutputFormat::Tinted(_) => {
+            vec![("Byte", "Red"), ("Byte", "Green"), ("Byte", "Blue")]
+        }
+        OutputFormat::Colour48 => vec![("UInt16", "Red"), ("UInt16", "Green"), ("UInt16", "Blue")],
+        _ => unreachable!(),
+    };
+    if output.elevation_band.is_some() {
+        let data_type = bands[0].0;
+        bands.push((data_type, "Alpha"));
+    }
+    bands
+}
+
+/// Writes a VRT describing a `width` by `height` equirectangular map made up
+/// of the given images, written in one of the greyscale, colour24, colour48,
+/// ocean-mask or tinted formats.  The map is georeferenced in latitude and
+/// longitude on a sphere the size of the planet.  Each sample is taken at the
+/// south west corner of its pixel, see `generate_rect`, so the map's extent is
+/// exactly the whole globe.
+pub fn write_vrt(filename: &str,
+                 width: usize,
+                 height: usize,
+                 sources: &[VrtSource],
+                 output: OutputOptions) {
+    let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+    let mut writer = BufWriter::new(file);
+    write_vrt_xml(&mut writer, width, height, sources, output).expect("Failed to write VRT");
+}
+
+fn write_vrt_xml<W: Write>(writer: &mut W,
+                           width: usize,
+                           height: usize,
+                           sources: &[VrtSource],
+                           output: OutputOptions)
+                           -> ::std::io::Result<()> {
+    let radius = PLANET_CIRCUMFERENCE / (2.0 * ::std::f64::consts::PI);
+    writeln!(writer,
+             "<VRTDataset rasterXSize=\"{}\" rasterYSize=\"{}\">",
+             width,
+             height)?;
+    writeln!(writer, "  <SRS>+proj=longlat +R={} +no_defs</SRS>", radius)?;
+    writeln!(writer,
+             "  <GeoTransform>-180, {}, 0, 90, 0, {}</GeoTransform>",
+             360.0 / width as f64,
+             -180.0 / height as f64)?;
+
+    for (index, &(data_type, colour_interp)) in bands(output).iter().enumerate() {
+        writeln!(writer,
+                 "  <VRTRasterBand dataType=\"{}\" band=\"{}\">",
+                 data_type,
+                 index + 1)?;
+        writeln!(writer, "    <ColorInterp>{}</ColorInterp>", colour_interp)?;
+        for source in sources {
+            writeln!(writer, "    <SimpleSource>")?;
+            writeln!(writer,
+                     "      <SourceFilename relativeToVRT=\"1\">{}</SourceFilename>",
+                     source.filename)?;
+            writeln!(writer, "      <SourceBand>{}</SourceBand>", index + 1)?;
+            writeln!(writer,
+                     "      <SrcRect xOff=\"0\" yOff=\"0\" xSize=\"{}\" ySize=\"{}\"/>",
+                     source.width,
+                     source.height)?;
+            writeln!(writer,
+                     "      <DstRect xOff=\"{}\" yOff=\"{}\" xSize=\"{}\" ySize=\"{}\"/>",
+                     source.x,
+                     source.y,
+                     source.width,
+                     source.height)?;
+            writeln!(writer, "    </SimpleSource>")?;
+        }
+        writeln!(writer, "  </VRTRasterBand>")?;
+    }
+
+    writeln!(writer, "</VRTDataset>")
+}
@@ -0,0 +1,524 @@
+// Copyright (C) 2017 Matthew Nicholls
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License
+// (COPYING.txt) for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc., 59
+// Temple Place, Suite 330, Boston, MA  02111-1307  USA
+//
+
+//! A WebP encoder producing VP8L (lossless) bitstreams.
+//!
+//! The version of `image` we depend on has no WebP encoder, so this is a
+//! small self-contained one.  It uses the subtract-green and predictor
+//! transforms followed by LZ77 and Huffman coding of the residuals, which
+//! suits the smooth gradients of elevation maps well.  The encoding is always
+//! lossless, but below quality 100 the low bits of each channel are rounded
+//! off beforehand, trading precision for size while still decoding with any
+//! WebP reader.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bits::{self, BitWriter};
+
+// Maximum width and height of a VP8L image.
+pub const MAX_DIMENSION: usize = 16384;
+
+// Predictor blocks are 1 << PREDICTOR_BITS pixels square.
+const PREDICTOR_BITS: usize = 5;
+
+// The predictor modes tried for each block: left, top and the average of
+// left and top.
+const PREDICTOR_MODES: [u32; 3] = [1, 2, 7];
+
+// Limits for backward references.
+const MIN_COPY_LENGTH: usize = 3;
+const MAX_COPY_LENGTH: usize = 4096;
+const MAX_DISTANCE: usize = (1 << 20) - 120;
+const MAX_CHAIN: usize = 16;
+const HASH_BITS: u32 = 16;
+
+const CODE_LENGTH_ORDER: [usize; 19] = [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12,
+                                        13, 14, 15];
+
+/// Writes 8-bit RGB data (top row first) as a WebP file of the given quality
+/// from 0 to 100.
+pub fn write_webp(filename: &str, rgb: &[u8], width: usize, height: usize, quality: u32) {
+    let file = File::create(Path::new(filename)).expect("Failed to create file for writing");
+    BufWriter::new(file)
+        .write_all(&encode_webp(rgb, width, height, quality))
+        .expect("Failed to write image data");
+}
+
+// Encodes the contents of a WebP file, quality 100 being lossless and lower
+// qualities rounding each channel to a multiple of a power of two, up to 32 at
+// quality 0.
+fn encode_webp(rgb: &[u8], width: usize, height: usize, quality: u32) -> Vec<u8> {
+    assert!(width >= 1 && width <= MAX_DIMENSION && height >= 1 && height <= MAX_DIMENSION);
+    assert!(rgb.len() == width * height * 3);
+    let dropped_bits = ((100 - u32::min(quality, 100)) * 5 + 99) / 100;
+
+    let quantize = |c: u8| -> u32 {
+        if dropped_bits == 0 {
+            c as u32
+        } else {
+            let half = 1 << (dropped_bits - 1);
+            u32::min(((c as u32 + half) >> dropped_bits) << dropped_bits, 255)
+        }
+    };
+
+    let mut argb: Vec<u32> = rgb.chunks(3)
+        .map(|p| 0xff000000 | quantize(p[0]) << 16 | quantize(p[1]) << 8 | quantize(p[2]))
+        .collect();
+
+    let mut bits = BitWriter::new();
+    bits.write_bits(0x2f, 8);
+    bits.write_bits((width - 1) as u32, 14);
+    bits.write_bits((height - 1) as u32, 14);
+    bits.write_bits(0, 1); // alpha_is_used
+    bits.write_bits(0, 3); // version
+
+    // Subtract-green transform.
+    bits.write_bits(1, 1);
+    bits.write_bits(2, 2);
+    for pixel in argb.iter_mut() {
+        let green = (*pixel >> 8) & 0xff;
+        let red = ((*pixel >> 16).wrapping_sub(green)) & 0xff;
+        let blue = (*pixel).wrapping_sub(green) & 0xff;
+        *pixel = (*pixel & 0xff00ff00) | red << 16 | blue;
+    }
+
+    // Predictor transform.
+    let (modes, residuals) = apply_predictor(&argb, width, height);
+    let modes_width = (width + (1 << PREDICTOR_BITS) - 1) >> PREDICTOR_BITS;
+    bits.write_bits(1, 1);
+    bits.write_bits(0, 2);
+    bits.write_bits((PREDICTOR_BITS - 2) as u32, 3);
+    bits.write_bits(0, 1); // color cache
+    write_entropy_coded_image(&mut bits, &modes, modes_width);
+
+    // No more transforms, then the main image without a color cache or meta
+    // prefix codes.
+    bits.write_bits(0, 1);
+    bits.write_bits(0, 1); // color cache
+    bits.write_bits(0, 1); // meta prefix codes
+    write_entropy_coded_image(&mut bits, &residuals, width);
+
+    let vp8l = bits.finish();
+    let padding = vp8l.len() % 2;
+
+    let mut webp = Vec::with_capacity(20 + vp8l.len() + padding);
+    webp.extend_from_slice(b"RIFF");
+    webp.extend_from_slice(&u32_le((4 + 8 + vp8l.len() + padding) as u32));
+    webp.extend_from_slice(b"WEBPVP8L");
+    webp.extend_from_slice(&u32_le(vp8l.len() as u32));
+    webp.extend_from_slice(&vp8l);
+    webp.extend_from_slice(&[0u8; 1][..padding]);
+    webp
+}
+
+fn u32_le(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
+
+fn average2(a: u32, b: u32) -> u32 {
+    (((a ^ b) & 0xfefefefe) >> 1) + (a & b)
+}
+
+fn sub_pixels(a: u32, b: u32) -> u32 {
+    let mut out = 0;
+    for shift in [0, 8, 16, 24].iter() {
+        let c = ((a >> shift) & 0xff).wrapping_sub((b >> shift) & 0xff) & 0xff;
+        out |= c << shift;
+    }
+    out
+}
+
+// Cost estimate of a residual, treating each channel as a signed value.
+fn residual_cost(residual: u32) -> u32 {
+    let mut cost = 0;
+    for shift in [0, 8, 16, 24].iter() {
+        let c = (residual >> shift) & 0xff;
+        cost += u32::min(c, 256 - c);
+    }
+    cost
+}
+
+// Chooses a predictor mode for each block, returning the image of modes (to
+// be encoded as the transform data) and the residuals.
+fn apply_predictor(argb: &[u32], width: usize, height: usize) -> (Vec<u32>, Vec<u32>) {
+    let block_size = 1 << PREDICTOR_BITS;
+    let blocks_x = (width + block_size - 1) / block_size;
+    let blocks_y = (height + block_size - 1) / block_size;
+
+    let predict = |mode: u32, x: usize, y: usize| -> u32 {
+        if x == 0 && y == 0 {
+            return 0xff000000;
+        } else if y == 0 {
+            return argb[x - 1];
+        } else if x == 0 {
+            return argb[(y - 1) * width];
+        }
+
+        let left = argb[y * width + x - 1];
+        let top = argb[(y - 1) * width + x];
+        match mode {
+            1 => left,
+            2 => top,
+            7 => average2(left, top),
+            _ => unreachable!(),
+        }
+    };
+
+    let mut modes = Vec::with_capacity(blocks_x * blocks_y);
+    let mut residuals = vec![0; width * height];
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let ys = (by * block_size)..usize::min((by + 1) * block_size, height);
+            let xs = (bx * block_size)..usize::min((bx + 1) * block_size, width);
+
+            let &mode = PREDICTOR_MODES.iter()
+                .min_by_key(|&&mode| {
+                    let mut cost = 0;
+                    for y in ys.clone() {
+                        for x in xs.clone() {
+                            cost += residual_cost(sub_pixels(argb[y * width + x],
+                                                             predict(mode, x, y)));
+                        }
+                    }
+                    cost
+                })
+                .unwrap();
+
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    residuals[y * width + x] = sub_pixels(argb[y * width + x],
+                                                          predict(mode, x, y));
+                }
+            }
+            modes.push(0xff000000 | mode << 8);
+        }
+    }
+
+    (modes, residuals)
+}
+
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u32>,
+}
+
+impl PrefixCode {
+    fn write_symbol(&self, bits: &mut BitWriter, symbol: usize) {
+        bits.write_code(self.codes[symbol], self.lengths[symbol] as u32);
+    }
+}
+
+enum Token {
+    Literal(u32),
+    // A backward reference, as a length and a distance code.
+    Copy(usize, usize),
+}
+
+// Splits a length or distance code into its prefix symbol, number of extra
+// bits and the extra bits' value.
+fn prefix_encode(value: usize) -> (usize, u32, u32) {
+    let n = value - 1;
+    if n < 4 {
+        (n, 0, 0)
+    } else {
+        let highest_bit = (0usize.leading_zeros() - n.leading_zeros() - 1) as usize;
+        let second_highest_bit = (n >> (highest_bit - 1)) & 1;
+        let extra_bits = highest_bit - 1;
+        (2 * highest_bit + second_highest_bit,
+         extra_bits as u32,
+         (n & ((1 << extra_bits) - 1)) as u32)
+    }
+}
+
+// Finds backward references using a hash chain over runs of three pixels,
+// always considering the previous pixel and the pixel above.
+fn find_backward_references(argb: &[u32], xsize: usize) -> Vec<Token> {
+    let hash = |pos: usize| -> usize {
+        let h = argb[pos].wrapping_mul(0x9e3779b1) ^
+                argb[pos + 1].wrapping_mul(0x85ebca6b) ^
+                argb[pos + 2].wrapping_mul(0xc2b2ae35);
+        (h >> (32 - HASH_BITS)) as usize
+    };
+
+    let mut head = vec![usize::max_value(); 1 << HASH_BITS];
+    let mut prev = vec![usize::max_value(); argb.len()];
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < argb.len() {
+        let max_len = usize::min(MAX_COPY_LENGTH, argb.len() - pos);
+        let match_len = |candidate: usize| -> usize {
+            let mut len = 0;
+            while len < max_len && argb[candidate + len] == argb[pos + len] {
+                len += 1;
+            }
+            len
+        };
+
+        let mut best_len = 0;
+        let mut best_dist = 0;
+        let consider = |candidate: usize, best_len: &mut usize, best_dist: &mut usize| {
+            let len = match_len(candidate);
+            if len > *best_len {
+                *best_len = len;
+                *best_dist = pos - candidate;
+            }
+        };
+
+        if pos >= 1 {
+            consider(pos - 1, &mut best_len, &mut best_dist);
+        }
+        if pos >= xsize {
+            consider(pos - xsize, &mut best_len, &mut best_dist);
+        }
+        if pos + MIN_COPY_LENGTH <= argb.len() {
+            let mut candidate = head[hash(pos)];
+            let mut chain = 0;
+            while candidate != usize::max_value() && pos - candidate <= MAX_DISTANCE &&
+                  chain < MAX_CHAIN {
+                consider(candidate, &mut best_len, &mut best_dist);
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        let step = if best_len >= MIN_COPY_LENGTH {
+            // Use the short plane codes for the previous pixel and the pixel
+            // above, otherwise the distance offset by the 120 plane codes.
+            let dist_code = if best_dist == xsize {
+                1
+            } else if best_dist == 1 {
+                2
+            } else {
+                best_dist + 120
+            };
+            tokens.push(Token::Copy(best_len, dist_code));
+            best_len
+        } else {
+            tokens.push(Token::Literal(argb[pos]));
+            1
+        };
+
+        for p in pos..(pos + step) {
+            if p + MIN_COPY_LENGTH <= argb.len() {
+                let h = hash(p);
+                prev[p] = head[h];
+                head[h] = p;
+            }
+        }
+        pos += step;
+    }
+
+    tokens
+}
+
+// Writes an image using a single group of prefix codes.
+fn write_entropy_coded_image(bits: &mut BitWriter, argb: &[u32], xsize: usize) {
+    // Alphabets in stream order: green (plus length prefixes), red, blue,
+    // alpha and distance prefixes.  Each colour channel is stored at the
+    // given shift in the pixel.
+    let alphabet_sizes = [256 + 24, 256, 256, 256, 40];
+    let shifts = [8, 16, 0, 24];
+
+    let tokens = find_backward_references(argb, xsize);
+
+    let mut freqs: Vec<Vec<u32>> = alphabet_sizes.iter().map(|&size| vec![0; size]).collect();
+    for token in &tokens {
+        match *token {
+            Token::Literal(pixel) => {
+                for i in 0..4 {
+                    freqs[i][((pixel >> shifts[i]) & 0xff) as usize] += 1;
+                }
+            }
+            Token::Copy(len, dist_code) => {
+                freqs[0][256 + prefix_encode(len).0] += 1;
+                freqs[4][prefix_encode(dist_code).0] += 1;
+            }
+        }
+    }
+
+    let codes: Vec<PrefixCode> = freqs.iter().map(|f| write_prefix_code(bits, f)).collect();
+
+    for token in &tokens {
+        match *token {
+            Token::Literal(pixel) => {
+                for i in 0..4 {
+                    codes[i].write_symbol(bits, ((pixel >> shifts[i]) & 0xff) as usize);
+                }
+            }
+            Token::Copy(len, dist_code) => {
+                let (symbol, extra_bits, extra) = prefix_encode(len);
+                codes[0].write_symbol(bits, 256 + symbol);
+                bits.write_bits(extra, extra_bits);
+                let (symbol, extra_bits, extra) = prefix_encode(dist_code);
+                codes[4].write_symbol(bits, symbol);
+                bits.write_bits(extra, extra_bits);
+            }
+        }
+    }
+}
+
+fn write_prefix_code(bits: &mut BitWriter, freqs: &[u32]) -> PrefixCode {
+    let used: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 0).collect();
+
+    // The "simple" code handles one or two symbols below 256.  A lone symbol
+    // takes no bits at all to encode.
+    if used.len() <= 2 && used.iter().all(|&s| s < 256) {
+        let mut lengths = vec![0u8; freqs.len()];
+        let symbols = if used.is_empty() { vec![0] } else { used };
+
+        bits.write_bits(1, 1);
+        bits.write_bits((symbols.len() - 1) as u32, 1);
+        if symbols[0] < 2 {
+            bits.write_bits(0, 1);
+            bits.write_bits(symbols[0] as u32, 1);
+        } else {
+            bits.write_bits(1, 1);
+            bits.write_bits(symbols[0] as u32, 8);
+        }
+        if symbols.len() == 2 {
+            bits.write_bits(symbols[1] as u32, 8);
+            lengths[symbols[0]] = 1;
+            lengths[symbols[1]] = 1;
+        }
+        let codes = bits::canonical_codes(&lengths);
+        return PrefixCode {
+            lengths: lengths,
+            codes: codes,
+        };
+    }
+
+    let lengths = bits::huffman_lengths(freqs, 15);
+
+    // Code lengths are themselves Huffman coded.  Only the literal lengths
+    // 0-15 are used, without the run-length codes.  Make sure at least two
+    // code length symbols get a code, to avoid the single symbol case.
+    let mut length_freqs = [0u32; 19];
+    for &len in &lengths {
+        length_freqs[len as usize] += 1;
+    }
+    if length_freqs.iter().filter(|&&f| f > 0).count() < 2 {
+        let unused = if length_freqs[0] == 0 { 0 } else { 1 };
+        length_freqs[unused] = 1;
+    }
+    let length_lengths = bits::huffman_lengths(&length_freqs, 7);
+    let length_codes = bits::canonical_codes(&length_lengths);
+
+    let count = CODE_LENGTH_ORDER.iter()
+        .rposition(|&s| length_lengths[s] > 0)
+        .map_or(4, |last| usize::max(last + 1, 4));
+
+    bits.write_bits(0, 1);
+    bits.write_bits((count - 4) as u32, 4);
+    for &s in &CODE_LENGTH_ORDER[..count] {
+        bits.write_bits(length_lengths[s] as u32, 3);
+    }
+    // Code lengths are given for the whole alphabet.
+    bits.write_bits(0, 1);
+    for &len in &lengths {
+        bits.write_code(length_codes[len as usize], length_lengths[len as usize] as u32);
+    }
+
+    let codes = bits::canonical_codes(&lengths);
+    PrefixCode {
+        lengths: lengths,
+        codes: codes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image_webp::WebPDecoder;
+
+    use super::*;
+
+    fn decode(webp: &[u8]) -> (usize, usize, Vec<u8>) {
+        let mut decoder = WebPDecoder::new(Cursor::new(webp)).unwrap();
+        assert!(!decoder.has_alpha());
+        let (width, height) = decoder.dimensions();
+        let mut rgb = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut rgb).unwrap();
+        (width as usize, height as usize, rgb)
+    }
+
+    fn assert_round_trip(rgb: &[u8], width: usize, height: usize) {
+        let (w, h, decoded) = decode(&encode_webp(rgb, width, height, 100));
+        assert_eq!((w, h), (width, height));
+        assert!(decoded == rgb);
+    }
+
+    // Pseudo-random samples, which leave little for the predictor and LZ77 to
+    // find.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flat_image_round_trips() {
+        assert_round_trip(&[0x40, 0x80, 0xc0].repeat(64 * 48), 64, 48);
+    }
+
+    #[test]
+    fn gradient_round_trips() {
+        let (width, height) = (100, 70);
+        let mut rgb = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                rgb.extend_from_slice(&[(x * 2) as u8, (y * 3) as u8, (x + y) as u8]);
+            }
+        }
+        assert_round_trip(&rgb, width, height);
+    }
+
+    #[test]
+    fn noise_round_trips() {
+        assert_round_trip(&noise(80 * 60 * 3), 80, 60);
+    }
+
+    #[test]
+    fn single_pixel_round_trips() {
+        assert_round_trip(&[1, 2, 3], 1, 1);
+    }
+
+    #[test]
+    fn non_square_images_round_trip() {
+        // Neither side is a multiple of the predictor block size.
+        assert_round_trip(&noise(37 * 5 * 3), 37, 5);
+        assert_round_trip(&noise(3 * 70 * 3), 3, 70);
+    }
+
+    #[test]
+    fn lower_quality_rounds_each_channel() {
+        let rgb = noise(40 * 30 * 3);
+        let (_, _, decoded) = decode(&encode_webp(&rgb, 40, 30, 0));
+        for (&original, &value) in rgb.iter().zip(decoded.iter()) {
+            assert_eq!(value % 32, if value == 255 { 31 } else { 0 });
+            assert!((original as i32 - value as i32).abs() <= 16);
+        }
+    }
+}